@@ -22,6 +22,7 @@ pub struct FxAssets {
     pub laser_hit_vfx_small: Handle<EffectAsset>,
     pub ship_destroy_vfx: Handle<EffectAsset>,
     pub ship_damage_vfx: Handle<EffectAsset>,
+    pub shield_hit_vfx: Handle<EffectAsset>,
 }
 
 #[derive(Resource, Default)]
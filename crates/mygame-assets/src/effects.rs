@@ -18,6 +18,9 @@ pub(crate) fn register_fx(
 
     let effect_handle = effects.add(ship_damage_effect());
     fx_assets.ship_damage_vfx = effect_handle;
+
+    let effect_handle = effects.add(shield_hit_effect());
+    fx_assets.shield_hit_vfx = effect_handle;
 }
 
 fn ship_destroy_effect() -> EffectAsset {
@@ -212,6 +215,54 @@ fn laser_hit_effect_large() -> EffectAsset {
         })
 }
 
+fn shield_hit_effect() -> EffectAsset {
+    // Define a color gradient from blue to transparent black
+    let mut gradient = Gradient::new();
+    gradient.add_key(0.0, Vec4::new(0.2, 0.6, 1., 0.8));
+    gradient.add_key(1.0, Vec4::splat(0.0));
+
+    let mut module = Module::default();
+
+    // Spawn the particles over a shell around the hit, so it reads as a ripple on the
+    // shield rather than sparks off the hull
+    let init_pos = SetPositionSphereModifier {
+        center: module.lit(Vec3::ZERO),
+        radius: module.lit(1.5),
+        dimension: ShapeDimension::Surface,
+    };
+
+    let vel_mod = SetVelocitySphereModifier {
+        center: module.lit(Vec3::ZERO),
+        speed: module.lit(2.),
+    };
+
+    let lifetime = module.lit(0.5);
+    let init_lifetime = SetAttributeModifier::new(Attribute::LIFETIME, lifetime);
+
+    let scale = module.lit(Vec3::splat(0.15));
+    let init_scale = SetAttributeModifier::new(Attribute::SIZE3, scale);
+
+    EffectAsset::new(
+        // Maximum number of particles alive at a time
+        32768,
+        SpawnerSettings::once(80.0.into()),
+        module,
+    )
+    .with_name("Shield Hit")
+    .init(init_pos)
+    .init(init_lifetime)
+    .init(init_scale)
+    .init(vel_mod)
+    .render(ColorOverLifetimeModifier {
+        gradient,
+        ..default()
+    })
+    .render(OrientModifier {
+        mode: OrientMode::ParallelCameraDepthPlane,
+        ..default()
+    })
+}
+
 fn laser_hit_effect_small() -> EffectAsset {
     // Define a color gradient from green to transparent black
     let mut gradient = Gradient::new();
@@ -12,7 +12,10 @@ use bevy_hanabi::HanabiPlugin;
 use images::hemispherical_gradient;
 use materials::{GradientMaterial, SharedMaterialPlugin, SkyboxMaterial};
 use meshes::skybox_mesh;
-use mygame_protocol::message::Level;
+use mygame_protocol::{
+    component::{PickupKind, Team},
+    message::Level,
+};
 
 pub mod assets;
 mod effects;
@@ -40,6 +43,10 @@ impl Plugin for AssetPlugin {
             .init_resource::<GlobalAssets>()
             .init_resource::<FxAssets>()
             .register_type::<Geometry>()
+            .register_type::<SpawnPoint>()
+            .register_type::<FlagBase>()
+            .register_type::<Checkpoint>()
+            .register_type::<PickupSpawn>()
             .register_type::<NeedsRigidBody>();
 
         // certain assets and asset processing steps require that rendering is enabled, we are using UiPlugin as a cheat-y way to check
@@ -88,6 +95,30 @@ pub struct CurrentLevel(pub Level);
 #[reflect(Component)]
 pub struct Geometry;
 
+/// Tag component for the empty nodes in a level named "SpawnPoint ...", where ships may spawn
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct SpawnPoint;
+
+/// Where a team's flag sits in capture the flag, from the nodes named "FlagBase Red" and "FlagBase Blue"
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct FlagBase(pub Team);
+
+/// A ring ships race through in order, from the nodes named "Checkpoint 0", "Checkpoint 1" and so on.
+/// Checkpoint 0 is the start and finish line. Ships pass through in the direction of its forward (-Z).
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Checkpoint(pub u32);
+
+/// How far from the middle of a checkpoint a ship may pass and still count
+pub const CHECKPOINT_RADIUS: f32 = 8.0;
+
+/// Where the server puts out a pickup, from the nodes named "Pickup Repair", "Pickup Shield (1)" and so on
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct PickupSpawn(pub PickupKind);
+
 /// When CurrentLevel changes, load the assets required.
 /// Queue the resultant Handles to be polled for completion in `check_asset_loading`
 fn on_level_change(
@@ -161,6 +192,8 @@ pub enum CollisionMask {
     Ship,
     Environment,
     Projectile,
+    Flag,
+    Pickup,
 }
 
 #[derive(Component, Reflect)]
@@ -184,12 +217,66 @@ fn postprocess_assets(
             // that contains a World we can mutate freely
             if let Some(scene) = scenes.get_mut(&level_assets.example_level) {
                 let mut entities_to_process = Vec::new();
+                let mut spawn_points = Vec::new();
+                let mut flag_bases = Vec::new();
+                let mut checkpoints = Vec::new();
+                let mut pickup_spawns = Vec::new();
 
                 for entity_ref in scene.world.iter_entities() {
                     let entity = entity_ref.id();
                     if let Some(mesh_handle) = scene.world.get::<Mesh3d>(entity) {
                         entities_to_process.push((entity, mesh_handle.clone()));
                     }
+
+                    if scene
+                        .world
+                        .get::<Name>(entity)
+                        .is_some_and(|name| name.starts_with("SpawnPoint"))
+                    {
+                        spawn_points.push(entity);
+                    }
+
+                    if let Some(name) = scene.world.get::<Name>(entity) {
+                        if let Some(team) = Team::ALL
+                            .into_iter()
+                            .find(|team| name.as_str() == format!("FlagBase {}", team.name()))
+                        {
+                            flag_bases.push((entity, team));
+                        }
+
+                        if let Some(index) = name
+                            .as_str()
+                            .strip_prefix("Checkpoint ")
+                            .and_then(|index| index.parse().ok())
+                        {
+                            checkpoints.push((entity, index));
+                        }
+
+                        if let Some(kind) = name.as_str().strip_prefix("Pickup ").and_then(|rest| {
+                            let kind_name = rest.split(' ').next().unwrap_or(rest);
+                            PickupKind::ALL
+                                .into_iter()
+                                .find(|kind| kind.name() == kind_name)
+                        }) {
+                            pickup_spawns.push((entity, kind));
+                        }
+                    }
+                }
+
+                for entity in spawn_points {
+                    scene.world.entity_mut(entity).insert(SpawnPoint);
+                }
+
+                for (entity, team) in flag_bases {
+                    scene.world.entity_mut(entity).insert(FlagBase(team));
+                }
+
+                for (entity, index) in checkpoints {
+                    scene.world.entity_mut(entity).insert(Checkpoint(index));
+                }
+
+                for (entity, kind) in pickup_spawns {
+                    scene.world.entity_mut(entity).insert(PickupSpawn(kind));
                 }
 
                 for (entity, mesh_handle) in entities_to_process {
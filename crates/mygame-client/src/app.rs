@@ -1,3 +1,4 @@
+use bevy::app::PluginGroupBuilder;
 use bevy::asset::AssetMetaCheck;
 use bevy::{
     log::{Level, LogPlugin},
@@ -12,8 +13,11 @@ use mygame_common::CommonPlugin;
 use mygame_render::RenderPlugin;
 
 use crate::crosshair::CrosshairPlugin;
+use crate::death_cam::DeathCamPlugin;
 use crate::game_state::{GameLifecyclePlugin, GameState};
 use crate::input::InputPlugin;
+use crate::settings::SettingsPlugin;
+use crate::spectator::SpectatorPlugin;
 use crate::throwaway::ThrowawayPlugin;
 use crate::{
     interpolation::InterpolationPlugin, network::NetworkPlugin, replication::ReplicationPlugin,
@@ -21,6 +25,8 @@ use crate::{
 };
 use mygame_common::LaunchConfigurations;
 
+#[cfg(not(target_family = "wasm"))]
+use crate::discovery::LanDiscoveryPlugin;
 #[cfg(feature = "host")]
 use crate::host::HostPlugin;
 #[cfg(feature = "host")]
@@ -31,21 +37,26 @@ use lightyear::prelude::client::IoConfig;
 #[derive(Resource)]
 pub struct AssetPath(pub String);
 
+/// Bevy's DefaultPlugins, configured the way every windowed client app wants them
+pub(crate) fn default_plugins(asset_path: String) -> PluginGroupBuilder {
+    DefaultPlugins.build().set(AssetPlugin {
+        file_path: asset_path,
+        meta_check: AssetMetaCheck::Never,
+        ..default()
+    }).set(LogPlugin {
+        level: Level::INFO,
+        filter: "wgpu=error,bevy_render=info,bevy_ecs=info,offset_allocator=error,naga=warn,bevy_hanabi=error".into(),
+        ..default()
+    })
+}
+
 fn build_core_client_app(
     app: &mut App,
     client_remote_config: ClientConfig,
     asset_path: String,
 ) -> &mut App {
     app.add_plugins((
-        DefaultPlugins.build().set(AssetPlugin {
-            file_path: asset_path.clone(),
-            meta_check: AssetMetaCheck::Never,
-            ..default()
-        }).set(LogPlugin {
-            level: Level::INFO,
-            filter: "wgpu=error,bevy_render=info,bevy_ecs=info,offset_allocator=error,naga=warn,bevy_hanabi=error".into(),
-            ..default()
-        }),
+        default_plugins(asset_path.clone()),
         ClientPlugins {
             config: client_remote_config.clone(),
         },
@@ -58,8 +69,14 @@ fn build_core_client_app(
         InputPlugin,
         InterpolationPlugin,
         CrosshairPlugin,
-        ThrowawayPlugin
-    ));
+        ThrowawayPlugin,
+        SettingsPlugin,
+    ))
+    .add_plugins((DeathCamPlugin, SpectatorPlugin));
+
+    // Browsers can't broadcast
+    #[cfg(not(target_family = "wasm"))]
+    app.add_plugins(LanDiscoveryPlugin);
 
     app.insert_resource(AssetPath(asset_path));
     app.enable_state_scoped_entities::<GameState>();
@@ -1,7 +1,10 @@
-use bevy::{prelude::*, render::{render_resource::{AsBindGroup, ShaderRef}, view::RenderLayers}, window::{CursorGrabMode, PrimaryWindow}};
+use bevy::{color::palettes::tailwind::SLATE_400, prelude::*, render::{render_resource::{AsBindGroup, ShaderRef}, view::RenderLayers}, window::{CursorGrabMode, PrimaryWindow}};
+use leafwing_input_manager::prelude::ActionState;
+use mygame_common::ship::{PITCH_RATE, TURN_RATE};
+use mygame_protocol::input::NetworkedInput;
 use mygame_render::camera::MainCamera;
 
-use crate::{game_state::GameState, replication::LocalPlayer, ui::{respawn_menu::RespawnMenuState, system_menu::SystemMenuState}};
+use crate::{game_state::GameState, input::AimDirection, replication::LocalPlayer, ui::{respawn_menu::RespawnMenuState, system_menu::SystemMenuState}};
 
 pub (crate) struct CrosshairPlugin;
 
@@ -9,12 +12,17 @@ impl Plugin for CrosshairPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_systems(Update, spawn_crosshair_meshes)
-            .add_systems(OnEnter(GameState::Playing), (lock_mouse, spawn_crosshair_camera))
+            .add_systems(
+                Update,
+                (aim_crosshair_meshes, update_aim_reticle).run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnEnter(GameState::Playing), (lock_mouse, spawn_crosshair_camera, spawn_aim_reticle))
             .add_systems(OnExit(GameState::Playing), unlock_mouse)
             .add_systems(OnEnter(SystemMenuState::Open), unlock_mouse)
-            .add_systems(OnExit(SystemMenuState::Open), lock_mouse)
+            // Only Playing keeps the mouse locked, spectators need it for the UI
+            .add_systems(OnExit(SystemMenuState::Open), lock_mouse.run_if(in_state(GameState::Playing)))
             .add_systems(OnEnter(RespawnMenuState::Open), unlock_mouse)
-            .add_systems(OnExit(RespawnMenuState::Open), lock_mouse)
+            .add_systems(OnExit(RespawnMenuState::Open), lock_mouse.run_if(in_state(GameState::Playing)))
             .add_plugins(
                 MaterialPlugin::<CrosshairMaterial>::default(),
             );
@@ -109,3 +117,113 @@ fn spawn_crosshair_meshes(
             });
     }
 }
+
+/// How far ahead, in seconds of turning at the current Aim input, each crosshair looks.
+/// The crosshairs slide toward where the ship is about to point.
+const CROSSHAIR_NEAR_LOOKAHEAD: f32 = 0.25;
+const CROSSHAIR_FAR_LOOKAHEAD: f32 = 0.5;
+
+fn aim_crosshair_meshes(
+    q_local_player: Query<(&Transform, &ActionState<NetworkedInput>), With<LocalPlayer>>,
+    mut q_crosshairs: Query<
+        (&mut Transform, &ChildOf, Has<CrosshairNear>),
+        (Or<(With<CrosshairNear>, With<CrosshairFar>)>, Without<LocalPlayer>),
+    >,
+) {
+    for (mut crosshair_transform, child_of, is_near) in &mut q_crosshairs {
+        let Ok((player_transform, action_state)) = q_local_player.get(child_of.parent()) else {
+            continue;
+        };
+
+        let aim = action_state.axis_pair(&NetworkedInput::Aim);
+        let (distance, lookahead) = if is_near {
+            (CROSSHAIR_NEAR_DISTANCE, CROSSHAIR_NEAR_LOOKAHEAD)
+        } else {
+            (CROSSHAIR_FAR_DISTANCE, CROSSHAIR_FAR_LOOKAHEAD)
+        };
+
+        // Same yaw-around-world-up, pitch-around-right convention as move_ship
+        let forward = player_transform.forward().as_vec3();
+        let yaw = Quat::from_rotation_y(-aim.x * TURN_RATE * lookahead);
+        let forward_after_yaw = yaw * forward;
+        let right = forward_after_yaw.cross(Vec3::Y).normalize_or(Vec3::X);
+        let pitch = Quat::from_axis_angle(right, aim.y * PITCH_RATE * lookahead);
+        let aimed_forward = pitch * forward_after_yaw;
+
+        let world_offset = aimed_forward * distance + player_transform.up() * CROSSHAIR_VERTICAL_OFFSET;
+        crosshair_transform.translation = player_transform.rotation.inverse() * world_offset;
+    }
+}
+
+/// Radius, in pixels, of the circle the virtual cursor moves in
+const AIM_RETICLE_RADIUS: f32 = 60.0;
+const AIM_RETICLE_CURSOR_SIZE: f32 = 8.0;
+
+#[derive(Component)]
+struct AimReticle;
+
+#[derive(Component)]
+struct AimReticleCursor;
+
+fn spawn_aim_reticle(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            Pickable::IGNORE,
+            AimReticle,
+            StateScoped(GameState::Playing),
+        ))
+        .with_children(|child_builder| {
+            child_builder
+                .spawn((
+                    Node {
+                        width: Val::Px(AIM_RETICLE_RADIUS * 2.0),
+                        height: Val::Px(AIM_RETICLE_RADIUS * 2.0),
+                        border: UiRect::all(Val::Px(1.0)),
+                        ..default()
+                    },
+                    BorderColor(SLATE_400.with_alpha(0.3).into()),
+                    BorderRadius::MAX,
+                    Pickable::IGNORE,
+                ))
+                .with_children(|ring_builder| {
+                    ring_builder.spawn((
+                        Node {
+                            position_type: PositionType::Absolute,
+                            width: Val::Px(AIM_RETICLE_CURSOR_SIZE),
+                            height: Val::Px(AIM_RETICLE_CURSOR_SIZE),
+                            ..default()
+                        },
+                        BackgroundColor(Color::WHITE.with_alpha(0.8)),
+                        BorderRadius::MAX,
+                        Pickable::IGNORE,
+                        AimReticleCursor,
+                    ));
+                });
+        });
+}
+
+fn update_aim_reticle(
+    aim_direction: Res<AimDirection>,
+    primary_window: Single<&Window, With<PrimaryWindow>>,
+    mut reticle_visibility: Single<&mut Visibility, With<AimReticle>>,
+    mut cursor_node: Single<&mut Node, With<AimReticleCursor>>,
+) {
+    // Menus free the mouse, and the reticle would just be in the way
+    **reticle_visibility = if primary_window.cursor_options.grab_mode == CursorGrabMode::None {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+
+    // The ring has a 1px border, which offsets absolute children
+    let center = AIM_RETICLE_RADIUS - 1.0 - AIM_RETICLE_CURSOR_SIZE / 2.0;
+    cursor_node.left = Val::Px(center + aim_direction.cursor.x * AIM_RETICLE_RADIUS);
+    cursor_node.top = Val::Px(center - aim_direction.cursor.y * AIM_RETICLE_RADIUS);
+}
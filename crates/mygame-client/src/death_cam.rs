@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use lightyear::prelude::{ClientReceiveMessage, client::InterpolationSet};
+use mygame_common::Rendered;
+use mygame_protocol::{
+    component::{Bot, Health, Player, Ship},
+    message::{Killer, ServerShipDestroyed},
+};
+use mygame_render::camera::MainCamera;
+
+use crate::{game_state::GameState, replication::LocalPlayer};
+
+/// After the local ship is destroyed, the camera orbits the explosion for a moment,
+/// then pans over to the ship that destroyed it and watches it until respawn.
+pub(crate) struct DeathCamPlugin;
+
+impl Plugin for DeathCamPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(end_death_cam)
+            .add_systems(OnExit(GameState::Playing), remove_death_cam)
+            .add_systems(
+                Update,
+                (on_ship_destroyed, tick_respawn_timer)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                PostUpdate,
+                move_death_cam
+                    .run_if(in_state(GameState::Playing).and(resource_exists::<DeathCam>))
+                    .after(InterpolationSet::VisualInterpolation)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+const ORBIT_DURATION: Duration = Duration::from_millis(2500);
+const PAN_DURATION: Duration = Duration::from_secs(1);
+const ORBIT_RADIUS: f32 = 15.0;
+const ORBIT_HEIGHT: f32 = 5.0;
+/// Radians per second
+const ORBIT_SPEED: f32 = 0.6;
+const KILLER_FOLLOW_DISTANCE: f32 = 10.0;
+
+/// Present from the moment the server says our ship was destroyed until we respawn
+#[derive(Resource)]
+pub(crate) struct DeathCam {
+    pub position: Vec3,
+    pub killer: Option<Killer>,
+    pub respawn_timer: Timer,
+    elapsed: Duration,
+    /// Where the orbit started, so it picks up from wherever the camera was
+    orbit_start_angle: f32,
+    /// The camera at the moment it started panning to the killer
+    pan_from: Option<Transform>,
+}
+
+impl DeathCam {
+    pub fn can_respawn(&self) -> bool {
+        self.respawn_timer.finished()
+    }
+
+    pub fn killer_name(&self) -> Option<String> {
+        self.killer.map(|killer| match killer {
+            Killer::Player(client_id) => format!("Player {}", client_id),
+            Killer::Bot(_) => String::from("a bot"),
+        })
+    }
+}
+
+/// The killer's ship, if it's still around
+pub(crate) fn find_killer<'a>(
+    killer: Killer,
+    q_ships: &'a Query<(&Transform, Option<&Player>, Option<&Bot>, Option<&Health>), (Rendered, With<Ship>)>,
+) -> Option<(&'a Transform, Option<&'a Health>)> {
+    q_ships
+        .iter()
+        .find(|(_, player, bot, _)| match killer {
+            Killer::Player(client_id) => player.is_some_and(|player| player.0 == client_id),
+            Killer::Bot(bot_id) => bot.is_some_and(|bot| bot.0 == bot_id),
+        })
+        .map(|(transform, _, _, health)| (transform, health))
+}
+
+fn on_ship_destroyed(
+    mut commands: Commands,
+    mut ship_destroyed_events: EventReader<ClientReceiveMessage<ServerShipDestroyed>>,
+    q_main_cameras: Query<&Transform, With<MainCamera>>,
+) {
+    for ev in ship_destroyed_events.read() {
+        let orbit_start_angle = q_main_cameras
+            .iter()
+            .next()
+            .map(|camera| {
+                let offset = camera.translation - ev.message.position;
+                offset.z.atan2(offset.x)
+            })
+            .unwrap_or_default();
+
+        commands.insert_resource(DeathCam {
+            position: ev.message.position,
+            killer: ev.message.killer,
+            respawn_timer: Timer::new(ev.message.respawn_delay, TimerMode::Once),
+            elapsed: Duration::ZERO,
+            orbit_start_angle,
+            pan_from: None,
+        });
+    }
+}
+
+fn tick_respawn_timer(time: Res<Time>, death_cam: Option<ResMut<DeathCam>>) {
+    if let Some(mut death_cam) = death_cam {
+        let delta = time.delta();
+        death_cam.elapsed += delta;
+        death_cam.respawn_timer.tick(delta);
+    }
+}
+
+fn orbit_transform(death_cam: &DeathCam) -> Transform {
+    let angle = death_cam.orbit_start_angle + death_cam.elapsed.as_secs_f32() * ORBIT_SPEED;
+    let offset = Vec3::new(angle.cos() * ORBIT_RADIUS, ORBIT_HEIGHT, angle.sin() * ORBIT_RADIUS);
+
+    Transform::from_translation(death_cam.position + offset).looking_at(death_cam.position, Vec3::Y)
+}
+
+/// Behind and above the killer, looking at them
+fn chase_transform(killer: &Transform) -> Transform {
+    let translation = killer.translation - killer.forward() * KILLER_FOLLOW_DISTANCE
+        + Vec3::Y * (KILLER_FOLLOW_DISTANCE * 0.3);
+
+    Transform::from_translation(translation).looking_at(killer.translation, Vec3::Y)
+}
+
+fn move_death_cam(
+    mut death_cam: ResMut<DeathCam>,
+    q_ships: Query<(&Transform, Option<&Player>, Option<&Bot>, Option<&Health>), (Rendered, With<Ship>)>,
+    mut q_main_cameras: Query<&mut Transform, (With<MainCamera>, Without<Ship>)>,
+) {
+    let Ok(mut camera_transform) = q_main_cameras.single_mut() else {
+        return;
+    };
+
+    let killer_transform = death_cam
+        .killer
+        .and_then(|killer| find_killer(killer, &q_ships))
+        .map(|(transform, _)| *transform);
+
+    // Nobody to pan to (a bot that's gone, or we crashed into something), so just keep orbiting
+    let Some(killer_transform) = killer_transform.filter(|_| death_cam.elapsed >= ORBIT_DURATION) else {
+        *camera_transform = orbit_transform(&death_cam);
+        return;
+    };
+
+    let pan_from = *death_cam.pan_from.get_or_insert(*camera_transform);
+    let chase = chase_transform(&killer_transform);
+
+    let pan_progress = ((death_cam.elapsed - ORBIT_DURATION).as_secs_f32() / PAN_DURATION.as_secs_f32()).clamp(0.0, 1.0);
+    let t = pan_progress * pan_progress * (3.0 - 2.0 * pan_progress);
+
+    camera_transform.translation = pan_from.translation.lerp(chase.translation, t);
+    camera_transform.rotation = pan_from.rotation.slerp(chase.rotation, t);
+}
+
+fn end_death_cam(_trigger: Trigger<OnAdd, LocalPlayer>, mut commands: Commands) {
+    commands.remove_resource::<DeathCam>();
+}
+
+fn remove_death_cam(mut commands: Commands) {
+    commands.remove_resource::<DeathCam>();
+}
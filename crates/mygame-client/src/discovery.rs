@@ -0,0 +1,145 @@
+use std::{
+    io::ErrorKind,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use mygame_protocol::discovery::{DISCOVERY_PORT, DiscoveryProbe, DiscoveryReply, decode, encode};
+
+use crate::game_state::GameState;
+
+/// Finds games on the local network while the main menu is open, by broadcasting a probe every
+/// few seconds and listing whichever servers answer. Needs no internet connection at all.
+pub(crate) struct LanDiscoveryPlugin;
+
+impl Plugin for LanDiscoveryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LanGames>()
+            .add_systems(OnEnter(GameState::MainMenu), start_discovery)
+            .add_systems(OnExit(GameState::MainMenu), stop_discovery)
+            .add_systems(
+                Update,
+                (send_probes, receive_replies, forget_stale_games)
+                    .chain()
+                    .run_if(resource_exists::<LanProbe>),
+            );
+    }
+}
+
+/// How often the local network is asked for games
+const PROBE_INTERVAL: Duration = Duration::from_secs(2);
+/// A game that misses this many probes in a row is taken off the list
+const MISSED_PROBES_BEFORE_STALE: u32 = 3;
+
+/// A server on the local network that answered the last few probes
+#[derive(Clone, Debug)]
+pub struct LanGame {
+    pub addr: SocketAddr,
+    pub reply: DiscoveryReply,
+    last_seen: Duration,
+}
+
+/// Every game found on the local network, in the order they were found
+#[derive(Resource, Default)]
+pub struct LanGames(pub Vec<LanGame>);
+
+#[derive(Resource)]
+struct LanProbe {
+    socket: UdpSocket,
+    timer: Timer,
+}
+
+fn start_discovery(mut commands: Commands) {
+    let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)) {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!("unable to look for LAN games: {}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = socket
+        .set_broadcast(true)
+        .and_then(|_| socket.set_nonblocking(true))
+    {
+        warn!("unable to look for LAN games: {}", e);
+        return;
+    }
+
+    let mut timer = Timer::new(PROBE_INTERVAL, TimerMode::Repeating);
+    // Probe straight away rather than a whole interval after the menu opens
+    timer.set_elapsed(PROBE_INTERVAL);
+
+    commands.insert_resource(LanProbe { socket, timer });
+}
+
+fn stop_discovery(mut commands: Commands, mut lan_games: ResMut<LanGames>) {
+    commands.remove_resource::<LanProbe>();
+    lan_games.0.clear();
+}
+
+fn send_probes(time: Res<Time>, mut lan_probe: ResMut<LanProbe>) {
+    lan_probe.timer.tick(time.delta());
+    if !lan_probe.timer.just_finished() {
+        return;
+    }
+
+    let Some(probe) = encode(&DiscoveryProbe) else {
+        return;
+    };
+
+    // The broadcast finds every other machine, servers listening on loopback only hear the
+    // second probe
+    for target in [Ipv4Addr::BROADCAST, Ipv4Addr::LOCALHOST] {
+        if let Err(e) = lan_probe.socket.send_to(&probe, (target, DISCOVERY_PORT)) {
+            debug!("unable to send a discovery probe to {}: {}", target, e);
+        }
+    }
+}
+
+fn receive_replies(time: Res<Time>, lan_probe: Res<LanProbe>, mut lan_games: ResMut<LanGames>) {
+    let mut buffer = [0; 512];
+
+    loop {
+        let (len, from) = match lan_probe.socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => return,
+            Err(e) => {
+                warn!("unable to read discovery replies: {}", e);
+                return;
+            }
+        };
+
+        let Some(reply) = decode::<DiscoveryReply>(&buffer[..len]) else {
+            continue;
+        };
+
+        let addr = SocketAddr::new(from.ip(), reply.udp_port);
+
+        // Only a change in what's shown should count as a change to the list
+        match lan_games.0.iter().position(|game| game.addr == addr) {
+            Some(index) => {
+                lan_games.bypass_change_detection().0[index].last_seen = time.elapsed();
+                if lan_games.0[index].reply != reply {
+                    lan_games.0[index].reply = reply;
+                }
+            }
+            None => lan_games.0.push(LanGame {
+                addr,
+                reply,
+                last_seen: time.elapsed(),
+            }),
+        }
+    }
+}
+
+fn forget_stale_games(time: Res<Time>, mut lan_games: ResMut<LanGames>) {
+    let stale = time
+        .elapsed()
+        .saturating_sub(PROBE_INTERVAL * MISSED_PROBES_BEFORE_STALE);
+
+    if lan_games.0.iter().any(|game| game.last_seen < stale) {
+        lan_games.0.retain(|game| game.last_seen >= stale);
+    }
+}
@@ -1,10 +1,13 @@
 use bevy::prelude::*;
 use lightyear::prelude::{
-    ClientConnectionManager, Replicated,
-    client::{ClientCommandsExt, Confirmed, Predicted},
+    PreSpawned, Replicated,
+    client::{Confirmed, Predicted},
 };
-use mygame_assets::Geometry;
-use mygame_protocol::message::{ClientHostRequestShutdown, Reliable};
+use mygame_assets::{CurrentLevel, LevelState};
+use mygame_common::level::LevelRoot;
+use mygame_protocol::message::Level;
+
+use crate::ui::{respawn_menu::RespawnMenuState, system_menu::SystemMenuState};
 
 #[derive(States, Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub enum GameState {
@@ -15,39 +18,50 @@ pub enum GameState {
     ConnectingRemote, // Connection request sent to the server,
     #[cfg(feature = "host")]
     ConnectingSelf, // Connection request sent to the LOCAL server
+    Lobby,            // Connected to a hosted server, waiting for everyone to ready up
     Loading,          // Connected and server told us to load something
     Spawning,         // Loaded the assets, now wait for the Player to be replicated
     Playing,          // Player exists and we can give control to the client
+    Spectating,       // In the level without a ship, watching everyone else
+    Reconnecting,     // Lost the remote server mid-session, retrying the connection
 }
 
 pub (crate) struct GameLifecyclePlugin;
 
 impl Plugin for GameLifecyclePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnExit(GameState::Playing), cleanup_on_exit_to_menu);
+        app.add_systems(OnEnter(GameState::MainMenu), teardown_session)
+            .add_systems(OnEnter(GameState::Reconnecting), teardown_session);
 
         app.init_state::<GameState>();
     }
 }
 
-fn cleanup_on_exit_to_menu(
+/// Put the client back the way it was before it connected to anything.
+/// Every networked entity and the level are despawned, and every state machine is reset.
+fn teardown_session(
     mut commands: Commands,
     q_everything: Query<
         Entity,
         Or<(
-            With<Geometry>,
+            With<LevelRoot>,
             With<Predicted>,
             With<Confirmed>,
             With<Replicated>,
+            With<PreSpawned>,
         )>,
     >,
-    mut client_manager: ResMut<ClientConnectionManager>,
+    mut current_level: ResMut<CurrentLevel>,
 ) {
-    // Irrelevant if this fails since we're disconnecting + closing the server
-    let _ = client_manager
-        .send_message::<Reliable, ClientHostRequestShutdown>(&ClientHostRequestShutdown);
-
     for thing in &q_everything {
         commands.entity(thing).despawn_recursive()
     }
+
+    // Bypass change detection, otherwise the asset loader would go and "load" the Void level.
+    // The next ServerWelcome marks it changed again, even if it names the same level as before.
+    current_level.bypass_change_detection().0 = Level::Void;
+
+    commands.set_state(LevelState::Unloaded);
+    commands.set_state(RespawnMenuState::Closed);
+    commands.set_state(SystemMenuState::Closed);
 }
@@ -1,29 +1,164 @@
 use bevy::prelude::*;
 use crossbeam_channel::{Receiver, Sender};
 use lightyear::prelude::client::{Authentication, NetConfig};
-use lightyear::prelude::server::ServerTransport;
+use lightyear::prelude::server::{NetConfig as ServerNetConfig, ServerTransport};
 use lightyear::prelude::*;
 use lightyear::server::config::ServerConfig;
 use lightyear::{client::config::ClientConfig, prelude::client::ClientConnection};
 use mygame_common::LaunchConfigurations;
+use serde::{Deserialize, Serialize};
 use std::{
-    net::{Ipv4Addr, SocketAddr},
+    net::{IpAddr, Ipv4Addr, SocketAddr},
     thread,
     time::Duration,
 };
 
 use crate::app::{AssetPath};
 use crate::game_state::GameState;
+use crate::persistence;
 use mygame_server::app::{ServerMode, build_server_app};
 
 pub (crate) struct HostPlugin;
 
 impl Plugin for HostPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Hosting), on_client_begin_hosting);
+        app.add_systems(Startup, load_host_ports)
+            .add_systems(
+                Update,
+                on_client_begin_hosting
+                    .run_if(in_state(GameState::Hosting).and(previous_server_stopped)),
+            )
+            .add_systems(OnEnter(GameState::MainMenu), forget_host_info);
     }
 }
 
+const HOST_PORTS_KEY: &str = "host_ports";
+
+/// The ports a hosted server listens on. Picked in the main menu and remembered between runs.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct HostPorts {
+    pub udp: u16,
+    pub webtransport: u16,
+}
+
+/// What other players need to join the server this client is hosting
+#[derive(Resource, Clone, Debug)]
+pub struct HostInfo {
+    pub ports: HostPorts,
+    /// This machine's addresses, other than loopback
+    pub lan_addresses: Vec<IpAddr>,
+    /// Browsers need it to trust the server's self signed certificate
+    pub certificate_digest: Option<String>,
+}
+
+/// The thread running the server this client last hosted. It counts down for a few seconds
+/// after the host leaves, and holds onto its ports until it's done.
+#[derive(Resource)]
+pub(crate) struct HostedServer(thread::JoinHandle<()>);
+
+impl HostedServer {
+    pub(crate) fn is_running(&self) -> bool {
+        !self.0.is_finished()
+    }
+}
+
+/// Hosting again has to wait for the last hosted server to let go of its ports
+fn previous_server_stopped(hosted_server: Option<Res<HostedServer>>) -> bool {
+    hosted_server.is_none_or(|hosted_server| !hosted_server.is_running())
+}
+
+/// The ports the launcher configured the hosted server with
+fn configured_ports(config: &ServerConfig) -> HostPorts {
+    let mut ports = HostPorts {
+        udp: 0,
+        webtransport: 0,
+    };
+
+    for net_config in &config.net {
+        let ServerNetConfig::Netcode { io, .. } = net_config;
+
+        match &io.transport {
+            ServerTransport::UdpSocket(addr) => ports.udp = addr.port(),
+            ServerTransport::WebTransportServer { server_addr, .. } => {
+                ports.webtransport = server_addr.port()
+            }
+            _ => {}
+        }
+    }
+
+    ports
+}
+
+/// Listen on every interface, on the ports the host picked, so players on the LAN can join.
+/// The host's own client still goes through the in-memory channels.
+fn with_host_ports(mut config: ServerConfig, ports: HostPorts) -> ServerConfig {
+    for net_config in &mut config.net {
+        let ServerNetConfig::Netcode { io, .. } = net_config;
+
+        match &mut io.transport {
+            ServerTransport::UdpSocket(addr) => {
+                *addr = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), ports.udp);
+            }
+            ServerTransport::WebTransportServer { server_addr, .. } => {
+                *server_addr = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), ports.webtransport);
+            }
+            // Not picked by the host, only browsers without WebTransport use it
+            ServerTransport::WebSocketServer { server_addr } => {
+                *server_addr = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), server_addr.port());
+            }
+            _ => {}
+        }
+    }
+
+    config
+}
+
+fn certificate_digest(config: &ServerConfig) -> Option<String> {
+    config.net.iter().find_map(|net_config| {
+        let ServerNetConfig::Netcode { io, .. } = net_config;
+
+        match &io.transport {
+            ServerTransport::WebTransportServer { certificate, .. } => certificate
+                .certificate_chain()
+                .as_slice()
+                .first()
+                .map(|certificate| certificate.hash().to_string().replace(":", "")),
+            _ => None,
+        }
+    })
+}
+
+fn lan_addresses() -> Vec<IpAddr> {
+    match if_addrs::get_if_addrs() {
+        Ok(interfaces) => interfaces
+            .iter()
+            .filter(|interface| !interface.is_loopback() && interface.ip().is_ipv4())
+            .map(|interface| interface.ip())
+            .collect(),
+        Err(e) => {
+            warn!("unable to list network interfaces: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+fn load_host_ports(mut commands: Commands, launch_configurations: Res<LaunchConfigurations>) {
+    let ports = persistence::load::<HostPorts>(HOST_PORTS_KEY).unwrap_or_else(|| {
+        configured_ports(
+            launch_configurations
+                .server_config
+                .as_ref()
+                .expect("There must be a server config if we are in host mode."),
+        )
+    });
+
+    commands.insert_resource(ports);
+}
+
+fn forget_host_info(mut commands: Commands) {
+    commands.remove_resource::<HostInfo>();
+}
+
 struct SendApp(App);
 
 unsafe impl Send for SendApp {}
@@ -38,6 +173,7 @@ fn on_client_begin_hosting(
     launch_configurations: ResMut<LaunchConfigurations>,
     asset_path: Res<AssetPath>,
     client: Res<ClientConnection>,
+    host_ports: Res<HostPorts>,
 ) {
     let client_id = match launch_configurations
         .client_local_config
@@ -60,18 +196,32 @@ fn on_client_begin_hosting(
         NetConfig::Local { id } => panic!("Only networked configurations are supported."),
     };
 
+    persistence::save(HOST_PORTS_KEY, &*host_ports);
+
+    let server_config = with_host_ports(
+        launch_configurations
+            .server_config
+            .clone()
+            .expect("There must be a server config if we are in host mode."),
+        *host_ports,
+    );
+
+    commands.insert_resource(HostInfo {
+        ports: *host_ports,
+        lan_addresses: lan_addresses(),
+        certificate_digest: certificate_digest(&server_config),
+    });
+
     {
         let server_app = build_server_app(
-            launch_configurations
-                .server_config
-                .clone()
-                .expect("There must be a server config if we are in host mode."),
+            server_config,
             asset_path.0.clone(),
             ServerMode::ClientHost(ClientId::Netcode(client_id)),
         );
 
         let mut send_server_app = SendApp(server_app);
-        std::thread::spawn(move || send_server_app.run());
+        let handle = thread::spawn(move || send_server_app.run());
+        commands.insert_resource(HostedServer(handle));
     }
 
     commands.set_state(GameState::ConnectingSelf);
@@ -1,5 +1,5 @@
 use bevy::{
-    ecs::system::{lifetimeless::SRes, StaticSystemParam}, input::mouse::MouseMotion, prelude::*
+    ecs::system::{lifetimeless::SRes, StaticSystemParam}, input::mouse::MouseMotion, prelude::*, window::{CursorGrabMode, PrimaryWindow}
 };
 use leafwing_input_manager::{
     clashing_inputs::BasicInputs, plugin::{InputManagerPlugin, InputManagerSystem}, prelude::{
@@ -11,7 +11,7 @@ use mygame_common::Simulated;
 use mygame_protocol::input::NetworkedInput;
 use serde::{Deserialize, Serialize};
 
-use crate::{game_state::GameState, replication::LocalPlayer, ui::system_menu::SystemMenuState};
+use crate::{game_state::GameState, replication::LocalPlayer, settings::{AimModel, ClientSettings}, ui::{controls_menu::PendingRebind, net_stats::NetStatsState, system_menu::SystemMenuState}};
 
 pub struct InputPlugin;
 impl Plugin for InputPlugin {
@@ -22,7 +22,14 @@ impl Plugin for InputPlugin {
                 Update,
                 (
                     add_input_maps,
-                    handle_system_menu_or_cancel.run_if(in_state(GameState::Playing)),
+                    apply_input_maps.run_if(resource_changed::<ClientSettings>),
+                    handle_system_menu_or_cancel
+                        .run_if(
+                            in_state(GameState::Playing)
+                                .or(in_state(GameState::Spectating))
+                                .and(not(resource_exists::<PendingRebind>)),
+                        ),
+                    handle_toggle_net_stats.run_if(not(resource_exists::<PendingRebind>)),
                 ),
             )
             .add_systems(PreUpdate, update_aim_direction.in_set(InputManagerSystem::Update))
@@ -35,31 +42,93 @@ impl Plugin for InputPlugin {
 pub enum SystemInput {
     #[actionlike(Button)]
     SystemMenuOrCancel,
+    #[actionlike(Button)]
+    ToggleNetStats,
+    #[actionlike(Button)]
+    SpectateNextShip,
+    #[actionlike(Button)]
+    SpectateFreeCamera,
+}
+
+impl SystemInput {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SystemInput::SystemMenuOrCancel => "System Menu",
+            SystemInput::ToggleNetStats => "Network Stats",
+            SystemInput::SpectateNextShip => "Spectate Next Ship",
+            SystemInput::SpectateFreeCamera => "Spectate Free Camera",
+        }
+    }
+}
+
+/// Tag component for the entity that owns the (non-networked) SystemInput state
+#[derive(Component)]
+pub struct SystemInputs;
+
+pub fn default_system_input_map() -> InputMap<SystemInput> {
+    InputMap::<SystemInput>::default()
+        .with(SystemInput::SystemMenuOrCancel, KeyCode::Escape)
+        .with(SystemInput::SystemMenuOrCancel, GamepadButton::Start)
+        .with(SystemInput::ToggleNetStats, KeyCode::F3)
+        .with(SystemInput::SpectateNextShip, KeyCode::Tab)
+        .with(SystemInput::SpectateNextShip, GamepadButton::RightTrigger)
+        .with(SystemInput::SpectateFreeCamera, KeyCode::KeyF)
+        .with(SystemInput::SpectateFreeCamera, GamepadButton::North)
+}
+
+pub fn default_networked_input_map() -> InputMap<NetworkedInput> {
+    InputMap::<NetworkedInput>::default()
+        //.with_dual_axis(NetworkedInput::Aim, MouseMove::default().sensitivity(0.05).inverted_y())
+        .with_dual_axis(NetworkedInput::Aim, AimInput)
+        .with_dual_axis(NetworkedInput::Aim, GamepadStick::LEFT)
+        .with(NetworkedInput::Fire, MouseButton::Right)
+        .with(NetworkedInput::Fire, GamepadButton::RightTrigger2)
+        .with(NetworkedInput::Boost, KeyCode::Space)
+        .with(NetworkedInput::Boost, GamepadButton::LeftTrigger2)
 }
 
 fn spawn_system_input_entity(
-    mut commands: Commands
+    mut commands: Commands,
+    settings: Res<ClientSettings>,
 ) {
     commands.spawn((
-        InputMap::<SystemInput>::default().with(SystemInput::SystemMenuOrCancel, KeyCode::Escape),
+        settings.system_input_map.clone(),
         ActionState::<SystemInput>::default(),
+        SystemInputs,
     ));
 }
 
 fn add_input_maps(
     mut commands: Commands,
     q_local_player: Query<Entity, (Simulated, Added<LocalPlayer>)>,
+    settings: Res<ClientSettings>,
 ) {
     for player in &q_local_player {
         commands.entity(player).insert((
-            InputMap::<NetworkedInput>::default()
-                //.with_dual_axis(NetworkedInput::Aim, MouseMove::default().sensitivity(0.05).inverted_y())
-                .with_dual_axis(NetworkedInput::Aim, AimInput)
-                .with(NetworkedInput::Fire, MouseButton::Right),
+            settings.networked_input_map.clone(),
         ));
     }
 }
 
+/// Swap in rebound controls without waiting for the next spawn
+fn apply_input_maps(
+    settings: Res<ClientSettings>,
+    mut q_networked_input_maps: Query<&mut InputMap<NetworkedInput>, (Simulated, With<LocalPlayer>)>,
+    mut q_system_input_maps: Query<&mut InputMap<SystemInput>, With<SystemInputs>>,
+) {
+    for mut input_map in &mut q_networked_input_maps {
+        if *input_map != settings.networked_input_map {
+            *input_map = settings.networked_input_map.clone();
+        }
+    }
+
+    for mut input_map in &mut q_system_input_maps {
+        if *input_map != settings.system_input_map {
+            *input_map = settings.system_input_map.clone();
+        }
+    }
+}
+
 fn handle_system_menu_or_cancel(
     q_local_inputs: Query<&ActionState<SystemInput>>,
     system_menu_state: Res<State<SystemMenuState>>,
@@ -82,11 +151,36 @@ fn handle_system_menu_or_cancel(
     }
 }
 
+fn handle_toggle_net_stats(
+    q_local_inputs: Query<&ActionState<SystemInput>>,
+    net_stats_state: Res<State<NetStatsState>>,
+    mut next_net_stats_state: ResMut<NextState<NetStatsState>>,
+    mut waiting_release: Local<bool>,
+) {
+    for local_input in &q_local_inputs {
+        // Same just_pressed workaround as handle_system_menu_or_cancel
+        if local_input.released(&SystemInput::ToggleNetStats) {
+            *waiting_release = false;
+        }
+
+        if local_input.pressed(&SystemInput::ToggleNetStats) && !*waiting_release {
+            *waiting_release = true;
+            match **net_stats_state {
+                NetStatsState::Open => next_net_stats_state.set(NetStatsState::Closed),
+                NetStatsState::Closed => next_net_stats_state.set(NetStatsState::Open),
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 pub struct AimInput;
 
+/// Mouse aim for the local player. `cursor` is where the virtual cursor sits in the unit
+/// circle, `direction` is what gets fed to the Aim axis once the deadzone is applied.
 #[derive(Resource, Default)]
 pub struct AimDirection {
+    pub cursor: Vec2,
     pub direction: Vec2,
 }
 
@@ -123,6 +217,8 @@ impl DualAxislike for AimInput {
 fn update_aim_direction(
     mut aim_direction: ResMut<AimDirection>,
     mut mouse_motion_events: EventReader<MouseMotion>,
+    primary_window: Single<&Window, With<PrimaryWindow>>,
+    settings: Res<ClientSettings>,
     time: Res<Time>,
 ) {
     // Get cumulative motion this frame
@@ -130,20 +226,44 @@ fn update_aim_direction(
     for event in mouse_motion_events.read() {
         delta += event.delta;
     }
-    
-    // Apply sensitivity and update direction
-    let sensitivity = 0.001; // Adjust this value to your liking
-    
-    if delta != Vec2::ZERO {
-        // Update the aim direction based on mouse movement
-        // You might want to clamp these values to keep them in a certain range
-        aim_direction.direction.x += delta.x * sensitivity;
-        aim_direction.direction.y -= delta.y * sensitivity; // Invert Y for typical FPS controls
-        
-        // Optional: Normalize or clamp the direction vector
-        // aim_direction.direction = aim_direction.direction.normalize();
-        // or
-        aim_direction.direction.x = aim_direction.direction.x.clamp(-1.0, 1.0);
-        aim_direction.direction.y = aim_direction.direction.y.clamp(-1.0, 1.0);
+
+    // The mouse is only steering while it's captured. Don't let menu clicks swing the ship.
+    if primary_window.cursor_options.grab_mode == CursorGrabMode::None {
+        *aim_direction = AimDirection::default();
+        return;
     }
+
+    // Screen space is y-down, aim is y-up
+    let y_direction = if settings.invert_y { 1.0 } else { -1.0 };
+    let motion = Vec2::new(delta.x, delta.y * y_direction) * settings.mouse_sensitivity;
+
+    match settings.aim_model {
+        AimModel::VirtualJoystick => {
+            aim_direction.cursor = (aim_direction.cursor + motion).clamp_length_max(1.0);
+        }
+        AimModel::AutoCenter => {
+            let decay = (-settings.aim_recenter_rate * time.delta_secs()).exp();
+            aim_direction.cursor = ((aim_direction.cursor + motion) * decay).clamp_length_max(1.0);
+        }
+        AimModel::MouseDelta => {
+            // Normalize by frame time so the turn rate doesn't depend on frame rate
+            let delta_secs = time.delta_secs().max(f32::EPSILON);
+            aim_direction.cursor = (motion / delta_secs * MOUSE_DELTA_SCALE).clamp_length_max(1.0);
+        }
+    }
+
+    aim_direction.direction = apply_deadzone(aim_direction.cursor, settings.aim_deadzone);
+}
+
+/// Scales mouse speed, in sensitivity-adjusted units per second, to a full-rate turn
+const MOUSE_DELTA_SCALE: f32 = 0.5;
+
+/// Radial deadzone that rescales what's left so output still ramps smoothly from 0 to 1
+fn apply_deadzone(cursor: Vec2, deadzone: f32) -> Vec2 {
+    let length = cursor.length();
+    if length <= deadzone || deadzone >= 1.0 {
+        return Vec2::ZERO;
+    }
+
+    cursor / length * ((length - deadzone) / (1.0 - deadzone))
 }
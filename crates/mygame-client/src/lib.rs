@@ -1,13 +1,20 @@
 pub mod app;
+pub mod network;
+pub mod replay;
 
 #[cfg(feature = "host")]
 pub mod host;
 
+mod death_cam;
+#[cfg(not(target_family = "wasm"))]
+mod discovery;
 mod game_state;
 mod input;
 mod interpolation;
-mod network;
+mod persistence;
 mod replication;
+mod settings;
+mod spectator;
 mod ui;
 mod crosshair;
 mod throwaway;
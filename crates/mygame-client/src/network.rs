@@ -1,10 +1,24 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
 use bevy::prelude::*;
+#[cfg(not(target_family = "wasm"))]
+use bevy::tasks::{IoTaskPool, Task};
 use lightyear::{
-    client::config::ClientConfig, connection::client::ConnectionState, prelude::{client::{ClientCommandsExt, ClientConnection, NetClient}, ClientConnectEvent, ClientDisconnectEvent}
+    client::config::ClientConfig, connection::client::ConnectionState, prelude::{client::{Authentication, ClientCommandsExt, ClientTransport, ClientConnection, NetClient, NetConfig}, ClientConnectEvent, ClientConnectionManager, ClientDisconnectEvent, ClientReceiveMessage}
 };
 use mygame_common::LaunchConfigurations;
+use mygame_protocol::message::{
+    ClientHostRequestShutdown, ClientRequestLeave, MatchListing, Reliable, ServerMatchList,
+};
+use serde::{Deserialize, Serialize};
 
-use crate::game_state::GameState;
+use crate::{
+    game_state::GameState, persistence, settings::ClientSettings,
+    ui::shutdown_notice::ShutdownNotice,
+};
 
 pub (crate) struct NetworkPlugin;
 
@@ -12,38 +26,279 @@ impl Plugin for NetworkPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
             OnEnter(GameState::ConnectingRemote),
-            connect_to_remote_server,
+            (clear_match_list, connect_to_remote_server),
         );
+        app.add_systems(Update, receive_match_list);
         app.add_systems(
             OnEnter(GameState::MainMenu),
-            disconnect_client
+            (disconnect_client, reset_reconnect),
+        );
+        app.add_systems(
+            Update,
+            retry_connection.run_if(in_state(GameState::Reconnecting)),
         );
         #[cfg(feature = "host")]
         app.add_systems(OnEnter(GameState::ConnectingSelf), connect_to_local_server);
 
         app.add_observer(on_client_connect_success)
             .add_observer(on_client_disconnect);
+
+        app.init_resource::<RemoteServerAddress>()
+            .init_resource::<Reconnect>()
+            .init_resource::<MatchList>()
+            .insert_resource(persistence::load::<RecentServers>(RECENT_SERVERS_KEY).unwrap_or_default());
     }
 }
 
+const RECENT_SERVERS_KEY: &str = "recent_servers";
+const MAX_RECENT_SERVERS: usize = 5;
+
+/// The server the player asked to connect to from the main menu, as typed and as resolved.
+/// When unset, the server address from the launch configuration is used as-is.
+#[derive(Resource, Default)]
+pub struct RemoteServerAddress(pub Option<(String, SocketAddr)>);
+
+/// Addresses the client has successfully connected to, most recent first.
+#[derive(Resource, Serialize, Deserialize, Default, Clone, Debug)]
+pub struct RecentServers(pub Vec<String>);
+
+impl RecentServers {
+    fn remember(&mut self, address: &str) {
+        self.0.retain(|existing| existing != address);
+        self.0.insert(0, address.to_string());
+        self.0.truncate(MAX_RECENT_SERVERS);
+    }
+}
+
+/// The matches running on the server this client last connected to, if it runs more than
+/// one, so the main menu can offer the others
+#[derive(Resource, Default)]
+pub struct MatchList {
+    server: Option<SocketAddr>,
+    /// Whether the list came over WebSocket, so the other matches should be joined that way too
+    over_websocket: bool,
+    pub matches: Vec<MatchListing>,
+}
+
+impl MatchList {
+    /// Where to connect to one of the listed matches
+    pub fn address(&self, listing: &MatchListing) -> Option<SocketAddr> {
+        #[cfg(target_family = "wasm")]
+        let port = if self.over_websocket {
+            listing.websocket_port
+        } else {
+            listing.webtransport_port
+        };
+        #[cfg(not(target_family = "wasm"))]
+        let port = listing.udp_port;
+
+        self.server.map(|server| SocketAddr::new(server.ip(), port))
+    }
+}
+
+/// Browsers that support WebTransport still can't always use it, some proxies break it. When
+/// this is present and a WebTransport connection fails, the client switches to WebSocket on
+/// this port and tries again. Every connection after that goes over WebSocket.
+#[derive(Resource)]
+pub struct WebSocketFallback {
+    pub port: u16,
+}
+
+const MAX_RECONNECT_ATTEMPTS: u32 = 6;
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(16);
+
+/// Automatic reconnection to a remote server that dropped us mid-session
+#[derive(Resource, Default)]
+pub struct Reconnect {
+    /// Only sessions started with ConnectingRemote are retried. A host's own server is gone for good.
+    remote_session: bool,
+    /// The attempt currently waiting or in flight, starting at 1
+    pub attempt: u32,
+    delay: Timer,
+}
+
+impl Reconnect {
+    /// Wait before the next attempt, doubling the wait each time
+    fn schedule_next_attempt(&mut self) {
+        self.attempt += 1;
+
+        let backoff = Duration::from_secs(1 << (self.attempt - 1).min(8)).min(MAX_RECONNECT_BACKOFF);
+        self.delay = Timer::new(backoff, TimerMode::Once);
+    }
+}
+
+/// Parse an "ip:port" string typed by the player. Hostnames need lookup_server_address.
+pub fn parse_server_address(input: &str) -> Option<SocketAddr> {
+    input.trim().parse::<SocketAddr>().ok()
+}
+
+/// Look up a "host:port" string typed by the player on the IO task pool, since DNS can take
+/// a while. Browsers can't resolve hostnames for us, so there's no equivalent there.
+#[cfg(not(target_family = "wasm"))]
+pub fn lookup_server_address(input: &str) -> Task<Option<SocketAddr>> {
+    use std::net::ToSocketAddrs;
+
+    let input = input.trim().to_string();
+
+    IoTaskPool::get().spawn(async move {
+        let addrs: Vec<SocketAddr> = input.to_socket_addrs().ok()?.collect();
+        addrs.iter().find(|addr| addr.is_ipv4()).or(addrs.first()).copied()
+    })
+}
+
+/// The server address a client config will connect to, if it uses manual authentication.
+pub fn configured_server_address(config: &ClientConfig) -> Option<SocketAddr> {
+    match &config.net {
+        NetConfig::Netcode {
+            auth: Authentication::Manual { server_addr, .. },
+            ..
+        } => Some(*server_addr),
+        _ => None,
+    }
+}
+
+/// Rebuild a remote client config so it points at `addr`, keeping the client id, keys
+/// and the local socket the launcher configured.
+fn with_server_addr(mut config: ClientConfig, addr: SocketAddr) -> ClientConfig {
+    if let NetConfig::Netcode { auth, io, .. } = &mut config.net {
+        if let Authentication::Manual {
+            client_id,
+            private_key,
+            protocol_id,
+            ..
+        } = auth.clone()
+        {
+            *auth = Authentication::Manual {
+                server_addr: addr,
+                client_id,
+                private_key,
+                protocol_id,
+            };
+        }
+
+        match &mut io.transport {
+            // A socket bound to loopback can't reach a teammate's machine
+            ClientTransport::UdpSocket(local_addr)
+                if local_addr.ip().is_loopback() && !addr.ip().is_loopback() =>
+            {
+                *local_addr = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), local_addr.port());
+            }
+            ClientTransport::WebTransportClient { server_addr, .. } => {
+                *server_addr = addr;
+            }
+            ClientTransport::WebSocketClient { server_addr } => {
+                *server_addr = addr;
+            }
+            _ => {}
+        }
+    }
+
+    config
+}
+
+fn uses_webtransport(config: &ClientConfig) -> bool {
+    matches!(
+        &config.net,
+        NetConfig::Netcode { io, .. } if matches!(io.transport, ClientTransport::WebTransportClient { .. })
+    )
+}
+
+fn uses_websocket(config: &ClientConfig) -> bool {
+    matches!(
+        &config.net,
+        NetConfig::Netcode { io, .. } if matches!(io.transport, ClientTransport::WebSocketClient { .. })
+    )
+}
+
+/// Swap a WebTransport config for WebSocket to the same server, on `port`
+fn with_websocket(mut config: ClientConfig, port: u16) -> ClientConfig {
+    if let NetConfig::Netcode { auth, io, .. } = &mut config.net {
+        if let ClientTransport::WebTransportClient { server_addr, .. } = &io.transport {
+            let addr = SocketAddr::new(server_addr.ip(), port);
+            io.transport = ClientTransport::WebSocketClient { server_addr: addr };
+
+            if let Authentication::Manual { server_addr, .. } = auth {
+                *server_addr = addr;
+            }
+        }
+    }
+
+    config
+}
+
 fn disconnect_client(
     mut commands: Commands,
     client: Res<ClientConnection>,
+    mut client_manager: ResMut<ClientConnectionManager>,
+) {
+    match client.state() {
+        ConnectionState::Connected => {
+            // Irrelevant if these fail since we're disconnecting anyway, the server then treats
+            // it as a lost connection
+            let _ = client_manager
+                .send_message::<Reliable, ClientRequestLeave>(&ClientRequestLeave);
+            // Only a host's own server listens for this
+            let _ = client_manager
+                .send_message::<Reliable, ClientHostRequestShutdown>(&ClientHostRequestShutdown);
+
+            commands.disconnect_client();
+        }
+        // Cancelled while a reconnect attempt was in flight
+        ConnectionState::Connecting => commands.disconnect_client(),
+        _ => {}
+    }
+}
+
+fn reset_reconnect(mut reconnect: ResMut<Reconnect>) {
+    reconnect.attempt = 0;
+}
+
+fn retry_connection(mut commands: Commands, time: Res<Time>, mut reconnect: ResMut<Reconnect>) {
+    // Ticking isn't a change anyone displays, only the attempt count is
+    let delay = &mut reconnect.bypass_change_detection().delay;
+
+    if delay.tick(time.delta()).just_finished() {
+        info!("reconnecting, attempt {}", reconnect.attempt);
+        // The ClientConfig still points at the server we lost
+        commands.connect_client();
+    }
+}
+
+fn clear_match_list(mut match_list: ResMut<MatchList>) {
+    *match_list = MatchList::default();
+}
+
+fn receive_match_list(
+    mut match_list_events: EventReader<ClientReceiveMessage<ServerMatchList>>,
+    client_config: Res<ClientConfig>,
+    mut match_list: ResMut<MatchList>,
 ) {
-    if matches!(client.state(), ConnectionState::Connected) {
-        commands.disconnect_client();
+    for ev in match_list_events.read() {
+        match_list.server = configured_server_address(&client_config);
+        match_list.over_websocket = uses_websocket(&client_config);
+        match_list.matches = ev.message.matches.clone();
     }
 }
 
 fn connect_to_remote_server(
     mut commands: Commands,
     host_config: ResMut<LaunchConfigurations>,
+    remote_server_address: Res<RemoteServerAddress>,
     mut client_config: ResMut<ClientConfig>,
+    mut reconnect: ResMut<Reconnect>,
 ) {
-    *client_config = host_config
+    reconnect.remote_session = true;
+
+    let launch_config = host_config
         .client_remote_config
         .clone()
         .expect("There must be a remote client config we are a client.");
+
+    *client_config = match &remote_server_address.0 {
+        Some((_, addr)) => with_server_addr(launch_config, *addr),
+        None => launch_config,
+    };
+
     commands.connect_client();
 }
 
@@ -52,7 +307,10 @@ fn connect_to_local_server(
     mut commands: Commands,
     host_config: ResMut<LaunchConfigurations>,
     mut client_config: ResMut<ClientConfig>,
+    mut reconnect: ResMut<Reconnect>,
 ) {
+    reconnect.remote_session = false;
+
     *client_config = host_config
         .client_local_config
         .clone()
@@ -60,15 +318,78 @@ fn connect_to_local_server(
     commands.connect_client();
 }
 
-fn on_client_connect_success(_trigger: Trigger<ClientConnectEvent>) {
-    // No need to do anything, we are waiting for a ServerWelcome message
+fn on_client_connect_success(
+    _trigger: Trigger<ClientConnectEvent>,
+    game_state: Res<State<GameState>>,
+    remote_server_address: Res<RemoteServerAddress>,
+    mut recent_servers: ResMut<RecentServers>,
+) {
+    // No need to do anything else, we are waiting for a ServerWelcome message
     info!("successful client connection");
+
+    if matches!(**game_state, GameState::ConnectingRemote) {
+        if let Some((input, _)) = &remote_server_address.0 {
+            recent_servers.remember(input);
+            persistence::save(RECENT_SERVERS_KEY, &*recent_servers);
+        }
+    }
 }
 
 fn on_client_disconnect(
     _trigger: Trigger<ClientDisconnectEvent>,
-    mut game_state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
+    game_state: Res<State<GameState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut reconnect: ResMut<Reconnect>,
+    settings: Res<ClientSettings>,
+    shutdown_notice: Res<ShutdownNotice>,
+    websocket_fallback: Option<Res<WebSocketFallback>>,
+    mut launch_configurations: ResMut<LaunchConfigurations>,
+    mut client_config: ResMut<ClientConfig>,
 ) {
-    // TODO: Cleanup existing state?
-    game_state.set(GameState::MainMenu);
+    // Never got through over WebTransport, try the same server over WebSocket instead
+    if let Some(websocket_fallback) = websocket_fallback {
+        if **game_state == GameState::ConnectingRemote && uses_webtransport(&client_config) {
+            warn!(
+                "unable to connect over WebTransport, falling back to WebSocket on port {}",
+                websocket_fallback.port
+            );
+
+            launch_configurations.client_remote_config = launch_configurations
+                .client_remote_config
+                .take()
+                .map(|config| with_websocket(config, websocket_fallback.port));
+            *client_config = with_websocket(client_config.clone(), websocket_fallback.port);
+
+            commands.remove_resource::<WebSocketFallback>();
+            commands.connect_client();
+            return;
+        }
+    }
+
+    // The server warned us it was going, there's nothing to reconnect to
+    if shutdown_notice.reason.is_some() {
+        next_game_state.set(GameState::MainMenu);
+        return;
+    }
+
+    // Entering MainMenu or Reconnecting tears down whatever the session left behind
+    match **game_state {
+        GameState::Lobby
+        | GameState::Loading
+        | GameState::Spawning
+        | GameState::Playing
+        | GameState::Spectating
+            if settings.auto_reconnect && reconnect.remote_session =>
+        {
+            warn!("lost connection to the server, reconnecting");
+            reconnect.attempt = 0;
+            reconnect.schedule_next_attempt();
+            next_game_state.set(GameState::Reconnecting);
+        }
+        GameState::Reconnecting if reconnect.attempt < MAX_RECONNECT_ATTEMPTS => {
+            reconnect.schedule_next_attempt();
+        }
+        _ => next_game_state.set(GameState::MainMenu),
+    }
 }
@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use serde::{Serialize, de::DeserializeOwned};
+
+/// Natively, every key is stored as its own RON file in this directory,
+/// relative to the working directory the client was launched from.
+#[cfg(not(target_family = "wasm"))]
+const USER_DATA_DIR: &str = "./user_data";
+
+/// On the web, every key is stored as a RON string in localStorage under this prefix.
+#[cfg(target_family = "wasm")]
+const LOCAL_STORAGE_PREFIX: &str = "mygame.";
+
+/// Load a previously saved value. Returns None if nothing was saved or it no longer parses,
+/// in which case callers are expected to fall back to their defaults.
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let contents = read(key)?;
+
+    match ron::de::from_str(&contents) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("Discarding saved {} because it failed to parse: {}", key, e);
+            None
+        }
+    }
+}
+
+pub fn save<T: Serialize>(key: &str, value: &T) {
+    let contents = match ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()) {
+        Ok(contents) => contents,
+        Err(e) => {
+            error!("Unable to serialize {}: {}", key, e);
+            return;
+        }
+    };
+
+    write(key, &contents);
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn read(key: &str) -> Option<String> {
+    let path = std::path::Path::new(USER_DATA_DIR).join(format!("{}.ron", key));
+    std::fs::read_to_string(path).ok()
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn write(key: &str, contents: &str) {
+    let dir = std::path::Path::new(USER_DATA_DIR);
+
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        error!("Unable to create {:?}: {}", dir, e);
+        return;
+    }
+
+    if let Err(e) = std::fs::write(dir.join(format!("{}.ron", key)), contents) {
+        error!("Unable to save {}: {}", key, e);
+    }
+}
+
+#[cfg(target_family = "wasm")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_family = "wasm")]
+fn read(key: &str) -> Option<String> {
+    local_storage()?
+        .get_item(&format!("{}{}", LOCAL_STORAGE_PREFIX, key))
+        .ok()?
+}
+
+#[cfg(target_family = "wasm")]
+fn write(key: &str, contents: &str) {
+    let Some(storage) = local_storage() else {
+        error!("localStorage is unavailable, unable to save {}", key);
+        return;
+    };
+
+    if storage
+        .set_item(&format!("{}{}", LOCAL_STORAGE_PREFIX, key), contents)
+        .is_err()
+    {
+        error!("Unable to save {} to localStorage", key);
+    }
+}
@@ -0,0 +1,563 @@
+//! Plays back a match recorded by the server's MatchRecordingPlugin.
+//!
+//! The recording is decoded into a plain `ReplayWorld` one frame at a time, and the ECS is then
+//! brought in line with it. Seeking backwards rebuilds the ReplayWorld from the first frame,
+//! which is cheap since nothing but the recorded events is simulated.
+//!
+//! Space pauses, Left/Right seek 5 seconds (or step one tick while paused), Up/Down change the
+//! playback speed and Tab cycles which ship the camera follows. When not following a ship the camera
+//! flies freely. The timeline can be clicked or dragged to scrub.
+
+use std::{collections::HashMap, path::Path, time::Duration};
+
+use bevy::{
+    color::palettes::tailwind::{SLATE_400, SLATE_800},
+    prelude::*,
+};
+use lightyear::client::{config::ClientConfig, plugin::ClientPlugins};
+use mygame_assets::{CurrentLevel, LevelState, assets::GlobalAssets};
+use mygame_common::{
+    CommonPlugin,
+    match_recording::{MatchRecording, RecordedEvent, RecordedKind, RecordingError},
+};
+use mygame_protocol::component::Health;
+use mygame_render::{RenderPlugin, camera::{CameraTarget, FreeFlyCamera, MainCamera}, effects::PlayFx};
+
+use crate::app::default_plugins;
+
+/// Speeds that Up/Down step through
+const PLAYBACK_SPEEDS: [f32; 7] = [0.125, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+const DEFAULT_SPEED_INDEX: usize = 3;
+
+const SEEK_STEP: Duration = Duration::from_secs(5);
+
+/// Catching up more frames than this at once counts as a seek, so effects aren't replayed en masse
+const MAX_FRAMES_WITH_FX: usize = 10;
+
+const FOLLOW_DISTANCE: f32 = 6.0;
+
+pub fn build_replay_app(recording_path: &Path, asset_path: String) -> Result<App, RecordingError> {
+    let recording = MatchRecording::load(recording_path)?;
+
+    info!(
+        "replaying {:?}: {} ticks ({:.1}s) on {:?}",
+        recording_path,
+        recording.frames.len(),
+        recording.duration().as_secs_f32(),
+        recording.header.level
+    );
+
+    let mut app = App::new();
+
+    app.add_plugins((
+        default_plugins(asset_path),
+        // Never connects, but the shared gameplay and render plugins expect lightyear to be there
+        ClientPlugins {
+            config: ClientConfig::default(),
+        },
+        CommonPlugin,
+        RenderPlugin,
+        ReplayPlugin,
+    ))
+    .insert_resource(CurrentLevel(recording.header.level))
+    .insert_resource(Replay(recording));
+
+    Ok(app)
+}
+
+struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayPlayback>()
+            .init_resource::<ReplayWorld>()
+            .init_resource::<ReplayedEntities>()
+            .init_resource::<FollowedShip>()
+            .add_systems(OnEnter(LevelState::Loaded), spawn_replay_ui)
+            .add_systems(
+                Update,
+                (
+                    handle_replay_input,
+                    advance_playback,
+                    sync_replayed_entities,
+                    follow_ship,
+                    update_replay_ui,
+                )
+                    .chain()
+                    .run_if(in_state(LevelState::Loaded)),
+            );
+    }
+}
+
+#[derive(Resource)]
+struct Replay(MatchRecording);
+
+impl Replay {
+    fn tick_duration(&self) -> Duration {
+        self.0.header.tick_duration
+    }
+
+    fn duration(&self) -> Duration {
+        self.0.duration()
+    }
+
+    /// How many frames should have been applied to show this point in time
+    fn frames_at(&self, time: Duration) -> usize {
+        let frame = (time.as_secs_f64() / self.tick_duration().as_secs_f64()) as usize + 1;
+        frame.min(self.0.frames.len())
+    }
+}
+
+#[derive(Resource)]
+struct ReplayPlayback {
+    time: Duration,
+    speed_index: usize,
+    paused: bool,
+    /// Set when something other than normal playback moved `time`
+    seeked: bool,
+}
+
+impl Default for ReplayPlayback {
+    fn default() -> Self {
+        Self {
+            time: Duration::ZERO,
+            speed_index: DEFAULT_SPEED_INDEX,
+            paused: false,
+            seeked: false,
+        }
+    }
+}
+
+impl ReplayPlayback {
+    fn speed(&self) -> f32 {
+        PLAYBACK_SPEEDS[self.speed_index]
+    }
+
+    fn seek(&mut self, time: Duration, replay: &Replay) {
+        self.time = time.min(replay.duration());
+        self.seeked = true;
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ReplayedState {
+    kind: RecordedKind,
+    position: Vec3,
+    rotation: Quat,
+    health: Option<Health>,
+}
+
+/// The recorded world as of `frames_applied` frames
+#[derive(Resource, Default)]
+struct ReplayWorld {
+    frames_applied: usize,
+    entities: HashMap<u64, ReplayedState>,
+}
+
+impl ReplayWorld {
+    /// Apply one frame, triggering its effects unless `quiet`
+    fn apply(&mut self, events: &[RecordedEvent], commands: &mut Commands, quiet: bool) {
+        for event in events {
+            match event {
+                RecordedEvent::Spawned { id, kind } => {
+                    self.entities.insert(
+                        *id,
+                        ReplayedState {
+                            kind: *kind,
+                            position: Vec3::ZERO,
+                            rotation: Quat::IDENTITY,
+                            health: None,
+                        },
+                    );
+                }
+                RecordedEvent::Despawned { id } => {
+                    let Some(state) = self.entities.remove(id) else {
+                        continue;
+                    };
+
+                    if !quiet && matches!(state.kind, RecordedKind::Ship { .. }) {
+                        commands.trigger(PlayFx::ShipDestroyed(state.position));
+                    }
+                }
+                RecordedEvent::Moved {
+                    id,
+                    position,
+                    rotation,
+                } => {
+                    if let Some(state) = self.entities.get_mut(id) {
+                        state.position = *position;
+                        state.rotation = *rotation;
+                    }
+                }
+                RecordedEvent::HealthChanged { id, health } => {
+                    if let Some(state) = self.entities.get_mut(id) {
+                        state.health = Some(health.clone());
+                    }
+                }
+                RecordedEvent::ShipHit { position, kind } => {
+                    if !quiet {
+                        commands.trigger(PlayFx::ship_hit(*position, *kind));
+                    }
+                }
+            }
+        }
+
+        self.frames_applied += 1;
+    }
+
+    fn ships(&self) -> impl Iterator<Item = (&u64, &ReplayedState)> {
+        let mut ships: Vec<_> = self
+            .entities
+            .iter()
+            .filter(|(_, state)| matches!(state.kind, RecordedKind::Ship { .. }))
+            .collect();
+        ships.sort_by_key(|(id, _)| **id);
+        ships.into_iter()
+    }
+}
+
+/// Links a recorded entity id to the entity drawing it
+#[derive(Resource, Default)]
+struct ReplayedEntities(HashMap<u64, Entity>);
+
+#[derive(Component)]
+struct ReplayedEntity(u64);
+
+/// The recorded id of the ship the camera is following, if any
+#[derive(Resource, Default)]
+struct FollowedShip(Option<u64>);
+
+fn ship_label(kind: &RecordedKind) -> String {
+    match kind {
+        RecordedKind::Ship {
+            player: Some(player),
+            ..
+        } => format!("Player {}", player),
+        RecordedKind::Ship { bot: Some(bot), .. } => format!("Bot {}", bot),
+        _ => String::from("Ship"),
+    }
+}
+
+fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn handle_replay_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    replay: Res<Replay>,
+    replay_world: Res<ReplayWorld>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut followed_ship: ResMut<FollowedShip>,
+) {
+    if keys.just_pressed(KeyCode::Space) {
+        // Playing from the very end starts over
+        if playback.paused && playback.time >= replay.duration() {
+            playback.seek(Duration::ZERO, &replay);
+        }
+        playback.paused = !playback.paused;
+    }
+
+    let step = if playback.paused {
+        replay.tick_duration()
+    } else {
+        SEEK_STEP
+    };
+
+    if keys.just_pressed(KeyCode::ArrowLeft) {
+        let time = playback.time.saturating_sub(step);
+        playback.seek(time, &replay);
+    }
+
+    if keys.just_pressed(KeyCode::ArrowRight) {
+        let time = playback.time + step;
+        playback.seek(time, &replay);
+    }
+
+    if keys.just_pressed(KeyCode::ArrowUp) {
+        playback.speed_index = (playback.speed_index + 1).min(PLAYBACK_SPEEDS.len() - 1);
+    }
+
+    if keys.just_pressed(KeyCode::ArrowDown) {
+        playback.speed_index = playback.speed_index.saturating_sub(1);
+    }
+
+    // Cycle through every ship, then back to the free camera
+    if keys.just_pressed(KeyCode::Tab) {
+        let ships: Vec<u64> = replay_world.ships().map(|(id, _)| *id).collect();
+
+        followed_ship.0 = match followed_ship.0 {
+            None => ships.first().copied(),
+            Some(current) => ships
+                .iter()
+                .position(|id| *id == current)
+                .and_then(|index| ships.get(index + 1))
+                .copied(),
+        };
+    }
+}
+
+fn advance_playback(
+    mut commands: Commands,
+    time: Res<Time>,
+    replay: Res<Replay>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut replay_world: ResMut<ReplayWorld>,
+) {
+    if !playback.paused {
+        let elapsed = time.delta().mul_f32(playback.speed());
+        playback.time = (playback.time + elapsed).min(replay.duration());
+
+        if playback.time >= replay.duration() {
+            playback.paused = true;
+        }
+    }
+
+    let target_frames = replay.frames_at(playback.time);
+
+    // There's no going backwards through events, so start again from the top
+    if target_frames < replay_world.frames_applied {
+        *replay_world = ReplayWorld::default();
+    }
+
+    let quiet =
+        playback.seeked || target_frames - replay_world.frames_applied > MAX_FRAMES_WITH_FX;
+    playback.seeked = false;
+
+    while replay_world.frames_applied < target_frames {
+        let frame = &replay.0.frames[replay_world.frames_applied];
+        replay_world.apply(&frame.events, &mut commands, quiet);
+    }
+}
+
+fn sync_replayed_entities(
+    mut commands: Commands,
+    replay_world: Res<ReplayWorld>,
+    global_assets: Res<GlobalAssets>,
+    mut replayed_entities: ResMut<ReplayedEntities>,
+    mut q_transforms: Query<&mut Transform, With<ReplayedEntity>>,
+) {
+    if !replay_world.is_changed() {
+        return;
+    }
+
+    replayed_entities.0.retain(|id, entity| {
+        let exists = replay_world.entities.contains_key(id);
+        if !exists {
+            commands.entity(*entity).despawn_recursive();
+        }
+        exists
+    });
+
+    for (id, state) in &replay_world.entities {
+        let transform = Transform::from_translation(state.position).with_rotation(state.rotation);
+
+        if let Some(entity) = replayed_entities.0.get(id) {
+            if let Ok(mut current_transform) = q_transforms.get_mut(*entity) {
+                *current_transform = transform;
+            }
+            continue;
+        }
+
+        let scene = match state.kind {
+            RecordedKind::Ship { .. } => global_assets.character.clone(),
+            RecordedKind::Projectile => global_assets.laser.clone(),
+        };
+
+        let entity = commands
+            .spawn((SceneRoot(scene), transform, ReplayedEntity(*id)))
+            .id();
+        replayed_entities.0.insert(*id, entity);
+    }
+}
+
+fn follow_ship(
+    mut commands: Commands,
+    mut followed_ship: ResMut<FollowedShip>,
+    replayed_entities: Res<ReplayedEntities>,
+    q_camera_targets: Query<(Entity, &ReplayedEntity), With<CameraTarget>>,
+    q_main_cameras: Query<(Entity, Has<FreeFlyCamera>), With<MainCamera>>,
+) {
+    // The followed ship might have been destroyed, or not exist yet after a seek
+    let followed_entity = followed_ship
+        .0
+        .and_then(|id| replayed_entities.0.get(&id).copied());
+
+    if followed_entity.is_none() && followed_ship.0.is_some() && replayed_entities.is_changed() {
+        followed_ship.0 = None;
+    }
+
+    for (entity, _) in &q_camera_targets {
+        if Some(entity) != followed_entity {
+            commands.entity(entity).remove::<CameraTarget>();
+        }
+    }
+
+    if let Some(entity) = followed_entity {
+        if !q_camera_targets.contains(entity) {
+            commands.entity(entity).insert(CameraTarget {
+                follow_distance: FOLLOW_DISTANCE,
+                smooth_time: 0.15,
+            });
+        }
+    }
+
+    for (camera, free_flying) in &q_main_cameras {
+        match (followed_entity, free_flying) {
+            (Some(_), true) => {
+                commands.entity(camera).remove::<FreeFlyCamera>();
+            }
+            (None, false) => {
+                commands.entity(camera).insert(FreeFlyCamera::default());
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Component)]
+struct ReplayStatusText;
+
+#[derive(Component)]
+struct ReplayTimeline;
+
+#[derive(Component)]
+struct ReplayTimelineFill;
+
+fn spawn_replay_ui(mut commands: Commands) {
+    commands
+        .spawn(Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            justify_content: JustifyContent::End,
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(10.0)),
+            row_gap: Val::Px(6.0),
+            ..default()
+        })
+        .insert(Pickable::IGNORE)
+        .with_children(|child_builder| {
+            child_builder.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 16.,
+                    ..default()
+                },
+                ReplayStatusText,
+            ));
+
+            child_builder
+                .spawn((
+                    Node {
+                        width: Val::Percent(100.0),
+                        height: Val::Px(12.0),
+                        ..default()
+                    },
+                    BackgroundColor(SLATE_800.into()),
+                    ReplayTimeline,
+                ))
+                .observe(scrub_on_click)
+                .observe(scrub_on_drag)
+                .with_children(|timeline_builder| {
+                    timeline_builder.spawn((
+                        Node {
+                            width: Val::Percent(0.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        BackgroundColor(SLATE_400.into()),
+                        Pickable::IGNORE,
+                        ReplayTimelineFill,
+                    ));
+                });
+        });
+}
+
+fn scrub_on_click(
+    trigger: Trigger<Pointer<Click>>,
+    q_timeline: Query<(&ComputedNode, &GlobalTransform), With<ReplayTimeline>>,
+    replay: Res<Replay>,
+    mut playback: ResMut<ReplayPlayback>,
+) {
+    scrub_to(trigger.pointer_location.position, &q_timeline, &replay, &mut playback);
+}
+
+fn scrub_on_drag(
+    trigger: Trigger<Pointer<Drag>>,
+    q_timeline: Query<(&ComputedNode, &GlobalTransform), With<ReplayTimeline>>,
+    replay: Res<Replay>,
+    mut playback: ResMut<ReplayPlayback>,
+) {
+    scrub_to(trigger.pointer_location.position, &q_timeline, &replay, &mut playback);
+}
+
+/// Seek to wherever along the timeline `pointer` (in logical pixels) is
+fn scrub_to(
+    pointer: Vec2,
+    q_timeline: &Query<(&ComputedNode, &GlobalTransform), With<ReplayTimeline>>,
+    replay: &Replay,
+    playback: &mut ReplayPlayback,
+) {
+    let Ok((node, transform)) = q_timeline.single() else {
+        return;
+    };
+
+    // Node sizes and transforms are in physical pixels
+    let width = node.size().x * node.inverse_scale_factor();
+    let left = (transform.translation().x - node.size().x / 2.0) * node.inverse_scale_factor();
+
+    if width <= 0.0 {
+        return;
+    }
+
+    let fraction = ((pointer.x - left) / width).clamp(0.0, 1.0);
+    playback.seek(replay.duration().mul_f32(fraction), replay);
+}
+
+fn update_replay_ui(
+    replay: Res<Replay>,
+    playback: Res<ReplayPlayback>,
+    replay_world: Res<ReplayWorld>,
+    followed_ship: Res<FollowedShip>,
+    mut q_status_text: Query<&mut Text, With<ReplayStatusText>>,
+    mut q_timeline_fill: Query<&mut Node, With<ReplayTimelineFill>>,
+) {
+    let following = followed_ship
+        .0
+        .and_then(|id| replay_world.entities.get(&id))
+        .map(|state| match &state.health {
+            Some(health) => format!(
+                "Following {} ({}/{})",
+                ship_label(&state.kind),
+                health.current,
+                health.max
+            ),
+            None => format!("Following {}", ship_label(&state.kind)),
+        })
+        .unwrap_or_else(|| String::from("Free camera"));
+
+    let status = format!(
+        "{} {} / {}   {}x   {}   [Space] pause  [←/→] seek  [↑/↓] speed  [Tab] follow  [WASD/QE + right mouse] fly",
+        if playback.paused { "Paused" } else { "Playing" },
+        format_time(playback.time),
+        format_time(replay.duration()),
+        playback.speed(),
+        following
+    );
+
+    for mut text in &mut q_status_text {
+        if text.0 != status {
+            text.0 = status.clone();
+        }
+    }
+
+    let fraction = if replay.duration().is_zero() {
+        0.0
+    } else {
+        playback.time.as_secs_f32() / replay.duration().as_secs_f32()
+    };
+
+    for mut node in &mut q_timeline_fill {
+        node.width = Val::Percent(fraction * 100.0);
+    }
+}
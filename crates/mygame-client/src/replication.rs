@@ -12,6 +12,9 @@ use mygame_protocol::{
 };
 use mygame_render::camera::CameraTarget;
 
+use crate::settings::ClientSettings;
+use crate::spectator::{JoinAsSpectator, request_spectate};
+
 pub (crate) struct ReplicationPlugin;
 
 impl Plugin for ReplicationPlugin {
@@ -22,9 +25,14 @@ impl Plugin for ReplicationPlugin {
                 on_server_welcome.run_if(in_state(GameState::ConnectingRemote)),
                 #[cfg(feature = "host")]
                 on_server_welcome.run_if(in_state(GameState::ConnectingSelf)),
+                on_server_welcome.run_if(in_state(GameState::Reconnecting)),
+                on_server_welcome.run_if(in_state(GameState::Lobby)),
             ),
         );
-        app.add_systems(Update, await_spawn);
+        app.add_systems(
+            Update,
+            await_spawn.run_if(in_state(GameState::Spawning).or(in_state(GameState::Playing))),
+        );
         app.add_systems(OnEnter(LevelState::Loaded), on_assets_loaded);
     }
 }
@@ -34,8 +42,17 @@ impl Plugin for ReplicationPlugin {
 pub struct LocalPlayer;
 
 /// Once finished loading the assets that the server requested the client to load
-/// Signal the completion to the server
-fn on_assets_loaded(mut commands: Commands, mut client: ResMut<ClientConnectionManager>) {
+/// Signal the completion to the server, by asking to either spawn or spectate
+fn on_assets_loaded(
+    mut commands: Commands,
+    mut client: ResMut<ClientConnectionManager>,
+    join_as_spectator: Res<JoinAsSpectator>,
+) {
+    if join_as_spectator.0 {
+        request_spectate(&mut commands);
+        return;
+    }
+
     commands.set_state(GameState::Spawning);
 
     if let Err(e) =
@@ -48,6 +65,7 @@ fn on_assets_loaded(mut commands: Commands, mut client: ResMut<ClientConnectionM
 
 /// Respond to the welcome message from the server by initiating a load of the level requested
 fn on_server_welcome(
+    mut commands: Commands,
     mut server_welcome_events: ResMut<Events<ClientReceiveMessage<ServerWelcome>>>,
     game_state: Res<State<GameState>>,
     mut current_level: ResMut<CurrentLevel>,
@@ -56,31 +74,36 @@ fn on_server_welcome(
     for ev in server_welcome_events.drain() {
         next_state.set(GameState::Loading);
         current_level.0 = ev.message.current_level;
+        commands.insert_resource(ev.message.match_rules);
     }
 }
 
+/// A resumed session's ship may replicate while the level is still loading,
+/// so look for any unclaimed ship rather than only newly added ones.
 fn await_spawn(
     mut commands: Commands,
-    q_spawned_player: Query<(Entity, &Player), (Rendered, Added<Player>)>,
+    q_spawned_player: Query<(Entity, &Player), (Rendered, Without<LocalPlayer>)>,
     q_local_player: Query<&LocalPlayer>, 
     client: Res<ClientConnection>,
+    settings: Res<ClientSettings>,
 ) {
-    for (entity, player) in &q_spawned_player {
-        if !q_local_player.is_empty() {
-            warn!("Server spawned a local player when one already existed? Ignoring.");
-            return;
-        }
+    // Already flying our ship
+    if !q_local_player.is_empty() {
+        return;
+    }
 
+    for (entity, player) in &q_spawned_player {
         if player.0 == client.id() {
             commands.entity(entity)
                 .insert((
                     LocalPlayer,
                     CameraTarget {
-                        follow_distance: 6.0,
+                        follow_distance: settings.follow_distance,
                         smooth_time: 0.15,
                     }
                 ));
             commands.set_state(GameState::Playing);
+            return;
         }
     }
 }
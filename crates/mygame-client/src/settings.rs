@@ -0,0 +1,159 @@
+use bevy::{prelude::*, window::{PresentMode, PrimaryWindow}};
+use leafwing_input_manager::prelude::InputMap;
+use mygame_protocol::input::NetworkedInput;
+use mygame_render::camera::{CameraTarget, MainCamera};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    input::{SystemInput, default_networked_input_map, default_system_input_map},
+    persistence,
+    replication::LocalPlayer,
+};
+
+pub(crate) struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(persistence::load::<ClientSettings>(SETTINGS_KEY).unwrap_or_default())
+            .add_systems(
+                Update,
+                (
+                    save_settings,
+                    apply_camera_settings,
+                    apply_window_settings,
+                    apply_follow_distance,
+                )
+                    .run_if(resource_changed::<ClientSettings>),
+            )
+            .add_systems(Update, apply_camera_settings_to_new_cameras);
+    }
+}
+
+const SETTINGS_KEY: &str = "settings";
+
+pub const MOUSE_SENSITIVITY_RANGE: (f32, f32) = (0.0002, 0.005);
+pub const FOV_DEGREES_RANGE: (f32, f32) = (30.0, 110.0);
+pub const FOLLOW_DISTANCE_RANGE: (f32, f32) = (3.0, 15.0);
+pub const AIM_DEADZONE_RANGE: (f32, f32) = (0.0, 0.5);
+pub const AIM_RECENTER_RATE_RANGE: (f32, f32) = (0.5, 10.0);
+
+/// How mouse movement is turned into the Aim axis
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum AimModel {
+    /// The mouse moves a virtual cursor around a circle, and the ship steers toward it.
+    /// The cursor stays where it is left.
+    #[default]
+    VirtualJoystick,
+    /// Like VirtualJoystick, but the cursor drifts back to the center when the mouse is still
+    AutoCenter,
+    /// Turn rate follows mouse speed. The ship flies straight as soon as the mouse stops.
+    MouseDelta,
+}
+
+impl AimModel {
+    pub const ALL: [AimModel; 3] = [AimModel::VirtualJoystick, AimModel::AutoCenter, AimModel::MouseDelta];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AimModel::VirtualJoystick => "Joystick",
+            AimModel::AutoCenter => "Auto-center",
+            AimModel::MouseDelta => "Mouse Delta",
+        }
+    }
+}
+
+/// Local player preferences. Persisted between sessions and applied live whenever they change.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ClientSettings {
+    pub mouse_sensitivity: f32,
+    pub invert_y: bool,
+    pub aim_model: AimModel,
+    /// Fraction of the virtual cursor's range, around the center, that steers nowhere
+    pub aim_deadzone: f32,
+    /// How quickly the AutoCenter cursor returns to the center, per second
+    pub aim_recenter_rate: f32,
+    pub fov_degrees: f32,
+    pub follow_distance: f32,
+    pub vsync: bool,
+    pub msaa: bool,
+    /// Retry the server after an unexpected disconnect, rather than dropping to the main menu
+    pub auto_reconnect: bool,
+    pub networked_input_map: InputMap<NetworkedInput>,
+    pub system_input_map: InputMap<SystemInput>,
+}
+
+impl Default for ClientSettings {
+    fn default() -> Self {
+        Self {
+            mouse_sensitivity: 0.001,
+            invert_y: false,
+            aim_model: AimModel::default(),
+            aim_deadzone: 0.1,
+            aim_recenter_rate: 2.0,
+            fov_degrees: 45.0,
+            follow_distance: 6.0,
+            vsync: true,
+            msaa: true,
+            auto_reconnect: true,
+            networked_input_map: default_networked_input_map(),
+            system_input_map: default_system_input_map(),
+        }
+    }
+}
+
+fn save_settings(settings: Res<ClientSettings>) {
+    // The resource is inserted at startup, which counts as a change. Nothing to save yet.
+    if settings.is_added() {
+        return;
+    }
+
+    persistence::save(SETTINGS_KEY, &*settings);
+}
+
+fn apply_camera_settings(
+    settings: Res<ClientSettings>,
+    mut q_cameras: Query<(&mut Projection, &mut Msaa), With<Camera3d>>,
+) {
+    for (projection, msaa) in &mut q_cameras {
+        apply_to_camera(&settings, projection, msaa);
+    }
+}
+
+/// The crosshair camera is spawned on entering Playing, so it misses the change-triggered update
+fn apply_camera_settings_to_new_cameras(
+    settings: Res<ClientSettings>,
+    mut q_cameras: Query<(&mut Projection, &mut Msaa), Added<Camera3d>>,
+) {
+    for (projection, msaa) in &mut q_cameras {
+        apply_to_camera(&settings, projection, msaa);
+    }
+}
+
+fn apply_to_camera(settings: &ClientSettings, mut projection: Mut<Projection>, mut msaa: Mut<Msaa>) {
+    if let Projection::Perspective(perspective) = projection.as_mut() {
+        perspective.fov = settings.fov_degrees.to_radians();
+    }
+
+    *msaa = if settings.msaa { Msaa::Sample4 } else { Msaa::Off };
+}
+
+fn apply_window_settings(
+    settings: Res<ClientSettings>,
+    mut primary_window: Single<&mut Window, With<PrimaryWindow>>,
+) {
+    primary_window.present_mode = if settings.vsync {
+        PresentMode::AutoVsync
+    } else {
+        PresentMode::AutoNoVsync
+    };
+}
+
+fn apply_follow_distance(
+    settings: Res<ClientSettings>,
+    mut q_camera_targets: Query<&mut CameraTarget, With<LocalPlayer>>,
+) {
+    for mut camera_target in &mut q_camera_targets {
+        camera_target.follow_distance = settings.follow_distance;
+    }
+}
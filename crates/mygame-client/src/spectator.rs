@@ -0,0 +1,288 @@
+use bevy::{
+    color::palettes::tailwind::{SLATE_400, SLATE_800},
+    prelude::*,
+};
+use leafwing_input_manager::prelude::ActionState;
+use lightyear::prelude::ClientConnectionManager;
+use mygame_common::Rendered;
+use mygame_protocol::{
+    component::{Bot, Player, Ship},
+    message::{ClientRequestRespawn, ClientRequestSpectate, UnorderedReliable},
+};
+use mygame_render::camera::{CameraTarget, FreeFlyCamera, MainCamera};
+
+use crate::{game_state::GameState, input::SystemInput, settings::ClientSettings};
+
+/// Watching the match without a ship. The camera either flies freely or follows
+/// one of the (interpolated) ships via CameraTarget.
+pub(crate) struct SpectatorPlugin;
+
+impl Plugin for SpectatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<JoinAsSpectator>()
+            .init_resource::<SpectateTarget>()
+            .add_systems(OnEnter(GameState::MainMenu), reset_join_as_spectator)
+            .add_systems(OnEnter(GameState::Spectating), (start_spectating, spawn_spectator_ui))
+            .add_systems(OnExit(GameState::Spectating), stop_spectating)
+            .add_systems(
+                Update,
+                (
+                    drop_lost_spectate_target,
+                    handle_spectate_next_ship,
+                    handle_spectate_free_camera,
+                    follow_spectate_target,
+                    update_spectator_ui,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Spectating)),
+            );
+    }
+}
+
+/// Whether the client should spectate, rather than spawn, once the level is loaded.
+/// Kept across reconnects so a spectator comes back as one.
+#[derive(Resource, Default)]
+pub(crate) struct JoinAsSpectator(pub bool);
+
+/// The ship being followed, or None to fly freely
+#[derive(Resource, Default)]
+struct SpectateTarget(Option<Entity>);
+
+#[derive(Component)]
+struct SpectatorStatusText;
+
+fn reset_join_as_spectator(mut join_as_spectator: ResMut<JoinAsSpectator>) {
+    join_as_spectator.0 = false;
+}
+
+/// Stop playing, if we were, and tell the server we're only watching
+pub(crate) fn request_spectate(commands: &mut Commands) {
+    commands.queue(|world: &mut World| {
+        world.resource_mut::<JoinAsSpectator>().0 = true;
+
+        if let Some(mut client) = world.get_resource_mut::<ClientConnectionManager>() {
+            let _ = client.send_message::<UnorderedReliable, ClientRequestSpectate>(&ClientRequestSpectate);
+        }
+    });
+
+    commands.set_state(GameState::Spectating);
+}
+
+fn start_spectating(mut spectate_target: ResMut<SpectateTarget>) {
+    spectate_target.0 = None;
+}
+
+fn stop_spectating(
+    mut commands: Commands,
+    mut spectate_target: ResMut<SpectateTarget>,
+    q_main_cameras: Query<Entity, With<MainCamera>>,
+) {
+    if let Some(target) = spectate_target.0.take() {
+        if let Ok(mut target) = commands.get_entity(target) {
+            target.remove::<CameraTarget>();
+        }
+    }
+
+    for camera in &q_main_cameras {
+        commands.entity(camera).remove::<FreeFlyCamera>();
+    }
+}
+
+/// The followed ship was destroyed or stopped replicating, so fall back to flying freely
+fn drop_lost_spectate_target(
+    mut spectate_target: ResMut<SpectateTarget>,
+    q_ships: Query<(), (Rendered, With<Ship>)>,
+) {
+    if let Some(target) = spectate_target.0 {
+        if !q_ships.contains(target) {
+            spectate_target.0 = None;
+        }
+    }
+}
+
+fn handle_spectate_next_ship(
+    q_local_inputs: Query<&ActionState<SystemInput>>,
+    q_ships: Query<Entity, (Rendered, With<Ship>)>,
+    mut spectate_target: ResMut<SpectateTarget>,
+    mut waiting_release: Local<bool>,
+) {
+    for local_input in &q_local_inputs {
+        // Same just_pressed workaround as handle_system_menu_or_cancel
+        if local_input.released(&SystemInput::SpectateNextShip) {
+            *waiting_release = false;
+        }
+
+        if local_input.pressed(&SystemInput::SpectateNextShip) && !*waiting_release {
+            *waiting_release = true;
+
+            let mut ships: Vec<Entity> = q_ships.iter().collect();
+            ships.sort();
+
+            // The ship after the current one, wrapping around
+            let next = match spectate_target.0 {
+                Some(current) => ships
+                    .iter()
+                    .position(|ship| *ship == current)
+                    .map(|index| ships[(index + 1) % ships.len()]),
+                None => None,
+            };
+
+            spectate_target.0 = next.or_else(|| ships.first().copied());
+        }
+    }
+}
+
+fn handle_spectate_free_camera(
+    q_local_inputs: Query<&ActionState<SystemInput>>,
+    mut spectate_target: ResMut<SpectateTarget>,
+    mut waiting_release: Local<bool>,
+) {
+    for local_input in &q_local_inputs {
+        // Same just_pressed workaround as handle_system_menu_or_cancel
+        if local_input.released(&SystemInput::SpectateFreeCamera) {
+            *waiting_release = false;
+        }
+
+        if local_input.pressed(&SystemInput::SpectateFreeCamera) && !*waiting_release {
+            *waiting_release = true;
+            spectate_target.0 = None;
+        }
+    }
+}
+
+/// Keep CameraTarget on the followed ship only, and let the camera fly when there isn't one
+fn follow_spectate_target(
+    mut commands: Commands,
+    spectate_target: Res<SpectateTarget>,
+    settings: Res<ClientSettings>,
+    q_camera_targets: Query<Entity, With<CameraTarget>>,
+    q_main_cameras: Query<(Entity, Has<FreeFlyCamera>), With<MainCamera>>,
+) {
+    if !spectate_target.is_changed() {
+        return;
+    }
+
+    for entity in &q_camera_targets {
+        if Some(entity) != spectate_target.0 {
+            commands.entity(entity).remove::<CameraTarget>();
+        }
+    }
+
+    if let Some(target) = spectate_target.0 {
+        commands.entity(target).insert(CameraTarget {
+            follow_distance: settings.follow_distance,
+            smooth_time: 0.15,
+        });
+    }
+
+    for (camera, free_flying) in &q_main_cameras {
+        match (spectate_target.0, free_flying) {
+            (Some(_), true) => {
+                commands.entity(camera).remove::<FreeFlyCamera>();
+            }
+            (None, false) => {
+                commands.entity(camera).insert(FreeFlyCamera::default());
+            }
+            _ => {}
+        }
+    }
+}
+
+fn spawn_spectator_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            Pickable::IGNORE,
+            StateScoped(GameState::Spectating),
+        ))
+        .with_children(|child_builder| {
+            child_builder
+                .spawn((
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    BackgroundColor(SLATE_800.with_alpha(0.8).into()),
+                ))
+                .with_children(|panel_builder| {
+                    panel_builder.spawn((
+                        Text::new("Spectating"),
+                        TextFont {
+                            font_size: 24.,
+                            ..default()
+                        },
+                        SpectatorStatusText,
+                    ));
+
+                    panel_builder.spawn((
+                        Text::new(
+                            "[Tab] next ship  [F] free camera  [WASD/QE + right mouse] fly",
+                        ),
+                        TextFont {
+                            font_size: 14.,
+                            ..default()
+                        },
+                        TextColor(SLATE_400.into()),
+                    ));
+
+                    panel_builder
+                        .spawn((
+                            Text::new("Join"),
+                            TextFont {
+                                font_size: 24.,
+                                ..default()
+                            },
+                            Node {
+                                padding: UiRect::top(Val::Px(10.)),
+                                ..default()
+                            },
+                        ))
+                        .observe(on_join_clicked);
+                });
+        });
+}
+
+fn on_join_clicked(
+    _click: Trigger<Pointer<Click>>,
+    mut commands: Commands,
+    mut join_as_spectator: ResMut<JoinAsSpectator>,
+    mut client: ResMut<ClientConnectionManager>,
+) {
+    join_as_spectator.0 = false;
+
+    if let Err(e) = client.send_message::<UnorderedReliable, ClientRequestRespawn>(&ClientRequestRespawn) {
+        error!("unable to request a spawn: {}", e);
+        return;
+    }
+
+    commands.set_state(GameState::Spawning);
+}
+
+fn update_spectator_ui(
+    spectate_target: Res<SpectateTarget>,
+    q_ship_owners: Query<(Option<&Player>, Option<&Bot>), With<Ship>>,
+    mut q_status_text: Query<&mut Text, With<SpectatorStatusText>>,
+) {
+    if !spectate_target.is_changed() {
+        return;
+    }
+
+    let status = match spectate_target.0.and_then(|target| q_ship_owners.get(target).ok()) {
+        Some((Some(player), _)) => format!("Spectating Player {}", player.0),
+        Some((_, Some(bot))) => format!("Spectating Bot {}", bot.0),
+        Some(_) => String::from("Spectating"),
+        None => String::from("Spectating - free camera"),
+    };
+
+    for mut text in &mut q_status_text {
+        text.0 = status.clone();
+    }
+}
@@ -14,7 +14,8 @@ impl Plugin for ThrowawayPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(MaterialPlugin::<CheckerMaterial>::default())
            //.add_systems(Startup, spawn_shader_plane);
-            .add_systems(OnEnter(GameState::Playing), add_sky_to_camera);
+            .add_systems(OnEnter(GameState::Playing), add_sky_to_camera)
+            .add_systems(OnEnter(GameState::Spectating), add_sky_to_camera);
     }
 }
 
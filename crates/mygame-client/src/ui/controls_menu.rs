@@ -0,0 +1,440 @@
+use bevy::{
+    color::palettes::tailwind::{RED_400, SLATE_400, SLATE_800},
+    prelude::*,
+};
+use leafwing_input_manager::prelude::{Buttonlike, InputMap};
+use mygame_protocol::input::NetworkedInput;
+
+use crate::{
+    input::{SystemInput, default_networked_input_map, default_system_input_map},
+    settings::ClientSettings,
+};
+
+use super::settings_menu::SettingsMenuState;
+
+pub struct ControlsMenuPlugin;
+
+impl Plugin for ControlsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<ControlsMenuState>()
+            .add_systems(OnEnter(ControlsMenuState::Open), open_controls_menu)
+            .add_systems(
+                OnEnter(ControlsMenuState::Closed),
+                (close_controls_menu, cancel_rebind),
+            )
+            .add_systems(OnEnter(SettingsMenuState::Closed), set_controls_menu_state_closed)
+            .add_systems(
+                Update,
+                (
+                    capture_rebind.run_if(resource_exists::<PendingRebind>),
+                    update_binding_texts.run_if(
+                        resource_changed::<ClientSettings>.or(resource_exists_and_changed::<PendingRebind>),
+                    ),
+                )
+                    .chain()
+                    .run_if(in_state(ControlsMenuState::Open)),
+            );
+    }
+}
+
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
+pub enum ControlsMenuState {
+    Open,
+    #[default]
+    Closed,
+}
+
+#[derive(Component)]
+pub struct ControlsMenu;
+
+/// Actions that can be rebound from the controls menu.
+/// Aim is an axis driven by the mouse or a stick, so it is not listed here.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RebindableAction {
+    Networked(NetworkedInput),
+    System(SystemInput),
+}
+
+impl RebindableAction {
+    const ALL: [RebindableAction; 6] = [
+        RebindableAction::Networked(NetworkedInput::Fire),
+        RebindableAction::Networked(NetworkedInput::Boost),
+        RebindableAction::System(SystemInput::SystemMenuOrCancel),
+        RebindableAction::System(SystemInput::ToggleNetStats),
+        RebindableAction::System(SystemInput::SpectateNextShip),
+        RebindableAction::System(SystemInput::SpectateFreeCamera),
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            RebindableAction::Networked(NetworkedInput::Aim) => "Aim",
+            RebindableAction::Networked(NetworkedInput::Fire) => "Fire",
+            RebindableAction::Networked(NetworkedInput::Boost) => "Boost",
+            RebindableAction::System(system_input) => system_input.label(),
+        }
+    }
+
+    fn bindings<'a>(&self, settings: &'a ClientSettings) -> &'a [Box<dyn Buttonlike>] {
+        let bindings = match self {
+            RebindableAction::Networked(action) => settings.networked_input_map.get_buttonlike(action),
+            RebindableAction::System(action) => settings.system_input_map.get_buttonlike(action),
+        };
+
+        bindings.map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Replace the binding on the same device as `input`, leaving bindings on other devices alone
+    fn rebind(&self, settings: &mut ClientSettings, input: CapturedInput) {
+        match self {
+            RebindableAction::Networked(action) => {
+                rebind_in_map(&mut settings.networked_input_map, action, input)
+            }
+            RebindableAction::System(action) => {
+                rebind_in_map(&mut settings.system_input_map, action, input)
+            }
+        }
+    }
+}
+
+fn rebind_in_map<A: leafwing_input_manager::Actionlike>(
+    input_map: &mut InputMap<A>,
+    action: &A,
+    input: CapturedInput,
+) {
+    let kept: Vec<CapturedInput> = input_map
+        .get_buttonlike(action)
+        .map(|bindings| {
+            bindings
+                .iter()
+                .filter(|binding| !input.same_device(binding.as_ref()))
+                .filter_map(|binding| CapturedInput::from_binding(binding.as_ref()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    input_map.clear_action(action);
+
+    for binding in std::iter::once(input).chain(kept) {
+        binding.insert_into(input_map, action);
+    }
+}
+
+/// A button press captured while rebinding
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CapturedInput {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+impl CapturedInput {
+    fn name(&self) -> String {
+        match self {
+            CapturedInput::Key(key_code) => format!("{:?}", key_code),
+            CapturedInput::Mouse(mouse_button) => format!("Mouse {:?}", mouse_button),
+            CapturedInput::Gamepad(gamepad_button) => format!("Pad {:?}", gamepad_button),
+        }
+    }
+
+    fn from_binding(binding: &dyn Buttonlike) -> Option<Self> {
+        let reflected = binding.as_reflect();
+
+        if let Some(key_code) = reflected.downcast_ref::<KeyCode>() {
+            Some(CapturedInput::Key(*key_code))
+        } else if let Some(mouse_button) = reflected.downcast_ref::<MouseButton>() {
+            Some(CapturedInput::Mouse(*mouse_button))
+        } else {
+            reflected
+                .downcast_ref::<GamepadButton>()
+                .map(|gamepad_button| CapturedInput::Gamepad(*gamepad_button))
+        }
+    }
+
+    fn insert_into<A: leafwing_input_manager::Actionlike>(&self, input_map: &mut InputMap<A>, action: &A) {
+        match *self {
+            CapturedInput::Key(key_code) => input_map.insert(action.clone(), key_code),
+            CapturedInput::Mouse(mouse_button) => input_map.insert(action.clone(), mouse_button),
+            CapturedInput::Gamepad(gamepad_button) => input_map.insert(action.clone(), gamepad_button),
+        };
+    }
+
+    fn same_device(&self, binding: &dyn Buttonlike) -> bool {
+        let binding_is_gamepad = binding.as_reflect().is::<GamepadButton>();
+        matches!(self, CapturedInput::Gamepad(_)) == binding_is_gamepad
+    }
+}
+
+fn binding_name(binding: &dyn Buttonlike) -> String {
+    match CapturedInput::from_binding(binding) {
+        Some(captured) => captured.name(),
+        None => format!("{:?}", binding),
+    }
+}
+
+/// While this resource exists, the next button press is captured as the new binding for `action`
+#[derive(Resource)]
+pub struct PendingRebind {
+    action: RebindableAction,
+}
+
+/// Feedback for the last rebind attempt, e.g. a conflict with another action
+#[derive(Component)]
+struct RebindStatusText;
+
+#[derive(Component)]
+struct BindingText(RebindableAction);
+
+fn set_controls_menu_state_closed(mut commands: Commands) {
+    commands.set_state(ControlsMenuState::Closed);
+}
+
+fn cancel_rebind(mut commands: Commands) {
+    commands.remove_resource::<PendingRebind>();
+}
+
+fn open_controls_menu(mut commands: Commands, settings: Res<ClientSettings>) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            GlobalZIndex(20),
+            BackgroundColor(Color::BLACK.with_alpha(0.5)),
+            ControlsMenu,
+        ))
+        .with_children(|child_builder| {
+            child_builder
+                .spawn((
+                    Node {
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    BackgroundColor(SLATE_800.into()),
+                ))
+                .with_children(|child_child_builder| {
+                    child_child_builder.spawn((
+                        Text::new("Controls"),
+                        TextFont {
+                            font_size: 30.,
+                            ..default()
+                        },
+                        Node {
+                            padding: UiRect::bottom(Val::Px(20.)),
+                            ..default()
+                        },
+                    ));
+
+                    child_child_builder.spawn((
+                        Text::new("Aim: Mouse, Pad Left Stick"),
+                        TextFont {
+                            font_size: 18.,
+                            ..default()
+                        },
+                        TextColor(SLATE_400.into()),
+                    ));
+
+                    for action in RebindableAction::ALL {
+                        spawn_binding_row(child_child_builder, action, &settings);
+                    }
+
+                    child_child_builder.spawn((
+                        Text::new(""),
+                        TextFont {
+                            font_size: 16.,
+                            ..default()
+                        },
+                        TextColor(RED_400.into()),
+                        RebindStatusText,
+                    ));
+
+                    child_child_builder
+                        .spawn((
+                            Text::new("Reset to Defaults"),
+                            TextFont {
+                                font_size: 20.,
+                                ..default()
+                            },
+                            Node {
+                                padding: UiRect::top(Val::Px(20.)),
+                                ..default()
+                            },
+                        ))
+                        .observe(
+                            |_click: Trigger<Pointer<Click>>, mut settings: ResMut<ClientSettings>| {
+                                settings.networked_input_map = default_networked_input_map();
+                                settings.system_input_map = default_system_input_map();
+                            },
+                        );
+
+                    child_child_builder
+                        .spawn((
+                            Text::new("Back"),
+                            TextFont {
+                                font_size: 30.,
+                                ..default()
+                            },
+                            Node {
+                                padding: UiRect::top(Val::Px(20.)),
+                                ..default()
+                            },
+                        ))
+                        .observe(|_click: Trigger<Pointer<Click>>, mut commands: Commands| {
+                            commands.set_state(ControlsMenuState::Closed);
+                        });
+                });
+        });
+}
+
+fn spawn_binding_row(
+    builder: &mut ChildSpawnerCommands,
+    action: RebindableAction,
+    settings: &ClientSettings,
+) {
+    builder
+        .spawn(Node {
+            width: Val::Px(420.),
+            justify_content: JustifyContent::SpaceBetween,
+            align_items: AlignItems::Center,
+            padding: UiRect::vertical(Val::Px(4.)),
+            ..default()
+        })
+        .with_children(|row_builder| {
+            row_builder.spawn((
+                Text::new(action.label()),
+                TextFont {
+                    font_size: 18.,
+                    ..default()
+                },
+            ));
+
+            row_builder
+                .spawn((
+                    Text::new(bindings_label(action, settings)),
+                    TextFont {
+                        font_size: 18.,
+                        ..default()
+                    },
+                    TextColor(SLATE_400.into()),
+                    BindingText(action),
+                ))
+                .observe(move |_click: Trigger<Pointer<Click>>, mut commands: Commands| {
+                    commands.insert_resource(PendingRebind { action });
+                });
+        });
+}
+
+fn bindings_label(action: RebindableAction, settings: &ClientSettings) -> String {
+    let names: Vec<String> = action
+        .bindings(settings)
+        .iter()
+        .map(|binding| binding_name(binding.as_ref()))
+        .collect();
+
+    if names.is_empty() {
+        String::from("Unbound")
+    } else {
+        names.join(", ")
+    }
+}
+
+fn update_binding_texts(
+    settings: Res<ClientSettings>,
+    pending_rebind: Option<Res<PendingRebind>>,
+    mut q_binding_texts: Query<(&mut Text, &BindingText)>,
+) {
+    for (mut text, binding_text) in &mut q_binding_texts {
+        text.0 = match &pending_rebind {
+            Some(pending_rebind) if pending_rebind.action == binding_text.0 => {
+                String::from("Press a button... (Esc to cancel)")
+            }
+            _ => bindings_label(binding_text.0, &settings),
+        };
+    }
+}
+
+fn capture_rebind(
+    mut commands: Commands,
+    pending_rebind: Res<PendingRebind>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    q_gamepads: Query<&Gamepad>,
+    mut settings: ResMut<ClientSettings>,
+    mut q_status_text: Query<&mut Text, With<RebindStatusText>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) {
+        commands.remove_resource::<PendingRebind>();
+        return;
+    }
+
+    let captured = keys
+        .get_just_pressed()
+        .next()
+        .map(|key_code| CapturedInput::Key(*key_code))
+        .or_else(|| {
+            mouse_buttons
+                .get_just_pressed()
+                .next()
+                .map(|mouse_button| CapturedInput::Mouse(*mouse_button))
+        })
+        .or_else(|| {
+            q_gamepads.iter().find_map(|gamepad| {
+                gamepad
+                    .get_just_pressed()
+                    .next()
+                    .map(|gamepad_button| CapturedInput::Gamepad(*gamepad_button))
+            })
+        });
+
+    let Some(captured) = captured else {
+        return;
+    };
+
+    commands.remove_resource::<PendingRebind>();
+
+    let status = match find_conflict(&settings, pending_rebind.action, captured) {
+        Some(conflicting_action) => format!(
+            "{} is already bound to {}",
+            captured.name(),
+            conflicting_action.label()
+        ),
+        None => {
+            pending_rebind.action.rebind(&mut settings, captured);
+            String::new()
+        }
+    };
+
+    for mut text in &mut q_status_text {
+        text.0 = status.clone();
+    }
+}
+
+/// Any other action already using `input`, across both the networked and system input maps
+fn find_conflict(
+    settings: &ClientSettings,
+    rebinding: RebindableAction,
+    input: CapturedInput,
+) -> Option<RebindableAction> {
+    RebindableAction::ALL
+        .into_iter()
+        .filter(|action| *action != rebinding)
+        .find(|action| {
+            action
+                .bindings(settings)
+                .iter()
+                .any(|binding| CapturedInput::from_binding(binding.as_ref()) == Some(input))
+        })
+}
+
+fn close_controls_menu(mut commands: Commands, q_controls_menu: Query<Entity, With<ControlsMenu>>) {
+    for controls_menu in &q_controls_menu {
+        commands.entity(controls_menu).despawn_recursive();
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use lightyear::prelude::client::Predicted;
+use mygame_protocol::component::{Flag, FlagState, Team};
+use mygame_render::{camera::MainCamera, teams::team_color};
+
+use crate::game_state::GameState;
+
+/// A marker over each flag in capture the flag, saying where it is. When the flag is off screen
+/// the marker sticks to the edge of the screen with an arrow pointing the way.
+pub struct FlagMarkersPlugin;
+
+impl Plugin for FlagMarkersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            (spawn_flag_markers, update_flag_markers)
+                .chain()
+                .after(TransformSystem::TransformPropagate)
+                .run_if(in_state(GameState::Playing).or(in_state(GameState::Spectating))),
+        );
+    }
+}
+
+const MARKER_WIDTH: f32 = 140.0;
+const MARKER_HEIGHT: f32 = 40.0;
+/// How far from the edge of the screen markers for off screen flags sit
+const SCREEN_EDGE_MARGIN: f32 = 40.0;
+/// Markers float this far above the flag
+const MARKER_HEIGHT_ABOVE_FLAG: f32 = 4.0;
+
+#[derive(Component)]
+struct FlagMarker(Entity);
+
+#[derive(Component)]
+struct FlagMarkerText;
+
+#[derive(Component)]
+struct FlagMarkerArrow;
+
+fn spawn_flag_markers(
+    mut commands: Commands,
+    game_state: Res<State<GameState>>,
+    q_flags: Query<(Entity, &Team), (With<Flag>, With<Predicted>)>,
+    q_markers: Query<(Entity, &FlagMarker)>,
+) {
+    let mut marked_flags = HashSet::new();
+
+    for (marker, flag_marker) in &q_markers {
+        if q_flags.contains(flag_marker.0) {
+            marked_flags.insert(flag_marker.0);
+        } else {
+            commands.entity(marker).despawn();
+        }
+    }
+
+    for (flag, team) in &q_flags {
+        if marked_flags.contains(&flag) {
+            continue;
+        }
+
+        commands
+            .spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    width: Val::Px(MARKER_WIDTH),
+                    height: Val::Px(MARKER_HEIGHT),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                Pickable::IGNORE,
+                FlagMarker(flag),
+                StateScoped(game_state.get().clone()),
+            ))
+            .with_children(|child_builder| {
+                child_builder.spawn((
+                    Text::default(),
+                    TextFont {
+                        font_size: 14.,
+                        ..default()
+                    },
+                    TextColor(team_color(*team)),
+                    FlagMarkerText,
+                ));
+
+                child_builder.spawn((
+                    Text::new(">"),
+                    TextFont {
+                        font_size: 20.,
+                        ..default()
+                    },
+                    TextColor(team_color(*team)),
+                    Visibility::Hidden,
+                    FlagMarkerArrow,
+                ));
+            });
+    }
+}
+
+fn flag_status(team: Team, flag_state: FlagState) -> String {
+    match flag_state {
+        FlagState::AtBase => format!("{} flag", team.name()),
+        FlagState::Carried(_) => format!("{} flag - taken", team.name()),
+        FlagState::Dropped => format!("{} flag - dropped", team.name()),
+    }
+}
+
+fn update_flag_markers(
+    q_camera: Single<(&Camera, &GlobalTransform), With<MainCamera>>,
+    q_flags: Query<(&Team, &FlagState, &GlobalTransform), With<Flag>>,
+    mut q_markers: Query<(&FlagMarker, &mut Node, &Children)>,
+    mut q_texts: Query<&mut Text, With<FlagMarkerText>>,
+    mut q_arrows: Query<(&mut Visibility, &mut Transform), With<FlagMarkerArrow>>,
+) {
+    let (camera, camera_transform) = *q_camera;
+
+    let Some(viewport_size) = camera.logical_viewport_size() else {
+        return;
+    };
+
+    let half_size = viewport_size / 2.0;
+
+    for (flag_marker, mut node, children) in &mut q_markers {
+        let Ok((team, flag_state, flag_transform)) = q_flags.get(flag_marker.0) else {
+            continue;
+        };
+
+        let world_position = flag_transform.translation() + Vec3::Y * MARKER_HEIGHT_ABOVE_FLAG;
+
+        let on_screen = camera
+            .world_to_viewport(camera_transform, world_position)
+            .ok()
+            .filter(|position| {
+                position.cmpge(Vec2::splat(SCREEN_EDGE_MARGIN)).all()
+                    && position.cmple(viewport_size - SCREEN_EDGE_MARGIN).all()
+            });
+
+        let (screen_position, arrow_angle) = match on_screen {
+            Some(position) => (position, None),
+            None => {
+                // Which way the flag is, in screen space (y down). Behind the camera still
+                // points the right way left/right, which is what matters for turning around.
+                let local = camera_transform
+                    .affine()
+                    .inverse()
+                    .transform_point3(world_position);
+                let direction = Vec2::new(local.x, -local.y).try_normalize().unwrap_or(Vec2::Y);
+
+                let edge = half_size - SCREEN_EDGE_MARGIN;
+                let scale = (edge.x / direction.x.abs()).min(edge.y / direction.y.abs());
+
+                (
+                    half_size + direction * scale,
+                    Some(direction.y.atan2(direction.x)),
+                )
+            }
+        };
+
+        node.left = Val::Px(screen_position.x - MARKER_WIDTH / 2.0);
+        node.top = Val::Px(screen_position.y - MARKER_HEIGHT / 2.0);
+
+        for child in children.iter() {
+            if let Ok(mut text) = q_texts.get_mut(child) {
+                let status = flag_status(*team, *flag_state);
+                if text.0 != status {
+                    text.0 = status;
+                }
+            }
+
+            if let Ok((mut visibility, mut transform)) = q_arrows.get_mut(child) {
+                match arrow_angle {
+                    Some(angle) => {
+                        *visibility = Visibility::Inherited;
+                        transform.rotation = Quat::from_rotation_z(angle);
+                    }
+                    None => *visibility = Visibility::Hidden,
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use bevy::{color::palettes::tailwind::SLATE_400, prelude::*};
+
+use crate::host::HostInfo;
+
+/// Lists what other players need to join the server this client is hosting: the addresses to
+/// connect to, and the certificate digest for browsers
+pub(crate) fn spawn_host_panel(builder: &mut ChildSpawnerCommands, host_info: &HostInfo) {
+    builder
+        .spawn(Node {
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            padding: UiRect::vertical(Val::Px(20.)),
+            ..default()
+        })
+        .with_children(|panel_builder| {
+            panel_builder.spawn((
+                Text::new(format!(
+                    "Hosting on UDP port {} and WebTransport port {}",
+                    host_info.ports.udp, host_info.ports.webtransport
+                )),
+                TextFont {
+                    font_size: 16.,
+                    ..default()
+                },
+            ));
+
+            let addresses = if host_info.lan_addresses.is_empty() {
+                String::from("No network found, only this machine can join")
+            } else {
+                let addresses: Vec<String> = host_info
+                    .lan_addresses
+                    .iter()
+                    .map(|address| format!("{}:{}", address, host_info.ports.udp))
+                    .collect();
+                format!("Players on your network can join at {}", addresses.join(", "))
+            };
+
+            panel_builder.spawn((
+                Text::new(addresses),
+                TextFont {
+                    font_size: 16.,
+                    ..default()
+                },
+            ));
+
+            if let Some(digest) = &host_info.certificate_digest {
+                panel_builder.spawn((
+                    Text::new(format!("Certificate digest for browsers: {}", digest)),
+                    TextFont {
+                        font_size: 12.,
+                        ..default()
+                    },
+                    TextColor(SLATE_400.into()),
+                ));
+            }
+        });
+}
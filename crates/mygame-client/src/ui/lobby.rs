@@ -0,0 +1,369 @@
+use std::time::Duration;
+
+use bevy::{
+    color::palettes::tailwind::{EMERALD_400, SLATE_400, SLATE_800},
+    prelude::*,
+};
+use lightyear::prelude::{
+    ClientConnectionManager, ClientReceiveMessage,
+    client::{ClientConnection, NetClient},
+};
+use mygame_protocol::message::{
+    ClientHostMatchSettings, ClientSetReady, GameMode, Level, MatchSettings, Reliable, ServerLobby,
+};
+
+#[cfg(feature = "host")]
+use crate::host::HostInfo;
+use crate::game_state::GameState;
+
+/// Where players wait for a hosted match to start: who's connected and ready, the settings the
+/// host has picked, and, for the host, the controls to change them
+pub struct LobbyPlugin;
+
+impl Plugin for LobbyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Lobby>()
+            .add_systems(OnEnter(GameState::MainMenu), reset_lobby)
+            .add_systems(OnEnter(GameState::Lobby), spawn_lobby_ui)
+            .add_systems(
+                Update,
+                (
+                    receive_lobby,
+                    update_lobby_text.run_if(in_state(GameState::Lobby)),
+                )
+                    .chain(),
+            );
+    }
+}
+
+const BOT_COUNTS: [u32; 5] = [0, 2, 4, 8, 16];
+const TIME_LIMITS: [Option<Duration>; 4] = [
+    None,
+    Some(Duration::from_secs(5 * 60)),
+    Some(Duration::from_secs(10 * 60)),
+    Some(Duration::from_secs(20 * 60)),
+];
+
+/// The lobby as the server last described it
+#[derive(Resource, Default)]
+struct Lobby(ServerLobby);
+
+/// One of the match settings, shown as a line of text the host can click to change
+#[derive(Component, Clone, Copy)]
+enum LobbySetting {
+    Level,
+    Mode,
+    Bots,
+    TimeLimit,
+    FriendlyFire,
+}
+
+impl LobbySetting {
+    const ALL: [LobbySetting; 5] = [
+        LobbySetting::Level,
+        LobbySetting::Mode,
+        LobbySetting::Bots,
+        LobbySetting::TimeLimit,
+        LobbySetting::FriendlyFire,
+    ];
+
+    fn label(&self, settings: &MatchSettings) -> String {
+        match self {
+            LobbySetting::Level => format!("Level: {}", settings.level.name()),
+            LobbySetting::Mode => format!("Mode: {}", settings.rules.mode.name()),
+            LobbySetting::Bots => format!("Bots: {}", settings.bot_count),
+            LobbySetting::TimeLimit => match settings.time_limit {
+                Some(time_limit) => format!("Time limit: {} minutes", time_limit.as_secs() / 60),
+                None => String::from("Time limit: none"),
+            },
+            LobbySetting::FriendlyFire => format!(
+                "Friendly fire: {}",
+                if settings.rules.friendly_fire {
+                    "on"
+                } else {
+                    "off"
+                }
+            ),
+        }
+    }
+
+    /// Move on to the next choice for this setting, wrapping around
+    fn cycle(&self, settings: &mut MatchSettings) {
+        match self {
+            LobbySetting::Level => settings.level = next_after(&Level::PLAYABLE, settings.level),
+            LobbySetting::Mode => {
+                settings.rules.mode = next_after(&GameMode::ALL, settings.rules.mode)
+            }
+            LobbySetting::Bots => settings.bot_count = next_after(&BOT_COUNTS, settings.bot_count),
+            LobbySetting::TimeLimit => {
+                settings.time_limit = next_after(&TIME_LIMITS, settings.time_limit)
+            }
+            LobbySetting::FriendlyFire => {
+                settings.rules.friendly_fire = !settings.rules.friendly_fire
+            }
+        }
+    }
+}
+
+fn next_after<T: PartialEq + Copy>(options: &[T], current: T) -> T {
+    let next = options
+        .iter()
+        .position(|option| *option == current)
+        .map_or(0, |index| (index + 1) % options.len());
+
+    options[next]
+}
+
+#[derive(Component)]
+struct LobbyPlayersText;
+
+#[derive(Component)]
+struct ReadyButton;
+
+/// Tells the player whether they're the one picking the settings
+#[derive(Component)]
+struct LobbyHintText;
+
+fn reset_lobby(mut lobby: ResMut<Lobby>) {
+    *lobby = Lobby::default();
+}
+
+/// The server describes the lobby to clients that connect before the match starts. Everyone
+/// is moved on to Loading by the ServerWelcome once it does.
+fn receive_lobby(
+    mut lobby_events: EventReader<ClientReceiveMessage<ServerLobby>>,
+    mut lobby: ResMut<Lobby>,
+    game_state: Res<State<GameState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+) {
+    for ev in lobby_events.read() {
+        lobby.0 = ev.message.clone();
+
+        match **game_state {
+            GameState::ConnectingRemote | GameState::Reconnecting => {
+                next_game_state.set(GameState::Lobby)
+            }
+            #[cfg(feature = "host")]
+            GameState::ConnectingSelf => next_game_state.set(GameState::Lobby),
+            _ => {}
+        }
+    }
+}
+
+fn spawn_lobby_ui(
+    mut commands: Commands,
+    #[cfg(feature = "host")] host_info: Option<Res<HostInfo>>,
+) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            BackgroundColor(SLATE_800.into()),
+            StateScoped(GameState::Lobby),
+        ))
+        .with_children(|child_builder| {
+            child_builder.spawn((
+                Text::new("Lobby"),
+                TextFont {
+                    font_size: 30.,
+                    ..default()
+                },
+                Node {
+                    padding: UiRect::bottom(Val::Px(20.)),
+                    ..default()
+                },
+            ));
+
+            child_builder.spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 14.,
+                    ..default()
+                },
+                TextColor(SLATE_400.into()),
+                LobbyHintText,
+            ));
+
+            for setting in LobbySetting::ALL {
+                child_builder
+                    .spawn((
+                        Text::default(),
+                        TextFont {
+                            font_size: 20.,
+                            ..default()
+                        },
+                        setting,
+                    ))
+                    .observe(on_setting_clicked);
+            }
+
+            child_builder.spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 16.,
+                    ..default()
+                },
+                Node {
+                    padding: UiRect::vertical(Val::Px(20.)),
+                    ..default()
+                },
+                LobbyPlayersText,
+            ));
+
+            // Everyone else needs to know where to find the host
+            #[cfg(feature = "host")]
+            if let Some(host_info) = &host_info {
+                super::host_panel::spawn_host_panel(child_builder, host_info);
+            }
+
+            child_builder
+                .spawn((
+                    Text::default(),
+                    Node {
+                        padding: UiRect::bottom(Val::Px(20.)),
+                        ..default()
+                    },
+                    ReadyButton,
+                ))
+                .observe(on_ready_clicked);
+
+            child_builder.spawn(Text::new("Main Menu")).observe(
+                |_click: Trigger<Pointer<Click>>, mut commands: Commands| {
+                    commands.set_state(GameState::MainMenu);
+                },
+            );
+        });
+}
+
+fn is_ready(lobby: &Lobby, client: &ClientConnection) -> bool {
+    lobby
+        .0
+        .players
+        .iter()
+        .any(|player| player.id == client.id() && player.ready)
+}
+
+fn on_ready_clicked(
+    _click: Trigger<Pointer<Click>>,
+    lobby: Res<Lobby>,
+    client: Res<ClientConnection>,
+    mut client_manager: ResMut<ClientConnectionManager>,
+) {
+    let ready = !is_ready(&lobby, &client);
+
+    if let Err(e) = client_manager.send_message::<Reliable, ClientSetReady>(&ClientSetReady(ready))
+    {
+        error!("unable to send ready status: {}", e);
+    }
+}
+
+fn on_setting_clicked(
+    trigger: Trigger<Pointer<Click>>,
+    q_settings: Query<&LobbySetting>,
+    lobby: Res<Lobby>,
+    client: Res<ClientConnection>,
+    mut client_manager: ResMut<ClientConnectionManager>,
+) {
+    // Only the host picks the settings
+    if lobby.0.host != Some(client.id()) {
+        return;
+    }
+
+    let Ok(setting) = q_settings.get(trigger.target()) else {
+        return;
+    };
+
+    let mut settings = lobby.0.settings;
+    setting.cycle(&mut settings);
+
+    if let Err(e) = client_manager
+        .send_message::<Reliable, ClientHostMatchSettings>(&ClientHostMatchSettings(settings))
+    {
+        error!("unable to send match settings: {}", e);
+    }
+}
+
+fn set_text(text: &mut Text, content: String) {
+    if text.0 != content {
+        text.0 = content;
+    }
+}
+
+fn update_lobby_text(
+    lobby: Res<Lobby>,
+    client: Res<ClientConnection>,
+    mut q_setting_texts: Query<(&LobbySetting, &mut Text)>,
+    mut q_players_text: Query<
+        &mut Text,
+        (
+            With<LobbyPlayersText>,
+            Without<LobbySetting>,
+            Without<ReadyButton>,
+        ),
+    >,
+    mut q_ready_button: Query<
+        (&mut Text, &mut TextColor),
+        (
+            With<ReadyButton>,
+            Without<LobbySetting>,
+            Without<LobbyHintText>,
+        ),
+    >,
+    mut q_hint_text: Query<
+        &mut Text,
+        (
+            With<LobbyHintText>,
+            Without<LobbySetting>,
+            Without<LobbyPlayersText>,
+        ),
+    >,
+) {
+    let is_host = lobby.0.host == Some(client.id());
+
+    for (setting, mut text) in &mut q_setting_texts {
+        set_text(&mut text, setting.label(&lobby.0.settings));
+    }
+
+    for mut text in &mut q_hint_text {
+        let hint = if is_host {
+            "Click a setting to change it. The match starts when everyone is ready."
+        } else {
+            "The host picks the settings. The match starts when everyone is ready."
+        };
+        set_text(&mut text, String::from(hint));
+    }
+
+    for mut text in &mut q_players_text {
+        let mut lines = vec![String::from("Players")];
+        lines.extend(lobby.0.players.iter().map(|player| {
+            format!(
+                "Player {}{}  {}",
+                player.id,
+                if lobby.0.host == Some(player.id) {
+                    " (host)"
+                } else {
+                    ""
+                },
+                if player.ready { "Ready" } else { "Not ready" }
+            )
+        }));
+        set_text(&mut text, lines.join("\n"));
+    }
+
+    let ready = is_ready(&lobby, &client);
+    for (mut text, mut color) in &mut q_ready_button {
+        if ready {
+            set_text(&mut text, String::from("Ready! Click to wait"));
+            color.0 = EMERALD_400.into();
+        } else {
+            set_text(&mut text, String::from("Ready up"));
+            color.0 = Color::WHITE;
+        }
+    }
+}
@@ -1,7 +1,37 @@
-use bevy::{color::palettes::tailwind::SLATE_800, prelude::*};
+#[cfg(not(target_family = "wasm"))]
+use std::net::SocketAddr;
+
+use bevy::{
+    color::palettes::tailwind::{AMBER_400, SLATE_400, SLATE_800},
+    prelude::*,
+};
 use lightyear::prelude::client::ClientCommandsExt;
+use mygame_common::LaunchConfigurations;
+
+use crate::{
+    game_state::GameState,
+    network::{
+        MatchList, Reconnect, RecentServers, RemoteServerAddress, configured_server_address,
+        parse_server_address,
+    },
+    spectator::JoinAsSpectator,
+};
+
+#[cfg(not(target_family = "wasm"))]
+use bevy::tasks::{Task, block_on, futures_lite::future};
+
+#[cfg(not(target_family = "wasm"))]
+use crate::{discovery::LanGames, network::lookup_server_address};
+#[cfg(feature = "host")]
+use crate::host::{HostPorts, HostedServer};
+
+use super::{
+    settings_menu::SettingsMenuState, shutdown_notice::ShutdownNotice, text_input::TextInput,
+};
 
-use crate::game_state::GameState;
+const SERVER_ADDRESS_MAX_LENGTH: usize = 64;
+#[cfg(feature = "host")]
+const PORT_MAX_LENGTH: usize = 5;
 
 pub struct MainMenuPlugin;
 
@@ -14,18 +44,50 @@ impl Plugin for MainMenuPlugin {
             (despawn_main_menu_buttons, on_client_begin_connecting).chain(),
         );
         #[cfg(feature = "host")]
+        app.add_systems(OnEnter(GameState::Hosting), on_client_waiting_to_host);
+        #[cfg(feature = "host")]
         app.add_systems(
             OnEnter(GameState::ConnectingSelf),
             (despawn_main_menu_buttons, on_client_begin_hosting).chain(),
         );
+        app.add_systems(OnEnter(GameState::Reconnecting), spawn_reconnecting_ui);
+        app.add_systems(
+            Update,
+            update_reconnecting_status
+                .run_if(in_state(GameState::Reconnecting).and(resource_changed::<Reconnect>)),
+        );
+        #[cfg(not(target_family = "wasm"))]
+        app.add_systems(
+            Update,
+            update_lan_games_list
+                .run_if(in_state(GameState::MainMenu).and(resource_changed::<LanGames>)),
+        );
+        #[cfg(not(target_family = "wasm"))]
+        app.add_systems(
+            Update,
+            finish_server_lookup.run_if(
+                in_state(GameState::MainMenu).and(resource_exists::<ServerAddressLookup>),
+            ),
+        )
+        .add_systems(OnExit(GameState::MainMenu), cancel_server_lookup);
         app.add_systems(OnEnter(GameState::Loading), on_client_begin_loading);
+        app.add_systems(OnEnter(GameState::Lobby), despawn_main_menu_ui);
         app.add_systems(OnEnter(GameState::Playing), despawn_main_menu_ui);
+        app.add_systems(OnEnter(GameState::Spectating), despawn_main_menu_ui);
     }
 }
 
 #[derive(Component)]
 pub struct MainMenu;
 
+/// A hostname the player asked to connect to, still being looked up
+#[cfg(not(target_family = "wasm"))]
+#[derive(Resource)]
+struct ServerAddressLookup {
+    input: String,
+    task: Task<Option<SocketAddr>>,
+}
+
 #[derive(Component)]
 pub struct MainMenuStatusText;
 
@@ -36,12 +98,53 @@ pub struct ConnectButton;
 #[derive(Component)]
 pub struct HostButton;
 
-fn spawn_main_menu_ui(mut commands: Commands, q_main_menu: Query<Entity, With<MainMenu>>) {
+/// Where the host picks the ports their server listens on
+#[cfg(feature = "host")]
+#[derive(Component)]
+pub enum HostPortInput {
+    Udp,
+    WebTransport,
+}
+
+#[derive(Component)]
+pub struct SettingsButton;
+
+/// Container for the direct-connect address field and the recent servers list
+#[derive(Component)]
+pub struct DirectConnect;
+
+#[derive(Component)]
+pub struct ServerAddressInput;
+
+/// Filled in with the games found on the local network as they answer
+#[cfg(not(target_family = "wasm"))]
+#[derive(Component)]
+pub struct LanGamesList;
+
+fn spawn_main_menu_ui(
+    mut commands: Commands,
+    q_main_menu: Query<Entity, With<MainMenu>>,
+    recent_servers: Res<RecentServers>,
+    match_list: Res<MatchList>,
+    launch_configurations: Res<LaunchConfigurations>,
+    shutdown_notice: Res<ShutdownNotice>,
+    #[cfg(feature = "host")] host_ports: Res<HostPorts>,
+) {
     // Despawn any existing copies of the menu
     for entity in &q_main_menu {
         commands.entity(entity).despawn_recursive();
     }
 
+    // Prefer whatever the player connected to last, otherwise what the launcher was configured with
+    let default_address = recent_servers.0.first().cloned().unwrap_or_else(|| {
+        launch_configurations
+            .client_remote_config
+            .as_ref()
+            .and_then(configured_server_address)
+            .map(|addr| addr.to_string())
+            .unwrap_or_default()
+    });
+
     commands
         .spawn((
             Node {
@@ -70,6 +173,132 @@ fn spawn_main_menu_ui(mut commands: Commands, q_main_menu: Query<Entity, With<Ma
                 ))
                 .insert(MainMenuStatusText);
 
+            // Why the last server went away, if it told us
+            if let Some(reason) = shutdown_notice.reason {
+                child_builder.spawn((
+                    Text::new(reason.message()),
+                    TextColor(AMBER_400.into()),
+                    Node {
+                        padding: UiRect::bottom(Val::Px(20.)),
+                        ..default()
+                    },
+                ));
+            }
+
+            child_builder
+                .spawn((
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::bottom(Val::Px(20.)),
+                        ..default()
+                    },
+                    DirectConnect,
+                ))
+                .with_children(|direct_connect_builder| {
+                    direct_connect_builder.spawn((
+                        Text::new("Server address"),
+                        TextFont {
+                            font_size: 14.,
+                            ..default()
+                        },
+                        TextColor(SLATE_400.into()),
+                    ));
+
+                    direct_connect_builder.spawn((
+                        TextInput::new(default_address, SERVER_ADDRESS_MAX_LENGTH),
+                        ServerAddressInput,
+                        Node {
+                            min_width: Val::Px(240.),
+                            padding: UiRect::all(Val::Px(5.)),
+                            margin: UiRect::vertical(Val::Px(5.)),
+                            ..default()
+                        },
+                    ));
+
+                    if !recent_servers.0.is_empty() {
+                        direct_connect_builder.spawn((
+                            Text::new("Recent servers"),
+                            TextFont {
+                                font_size: 14.,
+                                ..default()
+                            },
+                            TextColor(SLATE_400.into()),
+                        ));
+                    }
+
+                    for recent_server in &recent_servers.0 {
+                        let address = recent_server.clone();
+
+                        direct_connect_builder
+                            .spawn((
+                                Text::new(recent_server.clone()),
+                                TextFont {
+                                    font_size: 16.,
+                                    ..default()
+                                },
+                            ))
+                            .observe(
+                                move |_click: Trigger<Pointer<Click>>,
+                                      mut q_address_input: Query<&mut TextInput, With<ServerAddressInput>>| {
+                                    for mut address_input in &mut q_address_input {
+                                        address_input.value = address.clone();
+                                    }
+                                },
+                            );
+                    }
+
+                    #[cfg(not(target_family = "wasm"))]
+                    direct_connect_builder.spawn((
+                        Node {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        LanGamesList,
+                    ));
+
+                    if !match_list.matches.is_empty() {
+                        direct_connect_builder.spawn((
+                            Text::new("Matches on the last server"),
+                            TextFont {
+                                font_size: 14.,
+                                ..default()
+                            },
+                            TextColor(SLATE_400.into()),
+                        ));
+                    }
+
+                    for listing in &match_list.matches {
+                        let Some(address) = match_list.address(listing) else {
+                            continue;
+                        };
+                        let address = address.to_string();
+
+                        direct_connect_builder
+                            .spawn((
+                                Text::new(format!(
+                                    "{}  {}  {} players",
+                                    listing.name,
+                                    listing.mode.name(),
+                                    listing.players
+                                )),
+                                TextFont {
+                                    font_size: 16.,
+                                    ..default()
+                                },
+                            ))
+                            .observe(
+                                move |_click: Trigger<Pointer<Click>>,
+                                      mut q_address_input: Query<&mut TextInput, With<ServerAddressInput>>| {
+                                    for mut address_input in &mut q_address_input {
+                                        address_input.value = address.clone();
+                                    }
+                                },
+                            );
+                    }
+                });
+
             child_builder
                 .spawn((
                     Text::new("Connect"),
@@ -79,23 +308,343 @@ fn spawn_main_menu_ui(mut commands: Commands, q_main_menu: Query<Entity, With<Ma
                     },
                 ))
                 .insert(ConnectButton)
-                .observe(|_click: Trigger<Pointer<Click>>, mut commands: Commands| {
-                    commands.set_state(GameState::ConnectingRemote);
+                .observe(on_connect_clicked);
+
+            child_builder
+                .spawn((
+                    Text::new("Spectate"),
+                    Node {
+                        padding: UiRect::bottom(Val::Px(20.)),
+                        ..default()
+                    },
+                ))
+                .insert(ConnectButton)
+                .observe(on_spectate_clicked);
+
+            #[cfg(feature = "host")]
+            child_builder
+                .spawn((
+                    Node {
+                        column_gap: Val::Px(10.),
+                        align_items: AlignItems::Center,
+                        padding: UiRect::bottom(Val::Px(5.)),
+                        ..default()
+                    },
+                    HostButton,
+                ))
+                .with_children(|host_ports_builder| {
+                    host_ports_builder.spawn((
+                        Text::new("UDP port"),
+                        TextFont {
+                            font_size: 14.,
+                            ..default()
+                        },
+                        TextColor(SLATE_400.into()),
+                    ));
+
+                    host_ports_builder.spawn((
+                        TextInput::new(host_ports.udp.to_string(), PORT_MAX_LENGTH),
+                        HostPortInput::Udp,
+                        Node {
+                            min_width: Val::Px(60.),
+                            padding: UiRect::all(Val::Px(5.)),
+                            ..default()
+                        },
+                    ));
+
+                    host_ports_builder.spawn((
+                        Text::new("WebTransport port"),
+                        TextFont {
+                            font_size: 14.,
+                            ..default()
+                        },
+                        TextColor(SLATE_400.into()),
+                    ));
+
+                    host_ports_builder.spawn((
+                        TextInput::new(host_ports.webtransport.to_string(), PORT_MAX_LENGTH),
+                        HostPortInput::WebTransport,
+                        Node {
+                            min_width: Val::Px(60.),
+                            padding: UiRect::all(Val::Px(5.)),
+                            ..default()
+                        },
+                    ));
                 });
 
             #[cfg(feature = "host")]
             child_builder
-                .spawn(Text::new("Host"))
+                .spawn((
+                    Text::new("Host"),
+                    Node {
+                        padding: UiRect::bottom(Val::Px(20.)),
+                        ..default()
+                    },
+                ))
                 .insert(HostButton)
+                .observe(on_host_clicked);
+
+            child_builder
+                .spawn(Text::new("Settings"))
+                .insert(SettingsButton)
+                .observe(|_click: Trigger<Pointer<Click>>, mut commands: Commands| {
+                    commands.set_state(SettingsMenuState::Open);
+                });
+        });
+}
+
+/// A stripped down main menu shown while retrying a lost server.
+/// Tagged MainMenu so it's replaced or despawned exactly like the real one.
+fn spawn_reconnecting_ui(
+    mut commands: Commands,
+    q_main_menu: Query<Entity, With<MainMenu>>,
+    reconnect: Res<Reconnect>,
+) {
+    for entity in &q_main_menu {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            BackgroundColor(SLATE_800.into()),
+            MainMenu,
+        ))
+        .with_children(|child_builder| {
+            child_builder
+                .spawn((
+                    Text::new(reconnecting_status(&reconnect)),
+                    TextFont {
+                        font_size: 30.,
+                        ..default()
+                    },
+                    Node {
+                        padding: UiRect::bottom(Val::Px(200.)),
+                        ..default()
+                    },
+                ))
+                .insert(MainMenuStatusText);
+
+            child_builder
+                .spawn(Text::new("Cancel"))
                 .observe(|_click: Trigger<Pointer<Click>>, mut commands: Commands| {
-                    commands.set_state(GameState::Hosting);
+                    commands.set_state(GameState::MainMenu);
                 });
         });
 }
 
+fn reconnecting_status(reconnect: &Reconnect) -> String {
+    format!("Reconnecting ({})…", reconnect.attempt)
+}
+
+fn update_reconnecting_status(
+    reconnect: Res<Reconnect>,
+    mut q_status_text: Query<&mut Text, With<MainMenuStatusText>>,
+) {
+    for mut text in q_status_text.iter_mut() {
+        text.0 = reconnecting_status(&reconnect);
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn update_lan_games_list(
+    mut commands: Commands,
+    lan_games: Res<LanGames>,
+    q_lan_games_list: Query<Entity, With<LanGamesList>>,
+) {
+    for list in &q_lan_games_list {
+        commands
+            .entity(list)
+            .despawn_related::<Children>()
+            .with_children(|list_builder| {
+                if !lan_games.0.is_empty() {
+                    list_builder.spawn((
+                        Text::new("LAN games"),
+                        TextFont {
+                            font_size: 14.,
+                            ..default()
+                        },
+                        TextColor(SLATE_400.into()),
+                    ));
+                }
+
+                for game in &lan_games.0 {
+                    let address = game.addr.to_string();
+                    let reply = &game.reply;
+
+                    list_builder
+                        .spawn((
+                            Text::new(format!(
+                                "{}  {}  {}  {} players{}",
+                                reply.name,
+                                reply.level.name(),
+                                reply.mode.name(),
+                                reply.players,
+                                if reply.in_lobby { "  in lobby" } else { "" }
+                            )),
+                            TextFont {
+                                font_size: 16.,
+                                ..default()
+                            },
+                        ))
+                        .observe(
+                            move |_click: Trigger<Pointer<Click>>,
+                                  mut q_address_input: Query<&mut TextInput, With<ServerAddressInput>>| {
+                                for mut address_input in &mut q_address_input {
+                                    address_input.value = address.clone();
+                                }
+                            },
+                        );
+                }
+            });
+    }
+}
+
+fn on_connect_clicked(
+    _click: Trigger<Pointer<Click>>,
+    mut commands: Commands,
+    q_address_input: Query<&TextInput, With<ServerAddressInput>>,
+    mut q_status_text: Query<&mut Text, With<MainMenuStatusText>>,
+    mut remote_server_address: ResMut<RemoteServerAddress>,
+    mut join_as_spectator: ResMut<JoinAsSpectator>,
+) {
+    join_as_spectator.0 = false;
+    connect_to_entered_address(&mut commands, &q_address_input, &mut q_status_text, &mut remote_server_address);
+}
+
+fn on_spectate_clicked(
+    _click: Trigger<Pointer<Click>>,
+    mut commands: Commands,
+    q_address_input: Query<&TextInput, With<ServerAddressInput>>,
+    mut q_status_text: Query<&mut Text, With<MainMenuStatusText>>,
+    mut remote_server_address: ResMut<RemoteServerAddress>,
+    mut join_as_spectator: ResMut<JoinAsSpectator>,
+) {
+    join_as_spectator.0 = true;
+    connect_to_entered_address(&mut commands, &q_address_input, &mut q_status_text, &mut remote_server_address);
+}
+
+fn connect_to_entered_address(
+    commands: &mut Commands,
+    q_address_input: &Query<&TextInput, With<ServerAddressInput>>,
+    q_status_text: &mut Query<&mut Text, With<MainMenuStatusText>>,
+    remote_server_address: &mut RemoteServerAddress,
+) {
+    let Ok(address_input) = q_address_input.single() else {
+        return;
+    };
+
+    let input = address_input.value.trim().to_string();
+
+    if let Some(addr) = parse_server_address(&input) {
+        remote_server_address.0 = Some((input, addr));
+        commands.set_state(GameState::ConnectingRemote);
+        return;
+    }
+
+    // Not an IP address, so it's a hostname to look up without holding up the frame
+    #[cfg(not(target_family = "wasm"))]
+    {
+        for mut text in q_status_text.iter_mut() {
+            text.0 = format!("Looking up \"{}\"", input);
+        }
+
+        // Replaces any lookup still going from an earlier click
+        commands.insert_resource(ServerAddressLookup {
+            task: lookup_server_address(&input),
+            input,
+        });
+    }
+
+    #[cfg(target_family = "wasm")]
+    for mut text in q_status_text.iter_mut() {
+        text.0 = format!("Invalid server address \"{}\"", input);
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn finish_server_lookup(
+    mut commands: Commands,
+    mut lookup: ResMut<ServerAddressLookup>,
+    mut q_status_text: Query<&mut Text, With<MainMenuStatusText>>,
+    mut remote_server_address: ResMut<RemoteServerAddress>,
+) {
+    let Some(addr) = block_on(future::poll_once(&mut lookup.task)) else {
+        return;
+    };
+
+    commands.remove_resource::<ServerAddressLookup>();
+
+    match addr {
+        Some(addr) => {
+            remote_server_address.0 = Some((lookup.input.clone(), addr));
+            commands.set_state(GameState::ConnectingRemote);
+        }
+        None => {
+            for mut text in q_status_text.iter_mut() {
+                text.0 = format!("Invalid server address \"{}\"", lookup.input);
+            }
+        }
+    }
+}
+
+/// Hosting or joining something else meanwhile wins over a lookup still going
+#[cfg(not(target_family = "wasm"))]
+fn cancel_server_lookup(mut commands: Commands) {
+    commands.remove_resource::<ServerAddressLookup>();
+}
+
+#[cfg(feature = "host")]
+fn on_host_clicked(
+    _click: Trigger<Pointer<Click>>,
+    mut commands: Commands,
+    q_host_port_inputs: Query<(&TextInput, &HostPortInput)>,
+    mut q_status_text: Query<&mut Text, With<MainMenuStatusText>>,
+    mut host_ports: ResMut<HostPorts>,
+) {
+    let mut ports = *host_ports;
+
+    for (input, kind) in &q_host_port_inputs {
+        let value = input.value.trim();
+
+        // Port 0 would have the OS pick one, and nobody would know which to join
+        let Some(port) = value.parse::<u16>().ok().filter(|port| *port != 0) else {
+            for mut text in q_status_text.iter_mut() {
+                text.0 = format!("Invalid port \"{}\"", value);
+            }
+            return;
+        };
+
+        match kind {
+            HostPortInput::Udp => ports.udp = port,
+            HostPortInput::WebTransport => ports.webtransport = port,
+        }
+    }
+
+    if ports.udp == ports.webtransport {
+        for mut text in q_status_text.iter_mut() {
+            text.0 = String::from("UDP and WebTransport need different ports");
+        }
+        return;
+    }
+
+    *host_ports = ports;
+    commands.set_state(GameState::Hosting);
+}
+
 fn despawn_main_menu_buttons(
     mut commands: Commands,
-    q_connect_buttons: Query<Entity, With<ConnectButton>>,
+    q_connect_buttons: Query<
+        Entity,
+        Or<(With<ConnectButton>, With<DirectConnect>, With<SettingsButton>)>,
+    >,
     #[cfg(feature = "host")] q_host_buttons: Query<Entity, With<HostButton>>,
 ) {
     for entity in &q_connect_buttons {
@@ -120,6 +669,21 @@ fn on_client_begin_hosting(mut q_status_text: Query<&mut Text, With<MainMenuStat
     }
 }
 
+/// Hosting only starts once the last game this client hosted has finished shutting down
+#[cfg(feature = "host")]
+fn on_client_waiting_to_host(
+    hosted_server: Option<Res<HostedServer>>,
+    mut q_status_text: Query<&mut Text, With<MainMenuStatusText>>,
+) {
+    if !hosted_server.is_some_and(|hosted_server| hosted_server.is_running()) {
+        return;
+    }
+
+    for mut text in q_status_text.iter_mut() {
+        text.0 = String::from("Waiting for your last game to shut down");
+    }
+}
+
 fn on_client_begin_connecting(mut q_status_text: Query<&mut Text, With<MainMenuStatusText>>) {
     for mut text in q_status_text.iter_mut() {
         text.0 = String::from("Connecting");
@@ -1,16 +1,36 @@
 use bevy::prelude::*;
 
 mod main_menu;
+pub (crate) mod controls_menu;
+pub (crate) mod flag_markers;
+#[cfg(feature = "host")]
+pub (crate) mod host_panel;
+pub (crate) mod lobby;
+pub (crate) mod net_stats;
+pub (crate) mod race_hud;
 pub (crate) mod respawn_menu;
+pub (crate) mod settings_menu;
+pub (crate) mod shutdown_notice;
 pub (crate) mod system_menu;
+pub (crate) mod team_scores;
+pub (crate) mod text_input;
 
 pub(crate) struct UiPlugin;
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins((
             main_menu::MainMenuPlugin,
+            lobby::LobbyPlugin,
             system_menu::SystemMenuPlugin,
             respawn_menu::RespawnMenuPlugin,
+            settings_menu::SettingsMenuPlugin,
+            controls_menu::ControlsMenuPlugin,
+            net_stats::NetStatsPlugin,
+            team_scores::TeamScoresPlugin,
+            flag_markers::FlagMarkersPlugin,
+            race_hud::RaceHudPlugin,
+            text_input::TextInputPlugin,
+            shutdown_notice::ShutdownNoticePlugin,
         ));
     }
 }
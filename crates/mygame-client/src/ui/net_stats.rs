@@ -0,0 +1,443 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use bevy::{
+    color::palettes::tailwind::{SLATE_400, SLATE_800, SKY_400},
+    diagnostic::{DiagnosticPath, DiagnosticsStore},
+    prelude::*,
+};
+use lightyear::{
+    client::{
+        config::ClientConfig, prediction::diagnostics::PredictionDiagnosticsPlugin,
+    },
+    connection::client::ConnectionState,
+    prelude::{
+        TickManager,
+        client::{ClientConnection, Confirmed, NetClient, Predicted},
+    },
+    shared::ping::diagnostics::PingDiagnosticsPlugin,
+    transport::io::IoDiagnosticsPlugin,
+};
+
+use crate::replication::LocalPlayer;
+
+/// Debug overlay for tuning prediction, interpolation and the link conditioner.
+///
+/// Reads lightyear's ping, io and prediction diagnostics. Packet loss isn't one, so it's
+/// estimated from the running counts of pings sent and pongs received, and labelled as such.
+/// Traffic is for the whole connection, lightyear doesn't make its per channel counts public.
+pub struct NetStatsPlugin;
+
+impl Plugin for NetStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<NetStatsState>()
+            .init_resource::<NetStatsHistory>()
+            .add_systems(OnEnter(NetStatsState::Open), open_net_stats)
+            .add_systems(OnEnter(NetStatsState::Closed), close_net_stats)
+            .add_systems(
+                Update,
+                (
+                    sample_net_stats.run_if(in_state(NetStatsState::Open).or(client_connected)),
+                    update_net_stats.run_if(in_state(NetStatsState::Open)),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
+pub enum NetStatsState {
+    Open,
+    #[default]
+    Closed,
+}
+
+#[derive(Component)]
+pub struct NetStatsOverlay;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+const GRAPH_SAMPLES: usize = 60;
+const GRAPH_HEIGHT: f32 = 24.0;
+const GRAPH_BAR_WIDTH: f32 = 3.0;
+/// How many samples back rates are measured against, a second's worth
+const RATE_WINDOW_SAMPLES: usize = 10;
+
+/// Each line of the overlay
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum NetStat {
+    Rtt,
+    Jitter,
+    BytesIn,
+    BytesOut,
+    Packets,
+    PacketLoss,
+    Rollbacks,
+    Tick,
+    InterpolationDelay,
+}
+
+impl NetStat {
+    const ALL: [NetStat; 9] = [
+        NetStat::Rtt,
+        NetStat::Jitter,
+        NetStat::BytesIn,
+        NetStat::BytesOut,
+        NetStat::Packets,
+        NetStat::PacketLoss,
+        NetStat::Rollbacks,
+        NetStat::Tick,
+        NetStat::InterpolationDelay,
+    ];
+
+    /// Stats worth watching over time get a graph under their line
+    fn graphed(&self) -> bool {
+        matches!(
+            self,
+            NetStat::Rtt
+                | NetStat::BytesIn
+                | NetStat::BytesOut
+                | NetStat::PacketLoss
+                | NetStat::Rollbacks
+        )
+    }
+
+    /// The value a graph is drawn against, so a quiet connection doesn't look like a busy one
+    fn graph_floor(&self) -> f32 {
+        match self {
+            NetStat::Rtt => 100.0,
+            NetStat::BytesIn | NetStat::BytesOut => 10.0,
+            NetStat::PacketLoss => 5.0,
+            NetStat::Rollbacks => 10.0,
+            _ => 1.0,
+        }
+    }
+
+    fn sample(&self, snapshot: &NetStatsSnapshot) -> f32 {
+        match self {
+            NetStat::Rtt => snapshot.rtt_ms,
+            NetStat::Jitter => snapshot.jitter_ms,
+            NetStat::BytesIn => snapshot.kb_in_per_sec,
+            NetStat::BytesOut => snapshot.kb_out_per_sec,
+            NetStat::Packets => snapshot.packets_in_per_sec,
+            NetStat::PacketLoss => snapshot.packet_loss_percent,
+            NetStat::Rollbacks => snapshot.rollbacks_per_sec,
+            NetStat::Tick => snapshot.tick as f32,
+            NetStat::InterpolationDelay => snapshot.interpolation_delay_ms,
+        }
+    }
+
+    fn text(&self, snapshot: &NetStatsSnapshot) -> String {
+        match self {
+            NetStat::Rtt => format!("RTT {:.0} ms", snapshot.rtt_ms),
+            NetStat::Jitter => format!("Jitter {:.1} ms", snapshot.jitter_ms),
+            NetStat::BytesIn => format!("In {:.1} KB/s", snapshot.kb_in_per_sec),
+            NetStat::BytesOut => format!("Out {:.1} KB/s", snapshot.kb_out_per_sec),
+            NetStat::Packets => format!(
+                "Packets {:.0} in / {:.0} out per s",
+                snapshot.packets_in_per_sec, snapshot.packets_out_per_sec
+            ),
+            // Lost pings stand in for lost packets
+            NetStat::PacketLoss => {
+                format!("Packet loss {:.1}% (est.)", snapshot.packet_loss_percent)
+            }
+            NetStat::Rollbacks => format!("Rollbacks {:.0} /s", snapshot.rollbacks_per_sec),
+            NetStat::Tick => match snapshot.server_tick_offset {
+                Some(offset) => format!("Tick {} (ahead of server by {})", snapshot.tick, offset),
+                None => format!("Tick {}", snapshot.tick),
+            },
+            NetStat::InterpolationDelay => {
+                format!("Interpolation delay {:.0} ms", snapshot.interpolation_delay_ms)
+            }
+        }
+    }
+}
+
+/// One reading of every stat
+#[derive(Default, Clone)]
+struct NetStatsSnapshot {
+    rtt_ms: f32,
+    jitter_ms: f32,
+    kb_in_per_sec: f32,
+    kb_out_per_sec: f32,
+    packets_in_per_sec: f32,
+    packets_out_per_sec: f32,
+    /// Pings that got no pong back over the last second, an estimate of packets lost
+    packet_loss_percent: f32,
+    rollbacks_per_sec: f32,
+    tick: u16,
+    /// How many ticks the client's prediction runs ahead of the latest confirmed server state
+    server_tick_offset: Option<i16>,
+    interpolation_delay_ms: f32,
+}
+
+/// The connection's running counts at one sample, which rates are worked out from
+#[derive(Default, Clone)]
+struct NetTotals {
+    pings_sent: u32,
+    pongs_received: u32,
+}
+
+impl NetTotals {
+    fn read(diagnostics: &DiagnosticsStore) -> Self {
+        let latest = |path| {
+            diagnostics
+                .get(path)
+                .and_then(|diagnostic| diagnostic.value())
+                .unwrap_or_default() as u32
+        };
+
+        Self {
+            pings_sent: latest(&PingDiagnosticsPlugin::PINGS_SENT),
+            pongs_received: latest(&PingDiagnosticsPlugin::PONGS_RECEIVED),
+        }
+    }
+
+    fn packet_loss_percent(&self, earlier: &NetTotals) -> f32 {
+        let sent = self.pings_sent.saturating_sub(earlier.pings_sent);
+        let received = self.pongs_received.saturating_sub(earlier.pongs_received);
+
+        if sent == 0 {
+            return 0.0;
+        }
+
+        // Pongs for pings sent just before the window can make this briefly negative
+        (1.0 - received as f32 / sent as f32).max(0.0) * 100.0
+    }
+}
+
+/// Recent snapshots, sampled at a fixed interval so graphs don't depend on frame rate
+#[derive(Resource)]
+struct NetStatsHistory {
+    timer: Timer,
+    snapshots: VecDeque<NetStatsSnapshot>,
+    /// The last second of running counts, oldest first
+    totals: VecDeque<NetTotals>,
+}
+
+impl Default for NetStatsHistory {
+    fn default() -> Self {
+        Self {
+            timer: Timer::new(SAMPLE_INTERVAL, TimerMode::Repeating),
+            snapshots: VecDeque::with_capacity(GRAPH_SAMPLES),
+            totals: VecDeque::with_capacity(RATE_WINDOW_SAMPLES),
+        }
+    }
+}
+
+#[derive(Component)]
+struct NetStatText(NetStat);
+
+/// One bar in the graph for a NetStat, indexed oldest to newest
+#[derive(Component)]
+struct NetStatGraphBar(NetStat, usize);
+
+fn smoothed(diagnostics: &DiagnosticsStore, path: &DiagnosticPath) -> f32 {
+    diagnostics
+        .get(path)
+        .and_then(|diagnostic| diagnostic.smoothed())
+        .unwrap_or_default() as f32
+}
+
+/// Rollbacks are recorded per frame, so add up everything recorded in the last second
+fn per_second_sum(diagnostics: &DiagnosticsStore, path: &DiagnosticPath) -> f32 {
+    let Some(diagnostic) = diagnostics.get(path) else {
+        return 0.0;
+    };
+
+    let Some(latest) = diagnostic.measurement().map(|measurement| measurement.time) else {
+        return 0.0;
+    };
+
+    diagnostic
+        .measurements()
+        .filter(|measurement| latest.duration_since(measurement.time) <= Duration::from_secs(1))
+        .map(|measurement| measurement.value)
+        .sum::<f64>() as f32
+}
+
+/// Sampled while connected even with the overlay closed, so it opens with history to show
+fn client_connected(client: Res<ClientConnection>) -> bool {
+    matches!(client.state(), ConnectionState::Connected)
+}
+
+fn sample_net_stats(
+    time: Res<Time>,
+    mut history: ResMut<NetStatsHistory>,
+    diagnostics: Res<DiagnosticsStore>,
+    tick_manager: Res<TickManager>,
+    client_config: Res<ClientConfig>,
+    q_local_player: Query<&Predicted, With<LocalPlayer>>,
+    q_confirmed: Query<&Confirmed>,
+) {
+    // Only a new sample counts as a change, so the overlay redraws at the sample rate
+    let timer = &mut history.bypass_change_detection().timer;
+    if !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let tick = tick_manager.tick();
+
+    let server_tick_offset = q_local_player
+        .iter()
+        .find_map(|predicted| predicted.confirmed_entity)
+        .and_then(|confirmed_entity| q_confirmed.get(confirmed_entity).ok())
+        .map(|confirmed| tick - confirmed.tick);
+
+    let interpolation_delay = client_config.interpolation.min_delay.max(
+        client_config
+            .shared
+            .server_replication_send_interval
+            .mul_f32(client_config.interpolation.send_interval_ratio),
+    );
+
+    let totals = NetTotals::read(&diagnostics);
+    // Measured against the oldest counts kept, up to a second ago. The first sample has
+    // nothing to compare against, so it shows nothing rather than everything so far.
+    let earlier = history
+        .totals
+        .front()
+        .cloned()
+        .unwrap_or_else(|| totals.clone());
+
+    let snapshot = NetStatsSnapshot {
+        rtt_ms: smoothed(&diagnostics, &PingDiagnosticsPlugin::RTT),
+        jitter_ms: smoothed(&diagnostics, &PingDiagnosticsPlugin::JITTER),
+        // Already in KB
+        kb_in_per_sec: smoothed(&diagnostics, &IoDiagnosticsPlugin::BYTES_IN),
+        kb_out_per_sec: smoothed(&diagnostics, &IoDiagnosticsPlugin::BYTES_OUT),
+        packets_in_per_sec: smoothed(&diagnostics, &IoDiagnosticsPlugin::PACKETS_IN),
+        packets_out_per_sec: smoothed(&diagnostics, &IoDiagnosticsPlugin::PACKETS_OUT),
+        packet_loss_percent: totals.packet_loss_percent(&earlier),
+        rollbacks_per_sec: per_second_sum(&diagnostics, &PredictionDiagnosticsPlugin::ROLLBACKS),
+        tick: tick.0,
+        server_tick_offset,
+        interpolation_delay_ms: interpolation_delay.as_secs_f32() * 1000.0,
+    };
+
+    if history.snapshots.len() == GRAPH_SAMPLES {
+        history.snapshots.pop_front();
+    }
+    history.snapshots.push_back(snapshot);
+
+    if history.totals.len() == RATE_WINDOW_SAMPLES {
+        history.totals.pop_front();
+    }
+    history.totals.push_back(totals);
+}
+
+fn open_net_stats(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                right: Val::Px(10.0),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(8.0)),
+                row_gap: Val::Px(2.0),
+                ..default()
+            },
+            BackgroundColor(SLATE_800.with_alpha(0.85).into()),
+            // Stay visible over the menus, the numbers are most interesting while connecting
+            GlobalZIndex(30),
+            Pickable::IGNORE,
+            NetStatsOverlay,
+        ))
+        .with_children(|child_builder| {
+            child_builder.spawn((
+                Text::new("Network"),
+                TextFont {
+                    font_size: 14.,
+                    ..default()
+                },
+                TextColor(SLATE_400.into()),
+                Pickable::IGNORE,
+            ));
+
+            for stat in NetStat::ALL {
+                child_builder.spawn((
+                    Text::default(),
+                    TextFont {
+                        font_size: 12.,
+                        ..default()
+                    },
+                    Pickable::IGNORE,
+                    NetStatText(stat),
+                ));
+
+                if stat.graphed() {
+                    child_builder
+                        .spawn((
+                            Node {
+                                height: Val::Px(GRAPH_HEIGHT),
+                                align_items: AlignItems::FlexEnd,
+                                column_gap: Val::Px(1.0),
+                                margin: UiRect::bottom(Val::Px(4.0)),
+                                ..default()
+                            },
+                            BackgroundColor(Color::BLACK.with_alpha(0.3)),
+                            Pickable::IGNORE,
+                        ))
+                        .with_children(|graph_builder| {
+                            for index in 0..GRAPH_SAMPLES {
+                                graph_builder.spawn((
+                                    Node {
+                                        width: Val::Px(GRAPH_BAR_WIDTH),
+                                        height: Val::Percent(0.0),
+                                        ..default()
+                                    },
+                                    BackgroundColor(SKY_400.into()),
+                                    Pickable::IGNORE,
+                                    NetStatGraphBar(stat, index),
+                                ));
+                            }
+                        });
+                }
+            }
+        });
+}
+
+fn update_net_stats(
+    history: Res<NetStatsHistory>,
+    mut q_texts: Query<(&mut Text, &NetStatText)>,
+    mut q_bars: Query<(&mut Node, &NetStatGraphBar)>,
+) {
+    if !history.is_changed() {
+        return;
+    }
+
+    let Some(latest) = history.snapshots.back() else {
+        return;
+    };
+
+    for (mut text, stat_text) in &mut q_texts {
+        text.0 = stat_text.0.text(latest);
+    }
+
+    // Right-align the history so the newest sample is always the last bar
+    let offset = GRAPH_SAMPLES - history.snapshots.len();
+
+    for (mut node, bar) in &mut q_bars {
+        let stat = bar.0;
+        let scale = history
+            .snapshots
+            .iter()
+            .map(|snapshot| stat.sample(snapshot))
+            .fold(stat.graph_floor(), f32::max);
+
+        let value = bar
+            .1
+            .checked_sub(offset)
+            .and_then(|index| history.snapshots.get(index))
+            .map(|snapshot| stat.sample(snapshot))
+            .unwrap_or_default();
+
+        node.height = Val::Percent(value / scale * 100.0);
+    }
+}
+
+fn close_net_stats(mut commands: Commands, q_overlay: Query<Entity, With<NetStatsOverlay>>) {
+    for overlay in &q_overlay {
+        commands.entity(overlay).despawn_recursive();
+    }
+}
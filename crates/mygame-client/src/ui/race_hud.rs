@@ -0,0 +1,200 @@
+use std::time::Duration;
+
+use bevy::{
+    color::palettes::tailwind::{AMBER_400, SLATE_800},
+    prelude::*,
+};
+use lightyear::prelude::ClientReceiveMessage;
+use mygame_protocol::message::{
+    GameMode, MatchRules, ServerCheckpointPassed, ServerRaceLeaderboard,
+};
+
+use crate::game_state::GameState;
+
+/// In race mode, the running lap time and last split at the top of the screen and the
+/// leaderboard in the corner
+pub struct RaceHudPlugin;
+
+impl Plugin for RaceHudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RaceHud>()
+            .add_systems(OnEnter(GameState::MainMenu), reset_race_hud)
+            .add_systems(OnEnter(GameState::Playing), spawn_race_hud(GameState::Playing))
+            .add_systems(
+                OnEnter(GameState::Spectating),
+                spawn_race_hud(GameState::Spectating),
+            )
+            .add_systems(
+                Update,
+                (receive_race_messages, update_lap_text, update_leaderboard_text).chain(),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+struct RaceHud {
+    /// When, on this client's clock, the current lap started
+    lap_started: Option<Duration>,
+    last_pass: Option<ServerCheckpointPassed>,
+    leaderboard: ServerRaceLeaderboard,
+}
+
+#[derive(Component)]
+struct LapText;
+
+#[derive(Component)]
+struct LeaderboardText;
+
+fn reset_race_hud(mut race_hud: ResMut<RaceHud>) {
+    *race_hud = RaceHud::default();
+}
+
+fn receive_race_messages(
+    time: Res<Time>,
+    mut checkpoint_events: EventReader<ClientReceiveMessage<ServerCheckpointPassed>>,
+    mut leaderboard_events: EventReader<ClientReceiveMessage<ServerRaceLeaderboard>>,
+    mut race_hud: ResMut<RaceHud>,
+) {
+    for ev in checkpoint_events.read() {
+        if ev.message.checkpoint == 0 {
+            race_hud.lap_started = Some(time.elapsed());
+        }
+
+        race_hud.last_pass = Some(ev.message.clone());
+    }
+
+    for ev in leaderboard_events.read() {
+        race_hud.leaderboard = ev.message.clone();
+    }
+}
+
+fn format_lap_time(time: Duration) -> String {
+    let secs = time.as_secs_f32();
+    format!("{}:{:06.3}", (secs / 60.0) as u32, secs % 60.0)
+}
+
+fn format_optional_lap_time(time: Option<Duration>) -> String {
+    time.map(format_lap_time).unwrap_or_else(|| String::from("--"))
+}
+
+/// The HUD lives only as long as the state it was spawned for
+fn spawn_race_hud(state: GameState) -> impl Fn(Commands, Res<MatchRules>) {
+    move |mut commands: Commands, match_rules: Res<MatchRules>| {
+        if match_rules.mode != GameMode::Race {
+            return;
+        }
+
+        commands
+            .spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.0),
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                Pickable::IGNORE,
+                StateScoped(state.clone()),
+            ))
+            .with_children(|child_builder| {
+                child_builder.spawn((
+                    Text::default(),
+                    TextFont {
+                        font_size: 24.,
+                        ..default()
+                    },
+                    TextColor(AMBER_400.into()),
+                    TextLayout::new_with_justify(JustifyText::Center),
+                    Node {
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    BackgroundColor(SLATE_800.with_alpha(0.8).into()),
+                    LapText,
+                ));
+            });
+
+        commands.spawn((
+            Text::default(),
+            TextFont {
+                font_size: 16.,
+                ..default()
+            },
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                right: Val::Px(10.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            BackgroundColor(SLATE_800.with_alpha(0.8).into()),
+            Pickable::IGNORE,
+            LeaderboardText,
+            StateScoped(state.clone()),
+        ));
+    }
+}
+
+fn update_lap_text(
+    time: Res<Time>,
+    race_hud: Res<RaceHud>,
+    mut q_lap_text: Query<&mut Text, With<LapText>>,
+) {
+    let Ok(mut text) = q_lap_text.single_mut() else {
+        return;
+    };
+
+    let lap_time = race_hud
+        .lap_started
+        .map(|lap_started| time.elapsed() - lap_started);
+
+    let mut lines = vec![format!("Lap {}", format_optional_lap_time(lap_time))];
+
+    match &race_hud.last_pass {
+        Some(last_pass) => {
+            lines.push(format!(
+                "Checkpoint {}/{}  Split {}",
+                last_pass.checkpoint + 1,
+                last_pass.checkpoint_count,
+                format_lap_time(last_pass.split)
+            ));
+
+            if let Some(last_lap) = last_pass.lap_time {
+                lines.push(format!("Last lap {}", format_lap_time(last_lap)));
+            }
+        }
+        None => lines.push(String::from("Fly through the first ring to start")),
+    }
+
+    let content = lines.join("\n");
+    if text.0 != content {
+        text.0 = content;
+    }
+}
+
+fn update_leaderboard_text(
+    race_hud: Res<RaceHud>,
+    mut q_leaderboard_text: Query<&mut Text, With<LeaderboardText>>,
+) {
+    let Ok(mut text) = q_leaderboard_text.single_mut() else {
+        return;
+    };
+
+    // Freshly spawned text needs filling in even when the leaderboard hasn't changed
+    if !race_hud.is_changed() && !text.0.is_empty() {
+        return;
+    }
+
+    let mut lines = vec![String::from("Leaderboard")];
+    lines.extend(race_hud.leaderboard.entries.iter().map(|entry| {
+        format!(
+            "Player {}  {} laps  best {}  PB {}",
+            entry.player,
+            entry.laps,
+            format_optional_lap_time(entry.best_lap),
+            format_optional_lap_time(entry.personal_best)
+        )
+    }));
+
+    text.0 = lines.join("\n");
+}
@@ -1,9 +1,20 @@
-use bevy::{color::palettes::tailwind::SLATE_800, prelude::*};
+use bevy::{
+    color::palettes::tailwind::{SLATE_400, SLATE_800},
+    prelude::*,
+};
 use lightyear::prelude::ClientConnectionManager;
-use mygame_common::Simulated;
-use mygame_protocol::message::{ClientRequestRespawn, UnorderedReliable};
+use mygame_common::{Rendered, Simulated};
+use mygame_protocol::{
+    component::{Bot, Health, Player, Ship},
+    message::{ClientRequestRespawn, UnorderedReliable},
+};
 
-use crate::{game_state::GameState, replication::LocalPlayer};
+use crate::{
+    death_cam::{DeathCam, find_killer},
+    game_state::GameState,
+    replication::LocalPlayer,
+    spectator::request_spectate,
+};
 
 pub struct RespawnMenuPlugin;
 
@@ -14,7 +25,11 @@ impl Plugin for RespawnMenuPlugin {
             .add_observer(set_respawn_menu_state_closed)
             .add_systems(OnEnter(RespawnMenuState::Open), open_respawn_menu)
             .add_systems(OnEnter(RespawnMenuState::Closed), close_respawn_menu)
-            .add_systems(OnExit(GameState::Playing), close_respawn_menu);
+            .add_systems(OnExit(GameState::Playing), close_respawn_menu)
+            .add_systems(
+                Update,
+                update_respawn_menu.run_if(in_state(RespawnMenuState::Open)),
+            );
     }
 }
 
@@ -28,6 +43,13 @@ pub enum RespawnMenuState {
 #[derive(Component)]
 pub struct RespawnMenu;
 
+/// Who destroyed us, and how they're holding up
+#[derive(Component)]
+struct KillerText;
+
+#[derive(Component)]
+struct RespawnButtonText;
+
 fn set_respawn_menu_state_open(
     trigger: Trigger<OnRemove, LocalPlayer>, 
     mut commands: Commands,
@@ -68,6 +90,19 @@ fn open_respawn_menu(mut commands: Commands) {
                     BackgroundColor(SLATE_800.into()),
                 ))
                 .with_children(|child_child_builder| {
+                    child_child_builder.spawn((
+                        Text::new(""),
+                        TextFont {
+                            font_size: 20.,
+                            ..default()
+                        },
+                        Node {
+                            padding: UiRect::bottom(Val::Px(20.)),
+                            ..default()
+                        },
+                        KillerText,
+                    ));
+
                     child_child_builder
                         .spawn((
                             Text::new("Respawn"),
@@ -79,14 +114,33 @@ fn open_respawn_menu(mut commands: Commands) {
                                 padding: UiRect::bottom(Val::Px(20.)),
                                 ..default()
                             },
+                            RespawnButtonText,
                         ))
-                        .observe(|_click: Trigger<Pointer<Click>>, mut commands: Commands| {
+                        .observe(|_click: Trigger<Pointer<Click>>, mut commands: Commands, death_cam: Option<Res<DeathCam>>| {
+                            // Locked until the server will accept it
+                            if death_cam.is_some_and(|death_cam| !death_cam.can_respawn()) {
+                                return;
+                            }
+
                             commands.queue(|world: &mut World| {
                                 if let Some(mut client) = world.get_resource_mut::<ClientConnectionManager>() {
                                     let _ = client.send_message::<UnorderedReliable, ClientRequestRespawn>(&ClientRequestRespawn);
                                 }
                             });
                         });
+
+                    child_child_builder
+                        .spawn((
+                            Text::new("Spectate"),
+                            TextFont {
+                                font_size: 30.,
+                                ..default()
+                            },
+                        ))
+                        .observe(|_click: Trigger<Pointer<Click>>, mut commands: Commands| {
+                            commands.set_state(RespawnMenuState::Closed);
+                            request_spectate(&mut commands);
+                        });
                 });
         });
 }
@@ -96,3 +150,48 @@ fn close_respawn_menu(mut commands: Commands, q_respawn_menu: Query<Entity, With
         commands.entity(respawn_menu).despawn_recursive();
     }
 }
+
+fn update_respawn_menu(
+    death_cam: Option<Res<DeathCam>>,
+    q_ships: Query<(&Transform, Option<&Player>, Option<&Bot>, Option<&Health>), (Rendered, With<Ship>)>,
+    mut q_killer_text: Query<&mut Text, (With<KillerText>, Without<RespawnButtonText>)>,
+    mut q_respawn_button: Query<(&mut Text, &mut TextColor), (With<RespawnButtonText>, Without<KillerText>)>,
+) {
+    // Before the server's word arrives, or if it never does, leave the menu as it was
+    let Some(death_cam) = death_cam else {
+        return;
+    };
+
+    let killer_status = match (death_cam.killer_name(), death_cam.killer) {
+        (Some(name), Some(killer)) => match find_killer(killer, &q_ships) {
+            Some((_, Some(health))) => format!(
+                "Destroyed by {} ({}/{} health left)",
+                name, health.current, health.max
+            ),
+            _ => format!("Destroyed by {}", name),
+        },
+        _ => String::from("Destroyed"),
+    };
+
+    for mut text in &mut q_killer_text {
+        if text.0 != killer_status {
+            text.0 = killer_status.clone();
+        }
+    }
+
+    let (respawn_label, respawn_color) = if death_cam.can_respawn() {
+        (String::from("Respawn"), Color::WHITE)
+    } else {
+        let remaining = death_cam.respawn_timer.remaining_secs().ceil();
+        (format!("Respawn in {}", remaining), SLATE_400.into())
+    };
+
+    for (mut text, mut color) in &mut q_respawn_button {
+        if text.0 != respawn_label {
+            text.0 = respawn_label.clone();
+        }
+        if color.0 != respawn_color {
+            color.0 = respawn_color;
+        }
+    }
+}
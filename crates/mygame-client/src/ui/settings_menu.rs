@@ -0,0 +1,319 @@
+use bevy::{
+    color::palettes::tailwind::{SLATE_400, SLATE_800},
+    prelude::*,
+};
+
+use crate::{
+    game_state::GameState,
+    settings::{
+        AIM_DEADZONE_RANGE, AIM_RECENTER_RATE_RANGE, AimModel, ClientSettings,
+        FOLLOW_DISTANCE_RANGE, FOV_DEGREES_RANGE, MOUSE_SENSITIVITY_RANGE,
+    },
+};
+
+use super::{controls_menu::ControlsMenuState, system_menu::SystemMenuState};
+
+pub struct SettingsMenuPlugin;
+
+impl Plugin for SettingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<SettingsMenuState>()
+            .add_systems(OnEnter(SettingsMenuState::Open), open_settings_menu)
+            .add_systems(OnEnter(SettingsMenuState::Closed), close_settings_menu)
+            .add_systems(OnEnter(SystemMenuState::Closed), set_settings_menu_state_closed)
+            .add_systems(OnExit(GameState::MainMenu), set_settings_menu_state_closed)
+            .add_systems(
+                Update,
+                update_setting_values
+                    .run_if(in_state(SettingsMenuState::Open).and(resource_changed::<ClientSettings>)),
+            );
+    }
+}
+
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
+pub enum SettingsMenuState {
+    Open,
+    #[default]
+    Closed,
+}
+
+#[derive(Component)]
+pub struct SettingsMenu;
+
+/// Each row of the settings menu, and the setting it adjusts
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+enum SettingRow {
+    MouseSensitivity,
+    InvertY,
+    AimModel,
+    AimDeadzone,
+    AimRecenterRate,
+    FieldOfView,
+    CameraDistance,
+    VSync,
+    Msaa,
+    AutoReconnect,
+}
+
+impl SettingRow {
+    const ALL: [SettingRow; 10] = [
+        SettingRow::MouseSensitivity,
+        SettingRow::InvertY,
+        SettingRow::AimModel,
+        SettingRow::AimDeadzone,
+        SettingRow::AimRecenterRate,
+        SettingRow::FieldOfView,
+        SettingRow::CameraDistance,
+        SettingRow::VSync,
+        SettingRow::Msaa,
+        SettingRow::AutoReconnect,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SettingRow::MouseSensitivity => "Mouse Sensitivity",
+            SettingRow::InvertY => "Invert Y",
+            SettingRow::AimModel => "Aim Model",
+            SettingRow::AimDeadzone => "Aim Deadzone",
+            SettingRow::AimRecenterRate => "Recenter Speed",
+            SettingRow::FieldOfView => "Field of View",
+            SettingRow::CameraDistance => "Camera Distance",
+            SettingRow::VSync => "VSync",
+            SettingRow::Msaa => "Anti-aliasing",
+            SettingRow::AutoReconnect => "Auto-reconnect",
+        }
+    }
+
+    fn value(&self, settings: &ClientSettings) -> String {
+        match self {
+            SettingRow::MouseSensitivity => format!("{:.1}", settings.mouse_sensitivity * 1000.0),
+            SettingRow::InvertY => on_off(settings.invert_y),
+            SettingRow::AimModel => String::from(settings.aim_model.label()),
+            SettingRow::AimDeadzone => format!("{:.0}%", settings.aim_deadzone * 100.0),
+            SettingRow::AimRecenterRate => format!("{:.1}", settings.aim_recenter_rate),
+            SettingRow::FieldOfView => format!("{:.0}", settings.fov_degrees),
+            SettingRow::CameraDistance => format!("{:.1}", settings.follow_distance),
+            SettingRow::VSync => on_off(settings.vsync),
+            SettingRow::Msaa => on_off(settings.msaa),
+            SettingRow::AutoReconnect => on_off(settings.auto_reconnect),
+        }
+    }
+
+    /// Step the setting up or down. Toggles flip regardless of direction, choices cycle.
+    fn adjust(&self, settings: &mut ClientSettings, direction: f32) {
+        match self {
+            SettingRow::MouseSensitivity => {
+                settings.mouse_sensitivity = (settings.mouse_sensitivity + direction * 0.0002)
+                    .clamp(MOUSE_SENSITIVITY_RANGE.0, MOUSE_SENSITIVITY_RANGE.1);
+            }
+            SettingRow::InvertY => settings.invert_y = !settings.invert_y,
+            SettingRow::AimModel => {
+                let count = AimModel::ALL.len() as i32;
+                let index = AimModel::ALL
+                    .iter()
+                    .position(|model| *model == settings.aim_model)
+                    .unwrap_or_default() as i32;
+                let next = (index + direction as i32).rem_euclid(count);
+                settings.aim_model = AimModel::ALL[next as usize];
+            }
+            SettingRow::AimDeadzone => {
+                settings.aim_deadzone = (settings.aim_deadzone + direction * 0.05)
+                    .clamp(AIM_DEADZONE_RANGE.0, AIM_DEADZONE_RANGE.1);
+            }
+            SettingRow::AimRecenterRate => {
+                settings.aim_recenter_rate = (settings.aim_recenter_rate + direction * 0.5)
+                    .clamp(AIM_RECENTER_RATE_RANGE.0, AIM_RECENTER_RATE_RANGE.1);
+            }
+            SettingRow::FieldOfView => {
+                settings.fov_degrees = (settings.fov_degrees + direction * 5.0)
+                    .clamp(FOV_DEGREES_RANGE.0, FOV_DEGREES_RANGE.1);
+            }
+            SettingRow::CameraDistance => {
+                settings.follow_distance = (settings.follow_distance + direction * 0.5)
+                    .clamp(FOLLOW_DISTANCE_RANGE.0, FOLLOW_DISTANCE_RANGE.1);
+            }
+            SettingRow::VSync => settings.vsync = !settings.vsync,
+            SettingRow::Msaa => settings.msaa = !settings.msaa,
+            SettingRow::AutoReconnect => settings.auto_reconnect = !settings.auto_reconnect,
+        }
+    }
+}
+
+fn on_off(value: bool) -> String {
+    String::from(if value { "On" } else { "Off" })
+}
+
+/// The text displaying the current value of a SettingRow
+#[derive(Component)]
+struct SettingValueText(SettingRow);
+
+fn set_settings_menu_state_closed(mut commands: Commands) {
+    commands.set_state(SettingsMenuState::Closed);
+}
+
+fn open_settings_menu(mut commands: Commands, settings: Res<ClientSettings>) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            // Draw over, and swallow clicks meant for, whichever menu opened this one
+            GlobalZIndex(10),
+            BackgroundColor(Color::BLACK.with_alpha(0.5)),
+            SettingsMenu,
+        ))
+        .with_children(|child_builder| {
+            child_builder
+                .spawn((
+                    Node {
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    BackgroundColor(SLATE_800.into()),
+                ))
+                .with_children(|child_child_builder| {
+                    child_child_builder.spawn((
+                        Text::new("Settings"),
+                        TextFont {
+                            font_size: 30.,
+                            ..default()
+                        },
+                        Node {
+                            padding: UiRect::bottom(Val::Px(20.)),
+                            ..default()
+                        },
+                    ));
+
+                    for row in SettingRow::ALL {
+                        spawn_setting_row(child_child_builder, row, &settings);
+                    }
+
+                    child_child_builder
+                        .spawn((
+                            Text::new("Controls"),
+                            TextFont {
+                                font_size: 24.,
+                                ..default()
+                            },
+                            Node {
+                                padding: UiRect::top(Val::Px(20.)),
+                                ..default()
+                            },
+                        ))
+                        .observe(|_click: Trigger<Pointer<Click>>, mut commands: Commands| {
+                            commands.set_state(ControlsMenuState::Open);
+                        });
+
+                    child_child_builder
+                        .spawn((
+                            Text::new("Back"),
+                            TextFont {
+                                font_size: 30.,
+                                ..default()
+                            },
+                            Node {
+                                padding: UiRect::top(Val::Px(20.)),
+                                ..default()
+                            },
+                        ))
+                        .observe(|_click: Trigger<Pointer<Click>>, mut commands: Commands| {
+                            commands.set_state(SettingsMenuState::Closed);
+                        });
+                });
+        });
+}
+
+fn spawn_setting_row(builder: &mut ChildSpawnerCommands, row: SettingRow, settings: &ClientSettings) {
+    builder
+        .spawn(Node {
+            width: Val::Px(360.),
+            justify_content: JustifyContent::SpaceBetween,
+            align_items: AlignItems::Center,
+            padding: UiRect::vertical(Val::Px(4.)),
+            ..default()
+        })
+        .with_children(|row_builder| {
+            row_builder.spawn((
+                Text::new(row.label()),
+                TextFont {
+                    font_size: 18.,
+                    ..default()
+                },
+            ));
+
+            row_builder
+                .spawn(Node {
+                    column_gap: Val::Px(12.),
+                    align_items: AlignItems::Center,
+                    ..default()
+                })
+                .with_children(|value_builder| {
+                    value_builder
+                        .spawn((
+                            Text::new("<"),
+                            TextFont {
+                                font_size: 18.,
+                                ..default()
+                            },
+                            TextColor(SLATE_400.into()),
+                        ))
+                        .observe(
+                            move |_click: Trigger<Pointer<Click>>, mut settings: ResMut<ClientSettings>| {
+                                row.adjust(&mut settings, -1.0);
+                            },
+                        );
+
+                    value_builder.spawn((
+                        Text::new(row.value(settings)),
+                        TextFont {
+                            font_size: 18.,
+                            ..default()
+                        },
+                        Node {
+                            min_width: Val::Px(40.),
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        },
+                        SettingValueText(row),
+                    ));
+
+                    value_builder
+                        .spawn((
+                            Text::new(">"),
+                            TextFont {
+                                font_size: 18.,
+                                ..default()
+                            },
+                            TextColor(SLATE_400.into()),
+                        ))
+                        .observe(
+                            move |_click: Trigger<Pointer<Click>>, mut settings: ResMut<ClientSettings>| {
+                                row.adjust(&mut settings, 1.0);
+                            },
+                        );
+                });
+        });
+}
+
+fn update_setting_values(
+    settings: Res<ClientSettings>,
+    mut q_value_texts: Query<(&mut Text, &SettingValueText)>,
+) {
+    for (mut text, value_text) in &mut q_value_texts {
+        text.0 = value_text.0.value(&settings);
+    }
+}
+
+fn close_settings_menu(mut commands: Commands, q_settings_menu: Query<Entity, With<SettingsMenu>>) {
+    for settings_menu in &q_settings_menu {
+        commands.entity(settings_menu).despawn_recursive();
+    }
+}
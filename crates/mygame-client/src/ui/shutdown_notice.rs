@@ -0,0 +1,128 @@
+use bevy::{color::palettes::tailwind::AMBER_400, prelude::*};
+use lightyear::prelude::ClientReceiveMessage;
+use mygame_protocol::message::{ServerShutdownNotice, ShutdownReason};
+
+use crate::game_state::GameState;
+
+/// Warns the player when the server is about to go away, counts down to it, then takes them
+/// back to the main menu, which says why
+pub struct ShutdownNoticePlugin;
+
+impl Plugin for ShutdownNoticePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShutdownNotice>()
+            .add_systems(OnEnter(GameState::MainMenu), end_shutdown_countdown)
+            .add_systems(OnExit(GameState::MainMenu), clear_shutdown_notice)
+            .add_systems(
+                Update,
+                (receive_shutdown_notice, count_down_shutdown_notice).chain(),
+            );
+    }
+}
+
+/// Why the server said it was shutting down, and how long is left. The reason outlives the
+/// countdown so the main menu can show it.
+#[derive(Resource, Default)]
+pub struct ShutdownNotice {
+    pub reason: Option<ShutdownReason>,
+    countdown: Option<Timer>,
+}
+
+#[derive(Component)]
+struct ShutdownBanner;
+
+#[derive(Component)]
+struct ShutdownBannerText;
+
+fn countdown_text(reason: ShutdownReason, countdown: &Timer) -> String {
+    format!(
+        "{}. Disconnecting in {}",
+        reason.message(),
+        countdown.remaining_secs().ceil() as u32
+    )
+}
+
+fn receive_shutdown_notice(
+    mut commands: Commands,
+    mut notice_events: EventReader<ClientReceiveMessage<ServerShutdownNotice>>,
+    mut shutdown_notice: ResMut<ShutdownNotice>,
+    q_banner: Query<Entity, With<ShutdownBanner>>,
+) {
+    for ev in notice_events.read() {
+        let ServerShutdownNotice { reason, seconds } = ev.message;
+        let countdown = Timer::from_seconds(seconds as f32, TimerMode::Once);
+
+        for banner in &q_banner {
+            commands.entity(banner).despawn_recursive();
+        }
+
+        commands
+            .spawn((
+                Node {
+                    width: Val::Percent(100.0),
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ShutdownBanner,
+            ))
+            .with_children(|child_builder| {
+                child_builder.spawn((
+                    Text::new(countdown_text(reason, &countdown)),
+                    TextFont {
+                        font_size: 24.,
+                        ..default()
+                    },
+                    TextColor(AMBER_400.into()),
+                    ShutdownBannerText,
+                ));
+            });
+
+        shutdown_notice.reason = Some(reason);
+        shutdown_notice.countdown = Some(countdown);
+    }
+}
+
+fn count_down_shutdown_notice(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut shutdown_notice: ResMut<ShutdownNotice>,
+    mut q_banner_text: Query<&mut Text, With<ShutdownBannerText>>,
+) {
+    let ShutdownNotice {
+        reason: Some(reason),
+        countdown: Some(countdown),
+    } = &mut *shutdown_notice
+    else {
+        return;
+    };
+
+    countdown.tick(time.delta());
+
+    for mut text in &mut q_banner_text {
+        text.0 = countdown_text(*reason, countdown);
+    }
+
+    if countdown.just_finished() {
+        commands.set_state(GameState::MainMenu);
+    }
+}
+
+/// Back on the main menu, whether the server dropped us first or the countdown ran out. The
+/// reason stays for the menu to show.
+fn end_shutdown_countdown(
+    mut commands: Commands,
+    mut shutdown_notice: ResMut<ShutdownNotice>,
+    q_banner: Query<Entity, With<ShutdownBanner>>,
+) {
+    shutdown_notice.countdown = None;
+
+    for banner in &q_banner {
+        commands.entity(banner).despawn_recursive();
+    }
+}
+
+fn clear_shutdown_notice(mut shutdown_notice: ResMut<ShutdownNotice>) {
+    *shutdown_notice = ShutdownNotice::default();
+}
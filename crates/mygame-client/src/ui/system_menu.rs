@@ -1,7 +1,11 @@
 use bevy::{color::palettes::tailwind::SLATE_800, prelude::*};
 
+#[cfg(feature = "host")]
+use crate::host::HostInfo;
 use crate::game_state::GameState;
 
+use super::settings_menu::SettingsMenuState;
+
 pub struct SystemMenuPlugin;
 
 impl Plugin for SystemMenuPlugin {
@@ -10,7 +14,8 @@ impl Plugin for SystemMenuPlugin {
 
         app.add_systems(OnEnter(SystemMenuState::Open), open_system_menu)
             .add_systems(OnEnter(SystemMenuState::Closed), close_system_menu)
-            .add_systems(OnExit(GameState::Playing), close_system_menu);
+            .add_systems(OnExit(GameState::Playing), close_system_menu)
+            .add_systems(OnExit(GameState::Spectating), close_system_menu);
     }
 }
 
@@ -24,7 +29,10 @@ pub enum SystemMenuState {
 #[derive(Component)]
 pub struct SystemMenu;
 
-fn open_system_menu(mut commands: Commands) {
+fn open_system_menu(
+    mut commands: Commands,
+    #[cfg(feature = "host")] host_info: Option<Res<HostInfo>>,
+) {
     commands
         .spawn((
             Node {
@@ -66,6 +74,27 @@ fn open_system_menu(mut commands: Commands) {
                             commands.set_state(GameState::MainMenu);
                         });
 
+                    child_child_builder
+                        .spawn((
+                            Text::new("Settings"),
+                            TextFont {
+                                font_size: 30.,
+                                ..default()
+                            },
+                            Node {
+                                padding: UiRect::bottom(Val::Px(20.)),
+                                ..default()
+                            },
+                        ))
+                        .observe(|_click: Trigger<Pointer<Click>>, mut commands: Commands| {
+                            commands.set_state(SettingsMenuState::Open);
+                        });
+
+                    #[cfg(feature = "host")]
+                    if let Some(host_info) = &host_info {
+                        super::host_panel::spawn_host_panel(child_child_builder, host_info);
+                    }
+
                     #[cfg(not(target_family = "wasm"))]
                     child_child_builder
                         .spawn((
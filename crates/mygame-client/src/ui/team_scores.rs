@@ -0,0 +1,106 @@
+use bevy::{color::palettes::tailwind::SLATE_800, prelude::*};
+use lightyear::prelude::ClientReceiveMessage;
+use mygame_protocol::{
+    component::Team,
+    message::{MatchRules, ServerTeamScores},
+};
+use mygame_render::teams::team_color;
+
+use crate::game_state::GameState;
+
+/// Each team's kills in the corner of the screen, in game modes that have teams
+pub struct TeamScoresPlugin;
+
+impl Plugin for TeamScoresPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TeamScores>()
+            .add_systems(OnEnter(GameState::MainMenu), reset_team_scores)
+            .add_systems(OnEnter(GameState::Playing), spawn_team_scores(GameState::Playing))
+            .add_systems(
+                OnEnter(GameState::Spectating),
+                spawn_team_scores(GameState::Spectating),
+            )
+            .add_systems(Update, (receive_team_scores, update_team_scores).chain());
+    }
+}
+
+#[derive(Resource, Default)]
+struct TeamScores(ServerTeamScores);
+
+impl TeamScores {
+    fn get(&self, team: Team) -> u32 {
+        match team {
+            Team::Red => self.0.red,
+            Team::Blue => self.0.blue,
+        }
+    }
+}
+
+#[derive(Component)]
+struct TeamScoreText(Team);
+
+fn reset_team_scores(mut team_scores: ResMut<TeamScores>) {
+    team_scores.0 = ServerTeamScores::default();
+}
+
+fn receive_team_scores(
+    mut team_scores_events: EventReader<ClientReceiveMessage<ServerTeamScores>>,
+    mut team_scores: ResMut<TeamScores>,
+) {
+    for ev in team_scores_events.read() {
+        team_scores.0 = ev.message.clone();
+    }
+}
+
+/// The scores live only as long as the state they were spawned for
+fn spawn_team_scores(
+    state: GameState,
+) -> impl Fn(Commands, Res<MatchRules>, Res<TeamScores>) {
+    move |mut commands: Commands, match_rules: Res<MatchRules>, team_scores: Res<TeamScores>| {
+        if !match_rules.has_teams() {
+            return;
+        }
+
+        commands
+            .spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.0),
+                    right: Val::Px(10.0),
+                    column_gap: Val::Px(20.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                BackgroundColor(SLATE_800.with_alpha(0.8).into()),
+                Pickable::IGNORE,
+                StateScoped(state.clone()),
+            ))
+            .with_children(|child_builder| {
+                for team in Team::ALL {
+                    child_builder.spawn((
+                        Text::new(format!("{} {}", team.name(), team_scores.get(team))),
+                        TextFont {
+                            font_size: 24.,
+                            ..default()
+                        },
+                        TextColor(team_color(team)),
+                        TeamScoreText(team),
+                    ));
+                }
+            });
+    }
+}
+
+fn update_team_scores(
+    team_scores: Res<TeamScores>,
+    mut q_team_score_texts: Query<(&mut Text, &TeamScoreText)>,
+) {
+    if !team_scores.is_changed() {
+        return;
+    }
+
+    for (mut text, team_score_text) in &mut q_team_score_texts {
+        let team = team_score_text.0;
+        text.0 = format!("{} {}", team.name(), team_scores.get(team));
+    }
+}
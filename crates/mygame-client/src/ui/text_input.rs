@@ -0,0 +1,126 @@
+use bevy::{
+    color::palettes::tailwind::{SLATE_600, SLATE_700},
+    input::{
+        ButtonState,
+        keyboard::{Key, KeyboardInput},
+    },
+    prelude::*,
+};
+
+pub struct TextInputPlugin;
+
+impl Plugin for TextInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (type_into_focused_input, render_text_inputs).chain())
+            .add_observer(focus_on_click)
+            .add_observer(unfocus_others);
+    }
+}
+
+/// A single line of editable text. Clicking it takes keyboard focus.
+/// Read `value` from whatever system owns the input.
+#[derive(Component)]
+#[require(Text, Node, BackgroundColor)]
+pub struct TextInput {
+    pub value: String,
+    pub max_length: usize,
+}
+
+impl TextInput {
+    pub fn new(value: impl Into<String>, max_length: usize) -> Self {
+        Self {
+            value: value.into(),
+            max_length,
+        }
+    }
+}
+
+/// Tag component for the TextInput currently receiving keyboard input.
+/// Only one input is focused at a time.
+#[derive(Component)]
+pub struct TextInputFocused;
+
+fn focus_on_click(
+    trigger: Trigger<Pointer<Click>>,
+    mut commands: Commands,
+    q_text_inputs: Query<(), With<TextInput>>,
+) {
+    if q_text_inputs.contains(trigger.target()) {
+        commands.entity(trigger.target()).insert(TextInputFocused);
+    }
+}
+
+fn unfocus_others(
+    trigger: Trigger<OnAdd, TextInputFocused>,
+    mut commands: Commands,
+    q_focused: Query<Entity, With<TextInputFocused>>,
+) {
+    for entity in &q_focused {
+        if entity != trigger.target() {
+            commands.entity(entity).remove::<TextInputFocused>();
+        }
+    }
+}
+
+fn type_into_focused_input(
+    mut commands: Commands,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut q_focused: Query<(Entity, &mut TextInput), With<TextInputFocused>>,
+) {
+    let Ok((entity, mut text_input)) = q_focused.single_mut() else {
+        keyboard_events.clear();
+        return;
+    };
+
+    for ev in keyboard_events.read() {
+        if ev.state != ButtonState::Pressed {
+            continue;
+        }
+
+        match &ev.logical_key {
+            Key::Character(characters) => {
+                for character in characters.chars().filter(|c| !c.is_control()) {
+                    if text_input.value.chars().count() < text_input.max_length {
+                        text_input.value.push(character);
+                    }
+                }
+            }
+            Key::Space => {
+                if text_input.value.chars().count() < text_input.max_length {
+                    text_input.value.push(' ');
+                }
+            }
+            Key::Backspace => {
+                text_input.value.pop();
+            }
+            Key::Enter | Key::Escape | Key::Tab => {
+                commands.entity(entity).remove::<TextInputFocused>();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn render_text_inputs(
+    mut q_text_inputs: Query<(
+        &TextInput,
+        Has<TextInputFocused>,
+        &mut Text,
+        &mut BackgroundColor,
+    )>,
+) {
+    for (text_input, focused, mut text, mut background) in &mut q_text_inputs {
+        let rendered = if focused {
+            format!("{}_", text_input.value)
+        } else {
+            text_input.value.clone()
+        };
+
+        if text.0 != rendered {
+            text.0 = rendered;
+        }
+
+        let color = if focused { SLATE_600 } else { SLATE_700 };
+        background.0 = color.into();
+    }
+}
@@ -0,0 +1,311 @@
+use std::{collections::HashSet, time::Duration};
+
+use avian3d::prelude::Position;
+use bevy::prelude::*;
+use lightyear::prelude::{ClientId, NetworkIdentity, NetworkTarget, ServerConnectionManager};
+use mygame_protocol::{
+    component::{Bot, Health, Player, Shield, ShieldBoost, Ship, SpawnProtection, Team},
+    message::{HitKind, Killer, MatchRules, Reliable, ServerShipDestroyed, ServerShipHit},
+};
+
+use crate::ship::{RESPAWN_DELAY, ShipHit};
+
+/// Applies damage to ships on the server: shields first, then armor and hull, then the ship
+/// is destroyed.
+///
+/// Anything that hurts a ship (projectiles, ramming, the level, leaving the arena) sends a
+/// DamageEvent instead of touching Health itself. apply_damage takes it from there:
+/// invulnerability, friendly fire, who gets the kill, despawning the ship exactly once, the
+/// DeathEvent, and telling clients.
+pub struct DamagePlugin;
+
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .add_systems(FixedUpdate, (recharge_shields, end_shield_overcharge))
+            .add_systems(FixedPostUpdate, apply_damage.in_set(DamageSet));
+    }
+}
+
+/// Damage sources run before this, so their damage applies the same tick
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DamageSet;
+
+pub const SHIP_MAX_HEALTH: u16 = 60;
+pub const SHIP_MAX_SHIELD: u16 = 30;
+/// Extra shield a ShieldBoost gives on top of the max, lost once the boost runs out
+pub const SHIELD_OVERCHARGE: u16 = 30;
+
+/// How long a shield waits after the last hit before it starts recharging
+pub const SHIELD_RECHARGE_DELAY: Duration = Duration::from_secs(4);
+/// Shield points per second, once recharging
+const SHIELD_RECHARGE_RATE: f32 = 10.0;
+
+/// How long after being hit a ship's attacker still gets the kill, if something else finishes
+/// the ship off
+const KILL_CREDIT_WINDOW: Duration = Duration::from_secs(5);
+
+/// What dealt some damage
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DamageSource {
+    Projectile,
+    /// Ramming another ship
+    Collision,
+    /// Straying outside the arena
+    Boundary,
+    /// Flying into the level
+    Environment,
+}
+
+/// Damage dealt to a ship, sent on the server
+#[derive(Event, Clone, Debug)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: u16,
+    /// Where the hit landed, for effects
+    pub position: Vec3,
+    /// The ship responsible, if any
+    pub attacker: Option<Entity>,
+    pub source: DamageSource,
+}
+
+/// Triggered on the server when damage destroys a ship, after it's been despawned
+#[derive(Event, Clone, Debug)]
+pub struct DeathEvent {
+    pub position: Vec3,
+    /// The player flying the ship, if it wasn't a bot
+    pub victim: Option<ClientId>,
+    pub killer: Option<Killer>,
+    pub victim_team: Option<Team>,
+    pub killer_team: Option<Team>,
+    /// What dealt the final blow
+    pub source: DamageSource,
+}
+
+/// Multiplies damage that gets through to the hull. Ships without it take damage as is.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Armor(pub f32);
+
+/// Server side, counts down to a ship's shield recharging
+#[derive(Component)]
+struct ShieldRecharge {
+    delay: Timer,
+    /// Recharged shield not yet worth a whole point
+    partial: f32,
+}
+
+/// Server side, whoever last damaged a ship, so they get the kill if the level finishes it off
+#[derive(Component)]
+struct LastAttacker {
+    killer: Killer,
+    team: Option<Team>,
+    at: Duration,
+}
+
+impl Default for ShieldRecharge {
+    fn default() -> Self {
+        Self {
+            delay: Timer::new(SHIELD_RECHARGE_DELAY, TimerMode::Once),
+            partial: 0.0,
+        }
+    }
+}
+
+fn apply_damage(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    mut q_ships: Query<
+        (
+            &Position,
+            &mut Health,
+            Option<&mut Shield>,
+            Option<&Armor>,
+            Option<&Player>,
+            Option<&Team>,
+            Has<SpawnProtection>,
+            Option<&LastAttacker>,
+        ),
+        With<Ship>,
+    >,
+    q_attackers: Query<(Option<&Player>, Option<&Bot>, Option<&Team>)>,
+    time: Res<Time>,
+    match_rules: Res<MatchRules>,
+    server: Option<ResMut<ServerConnectionManager>>,
+    network_identity: NetworkIdentity,
+) {
+    // Clients only predict where shots land, the server decides what they do
+    if !network_identity.is_server() {
+        damage_events.clear();
+        return;
+    }
+
+    let Some(mut server) = server else {
+        return;
+    };
+
+    // We want the despawn to happen EXACTLY once, even if two projectiles hit this tick
+    let mut destroyed = HashSet::new();
+
+    for damage in damage_events.read() {
+        if destroyed.contains(&damage.target) {
+            continue;
+        }
+
+        let Ok((
+            ship_position,
+            mut health,
+            shield,
+            armor,
+            victim,
+            victim_team,
+            spawn_protected,
+            last_attacker,
+        )) = q_ships.get_mut(damage.target)
+        else {
+            continue;
+        };
+
+        // The hit is absorbed by the spawn protection bubble
+        if spawn_protected {
+            continue;
+        }
+
+        let attacker = damage
+            .attacker
+            .and_then(|attacker| q_attackers.get(attacker).ok());
+
+        let (killer, killer_team) = match attacker {
+            Some((Some(player), _, team)) => (Some(Killer::Player(player.0)), team.copied()),
+            Some((_, Some(bot), team)) => (Some(Killer::Bot(bot.0)), team.copied()),
+            _ => (None, None),
+        };
+
+        let same_team = victim_team.is_some() && victim_team.copied() == killer_team;
+        if same_team && !match_rules.friendly_fire {
+            continue;
+        }
+
+        let now = time.elapsed();
+
+        // Damage with nobody behind it is credited to whoever hit the ship last, if it was recent
+        let (killer, killer_team) = match (killer, last_attacker) {
+            (Some(killer), _) => {
+                commands.entity(damage.target).insert(LastAttacker {
+                    killer,
+                    team: killer_team,
+                    at: now,
+                });
+
+                (Some(killer), killer_team)
+            }
+            (None, Some(last_attacker)) if now - last_attacker.at <= KILL_CREDIT_WINDOW => {
+                (Some(last_attacker.killer), last_attacker.team)
+            }
+            (None, _) => (None, None),
+        };
+
+        let mut remaining = damage.amount;
+
+        if let Some(mut shield) = shield {
+            let absorbed = remaining.min(shield.current);
+            shield.current -= absorbed;
+            remaining -= absorbed;
+
+            commands
+                .entity(damage.target)
+                .insert(ShieldRecharge::default());
+        }
+
+        let hull_damage = match armor {
+            Some(armor) => (remaining as f32 * armor.0).round() as u16,
+            None => remaining,
+        };
+
+        let kind = if hull_damage > 0 {
+            HitKind::Hull
+        } else {
+            HitKind::Shield
+        };
+
+        if hull_damage >= health.current {
+            destroyed.insert(damage.target);
+            commands.entity(damage.target).despawn();
+
+            if let Some(victim) = victim {
+                if let Err(e) = server.send_message_to_target::<Reliable, ServerShipDestroyed>(
+                    &ServerShipDestroyed {
+                        position: ship_position.0,
+                        killer,
+                        respawn_delay: RESPAWN_DELAY,
+                    },
+                    NetworkTarget::Single(victim.0),
+                ) {
+                    error!("unable to tell client {} their ship was destroyed: {}", victim.0, e);
+                }
+            }
+
+            commands.trigger(DeathEvent {
+                position: ship_position.0,
+                victim: victim.map(|player| player.0),
+                killer,
+                victim_team: victim_team.copied(),
+                killer_team,
+                source: damage.source,
+            });
+        } else {
+            health.current -= hull_damage;
+        }
+
+        if let Err(e) = server.send_message_to_target::<Reliable, ServerShipHit>(
+            &ServerShipHit {
+                position: damage.position,
+                kind,
+            },
+            NetworkTarget::All,
+        ) {
+            error!("unable to send ship hit: {}", e);
+        }
+
+        commands.trigger(ShipHit {
+            position: damage.position,
+            kind,
+        });
+    }
+}
+
+fn recharge_shields(
+    mut commands: Commands,
+    time: Res<Time<Fixed>>,
+    mut q_recharging: Query<(Entity, &mut Shield, &mut ShieldRecharge)>,
+) {
+    for (ship, mut shield, mut recharge) in &mut q_recharging {
+        if !recharge.delay.tick(time.delta()).finished() {
+            continue;
+        }
+
+        // Still overcharged, even after the hit
+        if shield.current >= shield.max {
+            commands.entity(ship).remove::<ShieldRecharge>();
+            continue;
+        }
+
+        recharge.partial += SHIELD_RECHARGE_RATE * time.delta_secs();
+
+        let whole_points = recharge.partial.floor();
+        recharge.partial -= whole_points;
+        shield.current = (shield.current + whole_points as u16).min(shield.max);
+
+        if shield.current == shield.max {
+            commands.entity(ship).remove::<ShieldRecharge>();
+        }
+    }
+}
+
+/// Whatever's left of an overcharge goes once the ShieldBoost runs out
+fn end_shield_overcharge(mut q_shields: Query<&mut Shield, Without<ShieldBoost>>) {
+    for mut shield in &mut q_shields {
+        if shield.current > shield.max {
+            shield.current = shield.max;
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use avian3d::prelude::{
+    Collider, ColliderOf, CollidingEntities, CollisionLayers, PhysicsSet, Position, RigidBody,
+    Sensor,
+};
+use bevy::prelude::*;
+use lightyear::prelude::NetworkIdentity;
+use mygame_assets::{CollisionMask, LevelState, assets::GlobalAssets};
+use mygame_protocol::component::{Flag, FlagState, Ship, Team};
+
+use crate::{Rendered, Simulated};
+
+/// Capture the flag rules, run by the server and predicted by clients.
+///
+/// Touch the enemy flag to pick it up, touch your own dropped flag to send it home, and bring the
+/// enemy flag to your own flag while it's home to capture. A carrier that's destroyed drops the flag.
+pub struct FlagPlugin;
+
+impl Plugin for FlagPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (add_rendered_flag_components, add_simulated_flag_components)
+                .run_if(in_state(LevelState::Loaded))
+                .after(RunFixedMainLoopSystem::AfterFixedMainLoop),
+        );
+
+        app.add_systems(
+            FixedPostUpdate,
+            (drop_lost_flags, touch_flags, carry_flags)
+                .chain()
+                .after(PhysicsSet::StepSimulation)
+                .before(PhysicsSet::Sync),
+        );
+    }
+}
+
+/// How high above its carrier a flag floats
+const CARRY_HEIGHT: f32 = 2.5;
+
+const FLAG_PICKUP_RADIUS: f32 = 3.0;
+
+/// Triggered on the server when a team brings the enemy flag home
+#[derive(Event)]
+pub struct FlagCaptured {
+    pub team: Team,
+}
+
+fn add_rendered_flag_components(
+    mut commands: Commands,
+    q_rendered_flags: Query<Entity, (Rendered, With<Flag>, Without<SceneRoot>)>,
+    global_assets: Res<GlobalAssets>,
+) {
+    for flag in &q_rendered_flags {
+        commands
+            .entity(flag)
+            .insert(SceneRoot(global_assets.target.clone()));
+    }
+}
+
+fn add_simulated_flag_components(
+    mut commands: Commands,
+    q_simulated_flags: Query<Entity, (Simulated, With<Flag>, Without<RigidBody>)>,
+) {
+    for flag in &q_simulated_flags {
+        commands.entity(flag).insert((
+            RigidBody::Kinematic,
+            Collider::sphere(FLAG_PICKUP_RADIUS),
+            Sensor,
+            CollidingEntities::default(),
+            CollisionLayers::new(CollisionMask::Flag, [CollisionMask::Ship]),
+        ));
+    }
+}
+
+/// A carrier that's gone, usually destroyed, leaves the flag where it was
+fn drop_lost_flags(
+    mut q_flags: Query<&mut FlagState, (Simulated, With<Flag>)>,
+    q_ships: Query<(), With<Ship>>,
+) {
+    for mut flag_state in &mut q_flags {
+        if let FlagState::Carried(carrier) = *flag_state {
+            if !q_ships.contains(carrier) {
+                *flag_state = FlagState::Dropped;
+            }
+        }
+    }
+}
+
+fn touch_flags(
+    mut commands: Commands,
+    mut q_flags: Query<
+        (Entity, &Flag, &Team, &mut FlagState, &mut Position, &CollidingEntities),
+        Simulated,
+    >,
+    q_ships: Query<&Team, (Simulated, With<Ship>)>,
+    q_collider_of: Query<&ColliderOf>,
+    network_identity: NetworkIdentity,
+) {
+    // Carrier ship to the flag it carries
+    let carried: HashMap<Entity, Entity> = q_flags
+        .iter()
+        .filter_map(|(flag, _, _, flag_state, _, _)| match *flag_state {
+            FlagState::Carried(carrier) => Some((carrier, flag)),
+            _ => None,
+        })
+        .collect();
+
+    let mut captured_flags = Vec::new();
+
+    for (_, flag, flag_team, mut flag_state, mut flag_position, colliding_entities) in &mut q_flags {
+        for collider in colliding_entities.iter() {
+            // Ship colliders are children of the ship
+            let ship = q_collider_of
+                .get(*collider)
+                .map(|collider_of| collider_of.body)
+                .unwrap_or(*collider);
+
+            let Ok(ship_team) = q_ships.get(ship) else {
+                continue;
+            };
+
+            match *flag_state {
+                FlagState::AtBase | FlagState::Dropped
+                    if ship_team != flag_team && !carried.contains_key(&ship) =>
+                {
+                    *flag_state = FlagState::Carried(ship);
+                    break;
+                }
+                FlagState::Dropped if ship_team == flag_team => {
+                    *flag_state = FlagState::AtBase;
+                    flag_position.0 = flag.base;
+                    break;
+                }
+                FlagState::AtBase if ship_team == flag_team => {
+                    if let Some(enemy_flag) = carried.get(&ship) {
+                        captured_flags.push((*enemy_flag, *ship_team));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for (captured_flag, team) in captured_flags {
+        let Ok((_, flag, _, mut flag_state, mut flag_position, _)) = q_flags.get_mut(captured_flag) else {
+            continue;
+        };
+
+        *flag_state = FlagState::AtBase;
+        flag_position.0 = flag.base;
+
+        // Clients only predict the flag going home, the server keeps score
+        if network_identity.is_server() {
+            commands.trigger(FlagCaptured { team });
+        }
+    }
+}
+
+fn carry_flags(
+    mut q_flags: Query<(&FlagState, &mut Position), (Simulated, With<Flag>)>,
+    q_carriers: Query<&Position, (With<Ship>, Without<Flag>)>,
+) {
+    for (flag_state, mut flag_position) in &mut q_flags {
+        if let FlagState::Carried(carrier) = flag_state {
+            if let Ok(carrier_position) = q_carriers.get(*carrier) {
+                flag_position.0 = carrier_position.0 + Vec3::Y * CARRY_HEIGHT;
+            }
+        }
+    }
+}
@@ -16,6 +16,10 @@ impl Plugin for LevelPlugin {
     }
 }
 
+/// Tag component for the root of the spawned level scene, so the whole level can be torn down
+#[derive(Component)]
+pub struct LevelRoot;
+
 fn level_loaded(
     mut commands: Commands,
     current_level: Res<CurrentLevel>,
@@ -23,7 +27,7 @@ fn level_loaded(
 ) {
     match **current_level {
         Level::Example => {
-            commands.spawn(SceneRoot(level_assets.example_level.clone()));
+            commands.spawn((SceneRoot(level_assets.example_level.clone()), LevelRoot));
         }
         Level::Void => {}
     }
@@ -4,9 +4,14 @@ use lightyear::{client::config::ClientConfig, prelude::{
     client::{Confirmed, Interpolated, Predicted, VisualInterpolateStatus}, server::ReplicateToClient, PreSpawned, ReplicationGroup
 }, server::config::ServerConfig};
 use mygame_assets::AssetPlugin;
-use mygame_protocol::ProtocolPlugin;
+use mygame_protocol::{ProtocolPlugin, message::MatchRules};
 
+pub mod damage;
+pub mod flag;
 pub mod level;
+pub mod match_recording;
+pub mod pickup;
+pub mod rollback_diagnostics;
 pub mod ship;
 
 pub struct CommonPlugin;
@@ -22,7 +27,12 @@ impl Plugin for CommonPlugin {
                     .disable::<PhysicsInterpolationPlugin>(),
                 level::LevelPlugin,
                 ship::ShipPlugin,
+                damage::DamagePlugin,
+                flag::FlagPlugin,
+                pickup::PickupPlugin,
             ))
+            // The server's comes from its options, and clients are told in ServerWelcome
+            .init_resource::<MatchRules>()
             .insert_resource(NarrowPhaseConfig {
                 contact_tolerance: 0.1,
                 ..default()
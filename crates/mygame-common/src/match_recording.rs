@@ -0,0 +1,304 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use avian3d::prelude::{Position, Rotation};
+use bevy::prelude::*;
+use bincode::{
+    config::{Configuration, standard},
+    error::{DecodeError, EncodeError},
+};
+use mygame_assets::{CurrentLevel, LevelState};
+use mygame_protocol::{
+    component::{Bot, Health, Player, Projectile, Ship},
+    message::{HitKind, Level},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::ship::ShipHit;
+
+/// Records what the server simulated, every tick, so a match can be watched back later.
+///
+/// The file is a RecordingHeader followed by one RecordedFrame per fixed tick, each bincode
+/// encoded back to back. Frames are streamed to disk as they're recorded, so a server that dies
+/// mid-match still leaves a playable recording of everything up to the last flush.
+///
+/// Only what changed is written: entities are announced when they spawn and despawn,
+/// transforms when they move and health when it changes.
+pub struct MatchRecordingPlugin;
+
+impl Plugin for MatchRecordingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(on_ship_hit)
+            .add_systems(
+                OnEnter(LevelState::Loaded),
+                start_recording.run_if(resource_exists::<RecordMatch>),
+            )
+            .add_systems(
+                FixedLast,
+                record_frame.run_if(resource_exists::<MatchRecorder>),
+            )
+            .add_systems(
+                Last,
+                (
+                    flush_recording,
+                    finish_recording.run_if(on_event::<AppExit>),
+                )
+                    .chain()
+                    .run_if(resource_exists::<MatchRecorder>),
+            );
+    }
+}
+
+/// Bumped whenever the layout of a recording changes, so old files are rejected instead of misread
+pub const RECORDING_VERSION: u32 = 2;
+
+/// How often buffered frames are pushed to disk
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+const BINCODE_CONFIG: Configuration = standard();
+
+/// When present on the server, the match is recorded to this file once the level loads
+#[derive(Resource, Clone)]
+pub struct RecordMatch(pub PathBuf);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordingHeader {
+    pub version: u32,
+    pub level: Level,
+    pub tick_duration: Duration,
+}
+
+/// What a recorded entity is, so the replay knows how to draw it
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum RecordedKind {
+    Ship {
+        player: Option<u64>,
+        bot: Option<u64>,
+    },
+    Projectile,
+}
+
+/// Entities are identified by the server's Entity bits, which are unique for a whole session
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum RecordedEvent {
+    Spawned { id: u64, kind: RecordedKind },
+    Despawned { id: u64 },
+    Moved { id: u64, position: Vec3, rotation: Quat },
+    HealthChanged { id: u64, health: Health },
+    ShipHit { position: Vec3, kind: HitKind },
+}
+
+/// Everything that happened during one server tick
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RecordedFrame {
+    pub events: Vec<RecordedEvent>,
+}
+
+#[derive(Debug)]
+pub enum RecordingError {
+    Io(std::io::Error),
+    Encode(EncodeError),
+    Decode(DecodeError),
+    UnsupportedVersion(u32),
+}
+
+impl std::fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordingError::Io(e) => write!(f, "{}", e),
+            RecordingError::Encode(e) => write!(f, "unable to encode recording: {}", e),
+            RecordingError::Decode(e) => write!(f, "unable to decode recording: {}", e),
+            RecordingError::UnsupportedVersion(version) => write!(
+                f,
+                "recording is version {}, expected {}",
+                version, RECORDING_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RecordingError {}
+
+/// A whole recording, loaded back into memory for playback
+#[derive(Debug, Clone)]
+pub struct MatchRecording {
+    pub header: RecordingHeader,
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl MatchRecording {
+    pub fn load(path: &Path) -> Result<Self, RecordingError> {
+        let mut reader = BufReader::new(File::open(path).map_err(RecordingError::Io)?);
+
+        let header: RecordingHeader = bincode::serde::decode_from_std_read(&mut reader, BINCODE_CONFIG)
+            .map_err(RecordingError::Decode)?;
+
+        if header.version != RECORDING_VERSION {
+            return Err(RecordingError::UnsupportedVersion(header.version));
+        }
+
+        let mut frames = Vec::new();
+        loop {
+            match bincode::serde::decode_from_std_read(&mut reader, BINCODE_CONFIG) {
+                Ok(frame) => frames.push(frame),
+                // Running out of file is how the recording ends, possibly mid-frame if the server crashed
+                Err(DecodeError::UnexpectedEnd { .. }) => break,
+                Err(DecodeError::Io { inner, .. })
+                    if inner.kind() == std::io::ErrorKind::UnexpectedEof =>
+                {
+                    break;
+                }
+                Err(e) => return Err(RecordingError::Decode(e)),
+            }
+        }
+
+        Ok(Self { header, frames })
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.header.tick_duration * self.frames.len() as u32
+    }
+}
+
+/// The open recording file, and anything waiting to go into the current frame
+#[derive(Resource)]
+struct MatchRecorder {
+    writer: BufWriter<File>,
+    pending_hits: Vec<(Vec3, HitKind)>,
+    flush_timer: Timer,
+}
+
+impl MatchRecorder {
+    fn create(path: &Path, header: &RecordingHeader) -> Result<Self, RecordingError> {
+        let mut writer = BufWriter::new(File::create(path).map_err(RecordingError::Io)?);
+
+        bincode::serde::encode_into_std_write(header, &mut writer, BINCODE_CONFIG)
+            .map_err(RecordingError::Encode)?;
+
+        Ok(Self {
+            writer,
+            pending_hits: Vec::new(),
+            flush_timer: Timer::new(FLUSH_INTERVAL, TimerMode::Repeating),
+        })
+    }
+}
+
+fn start_recording(
+    mut commands: Commands,
+    record_match: Res<RecordMatch>,
+    current_level: Res<CurrentLevel>,
+    time: Res<Time<Fixed>>,
+) {
+    let header = RecordingHeader {
+        version: RECORDING_VERSION,
+        level: current_level.0,
+        tick_duration: time.timestep(),
+    };
+
+    match MatchRecorder::create(&record_match.0, &header) {
+        Ok(recorder) => {
+            info!("recording match to {:?}", record_match.0);
+            commands.insert_resource(recorder);
+        }
+        Err(e) => error!("unable to record match to {:?}: {}", record_match.0, e),
+    }
+}
+
+fn on_ship_hit(trigger: Trigger<ShipHit>, recorder: Option<ResMut<MatchRecorder>>) {
+    if let Some(mut recorder) = recorder {
+        recorder.pending_hits.push((trigger.position, trigger.kind));
+    }
+}
+
+fn record_frame(
+    mut commands: Commands,
+    mut recorder: ResMut<MatchRecorder>,
+    mut removed_ships: RemovedComponents<Ship>,
+    mut removed_projectiles: RemovedComponents<Projectile>,
+    q_spawned: Query<
+        (Entity, Has<Ship>, Option<&Player>, Option<&Bot>),
+        Or<(Added<Ship>, Added<Projectile>)>,
+    >,
+    q_moved: Query<
+        (Entity, &Position, &Rotation),
+        (
+            Or<(With<Ship>, With<Projectile>)>,
+            Or<(Changed<Position>, Changed<Rotation>)>,
+        ),
+    >,
+    q_health: Query<(Entity, &Health), (With<Ship>, Changed<Health>)>,
+) {
+    let mut frame = RecordedFrame::default();
+
+    // Despawns first, so a spawn reusing the same index in this frame isn't immediately undone
+    for entity in removed_ships.read().chain(removed_projectiles.read()) {
+        frame.events.push(RecordedEvent::Despawned {
+            id: entity.to_bits(),
+        });
+    }
+
+    for (entity, is_ship, player, bot) in &q_spawned {
+        let kind = if is_ship {
+            RecordedKind::Ship {
+                player: player.map(|player| player.0.to_bits()),
+                bot: bot.map(|bot| bot.0),
+            }
+        } else {
+            RecordedKind::Projectile
+        };
+
+        frame.events.push(RecordedEvent::Spawned {
+            id: entity.to_bits(),
+            kind,
+        });
+    }
+
+    for (entity, position, rotation) in &q_moved {
+        frame.events.push(RecordedEvent::Moved {
+            id: entity.to_bits(),
+            position: position.0,
+            rotation: rotation.0,
+        });
+    }
+
+    for (entity, health) in &q_health {
+        frame.events.push(RecordedEvent::HealthChanged {
+            id: entity.to_bits(),
+            health: health.clone(),
+        });
+    }
+
+    for (position, kind) in recorder.pending_hits.drain(..) {
+        frame.events.push(RecordedEvent::ShipHit { position, kind });
+    }
+
+    if let Err(e) = bincode::serde::encode_into_std_write(&frame, &mut recorder.writer, BINCODE_CONFIG) {
+        error!("stopping match recording, unable to write frame: {}", e);
+        commands.remove_resource::<MatchRecorder>();
+    }
+}
+
+fn flush_recording(time: Res<Time<Real>>, mut recorder: ResMut<MatchRecorder>) {
+    // The timer ticks every frame, so don't let that count as a change
+    let recorder = recorder.bypass_change_detection();
+
+    if !recorder.flush_timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    if let Err(e) = recorder.writer.flush() {
+        error!("unable to flush match recording: {}", e);
+    }
+}
+
+fn finish_recording(mut commands: Commands, mut recorder: ResMut<MatchRecorder>) {
+    if let Err(e) = recorder.writer.flush() {
+        error!("unable to flush match recording: {}", e);
+    }
+
+    commands.remove_resource::<MatchRecorder>();
+}
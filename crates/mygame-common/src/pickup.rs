@@ -0,0 +1,290 @@
+use std::time::Duration;
+
+use avian3d::prelude::{
+    Collider, ColliderOf, CollidingEntities, CollisionLayers, PhysicsSet, RigidBody, Sensor,
+};
+use bevy::prelude::*;
+use lightyear::prelude::{NetworkIdentity, Tick, TickManager, client::Rollback};
+use mygame_assets::{CollisionMask, LevelState};
+use mygame_protocol::component::{
+    Health, Pickup, PickupAvailable, PickupKind, RapidFire, Shield, ShieldBoost, Ship, SpeedBoost,
+};
+
+use crate::{Rendered, Simulated, damage::SHIELD_OVERCHARGE};
+
+/// Collecting pickups, and the buffs they give wearing off.
+///
+/// The server decides who gets a pickup. A client touching one with its own ship hides it and
+/// applies the buff straight away rather than waiting to hear back, and shows it again if the
+/// server never agrees, taking the buff back off.
+pub struct PickupPlugin;
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                add_pickup_components.run_if(in_state(LevelState::Loaded)),
+                (expire_local_collections, show_available_pickups).chain(),
+            )
+                .after(RunFixedMainLoopSystem::AfterFixedMainLoop),
+        );
+
+        app.add_systems(
+            FixedUpdate,
+            (
+                wear_off::<RapidFire>,
+                wear_off::<SpeedBoost>,
+                wear_off::<ShieldBoost>,
+            ),
+        );
+
+        app.add_systems(
+            FixedPostUpdate,
+            collect_pickups
+                .after(PhysicsSet::StepSimulation)
+                .before(PhysicsSet::Sync),
+        );
+    }
+}
+
+const PICKUP_RADIUS: f32 = 2.0;
+
+pub const RAPID_FIRE_DURATION: Duration = Duration::from_secs(10);
+pub const SPEED_BOOST_DURATION: Duration = Duration::from_secs(8);
+pub const SHIELD_BOOST_DURATION: Duration = Duration::from_secs(6);
+
+/// How long a client keeps a pickup it collected hidden while waiting for the server to agree
+const LOCAL_COLLECTION_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A timed buff on a ship, removed once the tick it expires on comes around
+pub trait Buff: Component {
+    fn expires(&self) -> Tick;
+}
+
+impl Buff for RapidFire {
+    fn expires(&self) -> Tick {
+        self.expires
+    }
+}
+
+impl Buff for SpeedBoost {
+    fn expires(&self) -> Tick {
+        self.expires
+    }
+}
+
+impl Buff for ShieldBoost {
+    fn expires(&self) -> Tick {
+        self.expires
+    }
+}
+
+/// The tick being simulated, which during a rollback is behind the TickManager's
+fn simulated_tick(tick_manager: &TickManager, rollback: Option<Res<Rollback>>) -> Tick {
+    rollback
+        .and_then(|rollback| rollback.get_rollback_tick())
+        .unwrap_or_else(|| tick_manager.tick())
+}
+
+/// The tick a buff lasting `duration` from `tick` expires on
+fn expiry_tick(tick: Tick, duration: Duration, timestep: Duration) -> Tick {
+    let ticks = (duration.as_secs_f64() / timestep.as_secs_f64()).ceil();
+    tick + ticks as i16
+}
+
+/// On a client, a pickup this client's ship collected that the server hasn't confirmed yet,
+/// and what it gave the ship so that can be undone if the server never does
+#[derive(Component)]
+pub struct PickupCollectedLocally {
+    timer: Timer,
+    ship: Entity,
+    /// The health a repair, or shield an overcharge, replaced and what it was set to instead.
+    /// Only put back if nothing else has changed it since.
+    replaced: Option<(u16, u16)>,
+}
+
+fn add_pickup_components(
+    mut commands: Commands,
+    q_pickups: Query<Entity, (Rendered, With<Pickup>, Without<RigidBody>)>,
+) {
+    for pickup in &q_pickups {
+        commands.entity(pickup).insert((
+            RigidBody::Kinematic,
+            Collider::sphere(PICKUP_RADIUS),
+            Sensor,
+            CollidingEntities::default(),
+            CollisionLayers::new(CollisionMask::Pickup, [CollisionMask::Ship]),
+            Visibility::default(),
+        ));
+    }
+}
+
+fn wear_off<B: Buff>(
+    mut commands: Commands,
+    tick_manager: Res<TickManager>,
+    rollback: Option<Res<Rollback>>,
+    q_buffs: Query<(Entity, &B), Simulated>,
+) {
+    let tick = simulated_tick(&tick_manager, rollback);
+
+    for (ship, buff) in &q_buffs {
+        if tick - buff.expires() >= 0 {
+            commands.entity(ship).remove::<B>();
+        }
+    }
+}
+
+/// Pickups are replicated to clients for interpolation rather than prediction, so both the
+/// server's and the clients' copies are collected here
+fn collect_pickups(
+    mut commands: Commands,
+    mut q_pickups: Query<
+        (
+            Entity,
+            &Pickup,
+            &mut PickupAvailable,
+            &CollidingEntities,
+            Has<PickupCollectedLocally>,
+        ),
+        Rendered,
+    >,
+    mut q_ships: Query<(&mut Health, Option<&mut Shield>), (Simulated, With<Ship>)>,
+    q_collider_of: Query<&ColliderOf>,
+    network_identity: NetworkIdentity,
+    tick_manager: Res<TickManager>,
+    rollback: Option<Res<Rollback>>,
+    time: Res<Time<Fixed>>,
+) {
+    let tick = simulated_tick(&tick_manager, rollback);
+    let expires = |duration| expiry_tick(tick, duration, time.timestep());
+
+    for (pickup, kind, mut available, colliding_entities, collected_locally) in &mut q_pickups {
+        if !available.0 || collected_locally {
+            continue;
+        }
+
+        for collider in colliding_entities.iter() {
+            // Ship colliders are children of the ship
+            let ship = q_collider_of
+                .get(*collider)
+                .map(|collider_of| collider_of.body)
+                .unwrap_or(*collider);
+
+            let Ok((mut health, shield)) = q_ships.get_mut(ship) else {
+                continue;
+            };
+
+            let mut replaced = None;
+
+            match kind.0 {
+                PickupKind::Repair => {
+                    replaced = Some((health.current, health.max));
+                    health.current = health.max;
+                }
+                PickupKind::RapidFire => {
+                    commands.entity(ship).insert(RapidFire {
+                        expires: expires(RAPID_FIRE_DURATION),
+                    });
+                }
+                // Fills the shield and overcharges it until the boost runs out
+                PickupKind::Shield => {
+                    if let Some(mut shield) = shield {
+                        let overcharged = shield.current.max(shield.max + SHIELD_OVERCHARGE);
+                        replaced = Some((shield.current, overcharged));
+                        shield.current = overcharged;
+                    }
+
+                    commands.entity(ship).insert(ShieldBoost {
+                        expires: expires(SHIELD_BOOST_DURATION),
+                    });
+                }
+                PickupKind::SpeedBoost => {
+                    commands.entity(ship).insert(SpeedBoost {
+                        expires: expires(SPEED_BOOST_DURATION),
+                    });
+                }
+            }
+
+            if network_identity.is_server() {
+                available.0 = false;
+            } else {
+                commands.entity(pickup).insert(PickupCollectedLocally {
+                    timer: Timer::new(LOCAL_COLLECTION_TIMEOUT, TimerMode::Once),
+                    ship,
+                    replaced,
+                });
+            }
+
+            break;
+        }
+    }
+}
+
+/// Once the server says the pickup is gone, the client stops predicting it. If it's been too
+/// long for the server to still agree, the ship loses whatever the pickup gave it too.
+fn expire_local_collections(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_pickups: Query<(Entity, &Pickup, &PickupAvailable, &mut PickupCollectedLocally)>,
+    mut q_ships: Query<(&mut Health, Option<&mut Shield>), (Simulated, With<Ship>)>,
+) {
+    for (pickup, kind, available, mut collected_locally) in &mut q_pickups {
+        if !available.0 {
+            commands.entity(pickup).remove::<PickupCollectedLocally>();
+            continue;
+        }
+
+        if !collected_locally.timer.tick(time.delta()).finished() {
+            continue;
+        }
+
+        commands.entity(pickup).remove::<PickupCollectedLocally>();
+
+        // Gone already if the ship was destroyed in the meantime
+        let Ok((mut health, shield)) = q_ships.get_mut(collected_locally.ship) else {
+            continue;
+        };
+
+        // Health or shield the server has since set, or damage has since changed, is left alone
+        // rather than guessing how much of it came from the pickup
+        let put_back = |current: &mut u16| match collected_locally.replaced {
+            Some((before, after)) if *current == after => *current = before,
+            _ => {}
+        };
+
+        match kind.0 {
+            PickupKind::Repair => put_back(&mut health.current),
+            PickupKind::RapidFire => {
+                commands.entity(collected_locally.ship).remove::<RapidFire>();
+            }
+            PickupKind::Shield => {
+                if let Some(mut shield) = shield {
+                    put_back(&mut shield.current);
+                }
+
+                commands.entity(collected_locally.ship).remove::<ShieldBoost>();
+            }
+            PickupKind::SpeedBoost => {
+                commands.entity(collected_locally.ship).remove::<SpeedBoost>();
+            }
+        }
+    }
+}
+
+fn show_available_pickups(
+    mut q_pickups: Query<
+        (&PickupAvailable, Has<PickupCollectedLocally>, &mut Visibility),
+        (Rendered, With<Pickup>),
+    >,
+) {
+    for (available, collected_locally, mut visibility) in &mut q_pickups {
+        let shown = if available.0 && !collected_locally {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+
+        visibility.set_if_neq(shown);
+    }
+}
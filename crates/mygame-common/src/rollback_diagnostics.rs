@@ -0,0 +1,296 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use avian3d::prelude::{PhysicsSet, Position, Rotation};
+use bevy::prelude::*;
+use lightyear::prelude::{
+    PreSpawned, Tick, TickManager,
+    client::{Confirmed, Predicted, PredictionSet, Rollback},
+};
+use mygame_protocol::component::Ship;
+use serde::Serialize;
+
+/// Catches mispredictions on predicted ships, the thing that makes lightyear roll back.
+///
+/// Every fixed tick the predicted Position/Rotation of each ship is recorded. When a confirmed
+/// update arrives from the server it's compared against what we predicted for that tick, and any
+/// difference is logged and added to the RollbackReport. PreSpawned projectiles that are despawned
+/// without ever being matched to a server entity are reported too, which points at `fire` hashing
+/// differently on each side.
+///
+/// Nothing here renders, so it works the same in a headless client. It's a debugging aid, so
+/// it's only added when asked for, with the launcher's --rollback-report, and by the harness.
+/// Each misprediction is logged at debug level, and the report keeps the first
+/// MAX_RECORDED of each kind, counting the rest.
+pub struct RollbackDiagnosticsPlugin;
+
+impl Plugin for RollbackDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RollbackReport>()
+            .add_observer(on_unmatched_prespawn_despawned)
+            .add_systems(
+                FixedPostUpdate,
+                (add_predicted_history, record_predicted_history)
+                    .chain()
+                    .after(PhysicsSet::Sync),
+            )
+            .add_systems(
+                PreUpdate,
+                compare_confirmed_to_predicted
+                    .after(PredictionSet::Sync)
+                    .before(PredictionSet::CheckRollback),
+            )
+            .add_systems(Last, write_report_on_exit.run_if(on_event::<AppExit>));
+    }
+}
+
+/// How many ticks of predicted history to keep per ship. Confirmed updates older than this
+/// can't be checked, but they'd be far outside any sensible RTT anyway.
+const HISTORY_LENGTH: usize = 256;
+/// How many mispredictions and unmatched prespawns the report keeps. A badly broken session
+/// would otherwise grow it for as long as it runs.
+const MAX_RECORDED: usize = 1000;
+
+/// When present, the report is written here as RON when the app exits
+#[derive(Resource, Clone)]
+pub struct RollbackReportPath(pub PathBuf);
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum MispredictedValue {
+    Position { predicted: Vec3, confirmed: Vec3 },
+    Rotation { predicted: Quat, confirmed: Quat },
+}
+
+impl MispredictedValue {
+    /// Distance for positions, angle in radians for rotations
+    pub fn delta(&self) -> f32 {
+        match self {
+            MispredictedValue::Position {
+                predicted,
+                confirmed,
+            } => predicted.distance(*confirmed),
+            MispredictedValue::Rotation {
+                predicted,
+                confirmed,
+            } => predicted.angle_between(*confirmed),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Misprediction {
+    pub tick: u16,
+    pub entity: Entity,
+    pub value: MispredictedValue,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct UnmatchedPreSpawn {
+    pub tick: u16,
+    pub entity: Entity,
+    pub hash: Option<u64>,
+}
+
+/// Everything the diagnostics have seen this session
+#[derive(Resource, Serialize, Default, Debug, Clone)]
+pub struct RollbackReport {
+    /// Confirmed updates that had a matching predicted tick to compare against
+    pub ticks_checked: u32,
+    /// Confirmed updates for ticks we no longer (or never) had a prediction for
+    pub ticks_unchecked: u32,
+    pub max_position_delta: f32,
+    pub max_rotation_delta: f32,
+    pub mispredictions: Vec<Misprediction>,
+    pub unmatched_prespawns: Vec<UnmatchedPreSpawn>,
+    /// Mispredictions and unmatched prespawns beyond MAX_RECORDED, counted but not kept
+    pub mispredictions_dropped: u32,
+    pub unmatched_prespawns_dropped: u32,
+}
+
+impl RollbackReport {
+    fn record(&mut self, misprediction: Misprediction) {
+        let delta = misprediction.value.delta();
+
+        match misprediction.value {
+            MispredictedValue::Position { .. } => {
+                self.max_position_delta = self.max_position_delta.max(delta)
+            }
+            MispredictedValue::Rotation { .. } => {
+                self.max_rotation_delta = self.max_rotation_delta.max(delta)
+            }
+        }
+
+        if self.mispredictions.len() < MAX_RECORDED {
+            self.mispredictions.push(misprediction);
+        } else {
+            self.mispredictions_dropped += 1;
+        }
+    }
+
+    fn record_unmatched_prespawn(&mut self, unmatched_prespawn: UnmatchedPreSpawn) {
+        if self.unmatched_prespawns.len() < MAX_RECORDED {
+            self.unmatched_prespawns.push(unmatched_prespawn);
+        } else {
+            self.unmatched_prespawns_dropped += 1;
+        }
+    }
+
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+}
+
+/// The predicted Position/Rotation of a ship at each recent tick
+#[derive(Component, Default)]
+struct PredictedHistory(VecDeque<(Tick, Position, Rotation)>);
+
+impl PredictedHistory {
+    fn get(&self, tick: Tick) -> Option<&(Tick, Position, Rotation)> {
+        self.0.iter().rev().find(|(recorded_tick, ..)| *recorded_tick == tick)
+    }
+
+    /// Re-simulated ticks replace what was recorded the first time around
+    fn record(&mut self, tick: Tick, position: Position, rotation: Rotation) {
+        self.0.retain(|(recorded_tick, ..)| *recorded_tick != tick);
+        if self.0.len() == HISTORY_LENGTH {
+            self.0.pop_front();
+        }
+        self.0.push_back((tick, position, rotation));
+    }
+}
+
+fn add_predicted_history(
+    mut commands: Commands,
+    q_new_predicted: Query<Entity, (With<Predicted>, With<Ship>, Without<PredictedHistory>)>,
+) {
+    for entity in &q_new_predicted {
+        commands.entity(entity).insert(PredictedHistory::default());
+    }
+}
+
+fn record_predicted_history(
+    tick_manager: Res<TickManager>,
+    rollback: Option<Res<Rollback>>,
+    mut q_predicted: Query<(&Position, &Rotation, &mut PredictedHistory), With<Predicted>>,
+) {
+    // During a rollback the TickManager still reports the present, not the tick being re-simulated
+    let tick = rollback
+        .and_then(|rollback| rollback.get_rollback_tick())
+        .unwrap_or_else(|| tick_manager.tick());
+
+    for (position, rotation, mut history) in &mut q_predicted {
+        history.record(tick, *position, *rotation);
+    }
+}
+
+fn compare_confirmed_to_predicted(
+    mut report: ResMut<RollbackReport>,
+    q_confirmed: Query<
+        (&Confirmed, &Position, &Rotation),
+        (With<Ship>, Or<(Changed<Position>, Changed<Rotation>)>),
+    >,
+    q_predicted: Query<&PredictedHistory>,
+) {
+    for (confirmed, confirmed_position, confirmed_rotation) in &q_confirmed {
+        let Some(predicted_entity) = confirmed.predicted else {
+            continue;
+        };
+
+        let Some((_, predicted_position, predicted_rotation)) = q_predicted
+            .get(predicted_entity)
+            .ok()
+            .and_then(|history| history.get(confirmed.tick))
+        else {
+            report.ticks_unchecked += 1;
+            continue;
+        };
+
+        report.ticks_checked += 1;
+
+        // Exact comparisons, since any difference at all triggers a rollback
+        let mut mispredicted = Vec::new();
+        if predicted_position != confirmed_position {
+            mispredicted.push(MispredictedValue::Position {
+                predicted: predicted_position.0,
+                confirmed: confirmed_position.0,
+            });
+        }
+        if predicted_rotation != confirmed_rotation {
+            mispredicted.push(MispredictedValue::Rotation {
+                predicted: predicted_rotation.0,
+                confirmed: confirmed_rotation.0,
+            });
+        }
+
+        for value in mispredicted {
+            debug!(
+                "misprediction at tick {} on {:?}: {:?}, off by {:.5}",
+                confirmed.tick.0,
+                predicted_entity,
+                value,
+                value.delta()
+            );
+
+            report.record(Misprediction {
+                tick: confirmed.tick.0,
+                entity: predicted_entity,
+                value,
+            });
+        }
+    }
+}
+
+/// A matched PreSpawned entity has its PreSpawned component removed before it could ever be
+/// despawned, so anything despawned while still PreSpawned never found its server twin.
+/// Projectiles that hit something before the server's copy arrives land here too.
+fn on_unmatched_prespawn_despawned(
+    trigger: Trigger<OnDespawn, PreSpawned>,
+    q_prespawned: Query<&PreSpawned>,
+    tick_manager: Option<Res<TickManager>>,
+    mut report: ResMut<RollbackReport>,
+) {
+    let Some(tick_manager) = tick_manager else {
+        return;
+    };
+
+    let entity = trigger.target();
+    let hash = q_prespawned.get(entity).ok().and_then(|prespawned| prespawned.hash);
+
+    debug!(
+        "prespawned {:?} (hash {:?}) despawned without matching a server entity at tick {}",
+        entity,
+        hash,
+        tick_manager.tick().0
+    );
+
+    report.record_unmatched_prespawn(UnmatchedPreSpawn {
+        tick: tick_manager.tick().0,
+        entity,
+        hash,
+    });
+}
+
+fn write_report_on_exit(report: Res<RollbackReport>, report_path: Option<Res<RollbackReportPath>>) {
+    info!(
+        "rollback diagnostics: {} ticks checked, {} mispredictions (max position delta {:.5}, max rotation delta {:.5}), {} unmatched prespawns",
+        report.ticks_checked,
+        report.mispredictions.len() as u32 + report.mispredictions_dropped,
+        report.max_position_delta,
+        report.max_rotation_delta,
+        report.unmatched_prespawns.len() as u32 + report.unmatched_prespawns_dropped
+    );
+
+    let Some(report_path) = report_path else {
+        return;
+    };
+
+    #[cfg(not(target_family = "wasm"))]
+    match report.to_ron() {
+        Ok(ron) => {
+            if let Err(e) = std::fs::write(&report_path.0, ron) {
+                error!("unable to write rollback report to {:?}: {}", report_path.0, e);
+            }
+        }
+        Err(e) => error!("unable to serialize rollback report: {}", e),
+    }
+}
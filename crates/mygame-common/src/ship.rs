@@ -19,17 +19,18 @@ use lightyear::{
     prelude::{
         client::{
             is_in_rollback, Confirmed, Interpolated, Predicted, PredictionDespawnCommandsExt, Rollback
-        }, server::{ControlledBy, Lifetime, SyncTarget}, ClientId, DisableReplicateHierarchy, NetworkIdentity, NetworkTarget, PreSpawned, ReplicateOnce, ServerConnectionManager, ServerReplicate, TickManager
+        }, server::{ControlledBy, Lifetime, SyncTarget}, DisableReplicateHierarchy, NetworkIdentity, NetworkRelevanceMode, NetworkTarget, PreSpawned, ReplicateOnce, ServerReplicate, TickManager
     },
 };
-use mygame_assets::{CollisionMask, LevelState, assets::GlobalAssets};
+use mygame_assets::{CollisionMask, Geometry, LevelState, assets::GlobalAssets};
 use mygame_protocol::{
-    component::{Bot, Health, Player, Projectile, Ship},
+    component::{Bot, Player, Projectile, RapidFire, Ship, SpeedBoost},
     input::NetworkedInput,
-    message::{Reliable, ServerShipHit},
+    message::{HitKind, MatchRules},
 };
 
 use crate::{
+    damage::{DamageEvent, DamageSet, DamageSource},
     LEFT_PROJECTILE_ID, REPLICATION_GROUP_PREDICTED, RIGHT_PROJECTILE_ID, Rendered, Simulated,
 };
 
@@ -48,22 +49,37 @@ impl Plugin for ShipPlugin {
 
         app.add_systems(
             FixedPostUpdate,
-            (handle_projectile_collisions, handle_ship_collisions)
-                .after(PhysicsSet::StepSimulation),
+            (
+                handle_projectile_collisions,
+                handle_ship_collisions,
+                damage_out_of_bounds,
+            )
+                .after(PhysicsSet::StepSimulation)
+                .before(DamageSet),
         );
 
         app.add_systems(Last, add_simulated_projectile_components);
     }
 }
 
+/// Ships take damage when they first touch the level or ram each other. Ships are kinematic, so
+/// they don't bounce off anything, and this is all that comes of it.
 fn handle_ship_collisions(
-    mut commands: Commands,
     collisions: Collisions,
-    mut q_ships: Query<(Entity, &Position, &mut Health), With<Ship>>,
+    q_ships: Query<&Position, With<Ship>>,
+    q_geometry: Query<(), With<Geometry>>,
+    mut damage_events: EventWriter<DamageEvent>,
     network_identity: NetworkIdentity,
-    tick_manager: Res<TickManager>,
 ) {
+    if !network_identity.is_server() {
+        return;
+    }
+
     for contact_pair in collisions.iter() {
+        if !contact_pair.collision_started() {
+            continue;
+        }
+
         let (ship_entity, other_entity) = if let Some(entity1) = contact_pair.body1 {
             if q_ships.contains(entity1) {
                 if let Some(entity2) = contact_pair.body2 {
@@ -83,6 +99,86 @@ fn handle_ship_collisions(
         } else {
             continue; // No rigidbody in body1
         };
+
+        let Ok(ship_position) = q_ships.get(ship_entity) else {
+            continue;
+        };
+
+        if let Ok(other_position) = q_ships.get(other_entity) {
+            // Ramming hurts both ships, and each is to blame for the other's damage
+            damage_events.write(DamageEvent {
+                target: ship_entity,
+                amount: RAM_DAMAGE,
+                position: ship_position.0,
+                attacker: Some(other_entity),
+                source: DamageSource::Collision,
+            });
+
+            damage_events.write(DamageEvent {
+                target: other_entity,
+                amount: RAM_DAMAGE,
+                position: other_position.0,
+                attacker: Some(ship_entity),
+                source: DamageSource::Collision,
+            });
+        } else if q_geometry.contains(other_entity) {
+            damage_events.write(DamageEvent {
+                target: ship_entity,
+                amount: ENVIRONMENT_COLLISION_DAMAGE,
+                position: ship_position.0,
+                attacker: None,
+                source: DamageSource::Environment,
+            });
+        }
+    }
+}
+
+/// Server side, on ships outside the arena, counting down to their next bit of damage
+#[derive(Component)]
+struct OutOfBounds(Timer);
+
+/// move_ship keeps ships inside the arena, but anything that gets out anyway (a teleport, a
+/// bad correction) is worn down until it comes back
+fn damage_out_of_bounds(
+    mut commands: Commands,
+    time: Res<Time<Fixed>>,
+    mut q_ships: Query<(Entity, &Position, Option<&mut OutOfBounds>), With<Ship>>,
+    mut damage_events: EventWriter<DamageEvent>,
+    network_identity: NetworkIdentity,
+) {
+    if !network_identity.is_server() {
+        return;
+    }
+
+    for (ship, position, out_of_bounds) in &mut q_ships {
+        let distance_from_origin = Vec2::new(position.x, position.z).length();
+        let outside = distance_from_origin > ARENA_RADIUS + BOUNDARY_TOLERANCE
+            || position.y < MIN_HEIGHT - BOUNDARY_TOLERANCE
+            || position.y > MAX_HEIGHT + BOUNDARY_TOLERANCE;
+
+        match (outside, out_of_bounds) {
+            (true, Some(mut out_of_bounds)) => {
+                if out_of_bounds.0.tick(time.delta()).just_finished() {
+                    damage_events.write(DamageEvent {
+                        target: ship,
+                        amount: BOUNDARY_DAMAGE,
+                        position: position.0,
+                        attacker: None,
+                        source: DamageSource::Boundary,
+                    });
+                }
+            }
+            (true, None) => {
+                commands.entity(ship).insert(OutOfBounds(Timer::new(
+                    BOUNDARY_DAMAGE_INTERVAL,
+                    TimerMode::Repeating,
+                )));
+            }
+            (false, Some(_)) => {
+                commands.entity(ship).remove::<OutOfBounds>();
+            }
+            (false, None) => {}
+        }
     }
 }
 
@@ -91,11 +187,35 @@ pub struct ProjectileHitNonShip {
     pub position: Vec3,
 }
 
+/// Triggered on the server whenever a ship takes damage, alongside the ServerShipHit message
+#[derive(Event)]
+pub struct ShipHit {
+    pub position: Vec3,
+    pub kind: HitKind,
+}
+
+/// How long a player has to wait to respawn after their ship is destroyed. Enforced by the server.
+pub const RESPAWN_DELAY: Duration = Duration::from_secs(5);
+
+/// How long a newly spawned ship is invulnerable for. Enforced by the server.
+pub const SPAWN_PROTECTION_DURATION: Duration = Duration::from_secs(3);
+
+/// Damage to each ship when two ships touch
+const RAM_DAMAGE: u16 = 15;
+/// Damage to a ship when it flies into the level
+const ENVIRONMENT_COLLISION_DAMAGE: u16 = 20;
+
+/// How far past the arena's edges a ship can be before it's out of bounds
+pub const BOUNDARY_TOLERANCE: f32 = 5.0;
+const BOUNDARY_DAMAGE: u16 = 10;
+const BOUNDARY_DAMAGE_INTERVAL: Duration = Duration::from_secs(1);
+
 fn handle_projectile_collisions(
     mut commands: Commands,
     collisions: Collisions,
     q_projectile: Query<(Entity, &Projectile, &Position, &Rotation, &LinearVelocity)>,
-    mut q_ships: Query<(Entity, &Position, &mut Health), With<Ship>>,
+    q_ships: Query<(), With<Ship>>,
+    mut damage_events: EventWriter<DamageEvent>,
     network_identity: NetworkIdentity,
     time: Res<Time<Fixed>>,
 ) {
@@ -173,7 +293,7 @@ fn handle_projectile_collisions(
         }
 
         // is "other_entity" a ship?
-        if let Ok((ship, ship_position, mut ship_health)) = q_ships.get_mut(other_entity) {
+        if q_ships.contains(other_entity) {
             if network_identity.is_client() {
                 commands
                     .entity(projectile_entity)
@@ -181,32 +301,13 @@ fn handle_projectile_collisions(
             } else {
                 commands.entity(projectile_entity).despawn();
 
-                // We want the despawn to happen EXACTLY once, even if two projectiles hit this frame
-                if ship_health.current == 1 {
-                    commands.entity(ship).despawn();
-                } else {
-                    ship_health.current -= 1;
-                }
-
-                let projectile_position = *projectile_position.clone();
-
-                commands.queue(move |world: &mut World| {
-                    let mut server = world.resource_mut::<ServerConnectionManager>();
-                    let mut clients: Vec<ClientId> = vec![];
-
-                    for client in server.connected_clients() {
-                        clients.push(client);
-                    }
-
-                    for client in clients {
-                        let _ = server.send_message::<Reliable, ServerShipHit>(
-                            client,
-                            &ServerShipHit {
-                                position: projectile_position,
-                            },
-                        );
-                    }
-                })
+                damage_events.write(DamageEvent {
+                    target: other_entity,
+                    amount: projectile.damage,
+                    position: projectile_position.0,
+                    attacker: Some(projectile.owner),
+                    source: DamageSource::Projectile,
+                });
             }
         } else {
             if network_identity.is_client() {
@@ -275,7 +376,13 @@ fn add_rendered_ship_components(
                 //Collider::sphere(1.0),
                 CollisionLayers::new(
                     CollisionMask::Ship,
-                    [CollisionMask::Environment, CollisionMask::Projectile],
+                    [
+                        CollisionMask::Ship,
+                        CollisionMask::Environment,
+                        CollisionMask::Projectile,
+                        CollisionMask::Flag,
+                        CollisionMask::Pickup,
+                    ],
                 ),
                 Transform::from_translation(Vec3::Y * 0.25), // better alignment vertically
             ));
@@ -369,6 +476,9 @@ pub fn despawn_after_lifetime(
 }
 
 const PROJECTILE_VELOCITY: f32 = 200.;
+const PROJECTILE_DAMAGE: u16 = 10;
+/// RapidFire divides the weapon's cooldown by this
+const RAPID_FIRE_COOLDOWN_DIVISOR: u16 = 2;
 
 #[derive(Component)]
 pub struct ProjectileVelocity(pub Vec3);
@@ -385,13 +495,19 @@ fn fire(
             &mut ShipWeapon,
             Option<&Player>,
             Option<&Bot>,
+            Has<RapidFire>,
         ),
         (Simulated, With<Ship>),
     >,
     network_identity: NetworkIdentity,
     tick_manager: Res<TickManager>,
     rollback_manager: Option<Res<Rollback>>,
+    match_rules: Res<MatchRules>,
 ) {
+    if !match_rules.has_combat() {
+        return;
+    }
+
     let tick = tick_manager.tick();
     let rollback = if let Some(rollback_manager) = rollback_manager {
         rollback_manager.is_rollback()
@@ -408,6 +524,7 @@ fn fire(
         mut ship_weapon,
         maybe_player,
         maybe_bot,
+        rapid_fire,
     ) in q_ship.iter_mut()
     {
         if let Some(fire) = action_state.button_data_mut(&NetworkedInput::Fire) {
@@ -420,7 +537,13 @@ fn fire(
                 0
             };
 
-            if fire.pressed() && *tick > ship_weapon.last_fired_tick + ship_weapon.cooldown_ticks {
+            let cooldown_ticks = if rapid_fire {
+                ship_weapon.cooldown_ticks / RAPID_FIRE_COOLDOWN_DIVISOR
+            } else {
+                ship_weapon.cooldown_ticks
+            };
+
+            if fire.pressed() && *tick > ship_weapon.last_fired_tick + cooldown_ticks {
                 ship_weapon.last_fired_tick = *tick;
 
                 let offset_distance = 0.5;
@@ -444,7 +567,10 @@ fn fire(
                 let left_projectile_base = (
                     Position(left_offset),
                     ship_rotation.clone(),
-                    Projectile { owner: ship_entity },
+                    Projectile {
+                        owner: ship_entity,
+                        damage: PROJECTILE_DAMAGE,
+                    },
                     LinearVelocity(projectile_velocity),
                     PreSpawned::new(left_hash),
                     DespawnAfter {
@@ -457,7 +583,10 @@ fn fire(
                 let right_projectile_base = (
                     Position(right_offset),
                     ship_rotation.clone(),
-                    Projectile { owner: ship_entity },
+                    Projectile {
+                        owner: ship_entity,
+                        damage: PROJECTILE_DAMAGE,
+                    },
                     LinearVelocity(projectile_velocity),
                     PreSpawned::new(right_hash),
                     DespawnAfter {
@@ -481,6 +610,8 @@ fn fire(
                             prediction: NetworkTarget::All,
                             interpolation: NetworkTarget::None,
                         },
+                        // Only clients near the shot receive it, see the server's InterestPlugin
+                        relevance_mode: NetworkRelevanceMode::InterestManagement,
                         ..default()
                     },
                     DisableReplicateHierarchy,
@@ -513,13 +644,16 @@ fn compute_hash(object_id: u64, client_id: u64, tick_manager: &TickManager) -> u
 }
 
 const SHIP_MOVE_SPEED: f32 = 10.0;
+const BOOST_SPEED_MULTIPLIER: f32 = 1.75;
+/// On top of boosting, for ships with a SpeedBoost
+const SPEED_BOOST_MULTIPLIER: f32 = 1.5;
 const MAX_ROLL_ANGLE: f32 = std::f32::consts::FRAC_PI_2; // 90 degrees
 const MAX_PITCH_ANGLE: f32 = std::f32::consts::FRAC_PI_4; // 45 degrees
-const TURN_RATE: f32 = 1.0;
-const PITCH_RATE: f32 = 1.0;
+pub const TURN_RATE: f32 = 1.0;
+pub const PITCH_RATE: f32 = 1.0;
 const MIN_HEIGHT: f32 = 5.0; // Minimum allowed height
 const MAX_HEIGHT: f32 = 75.0; // Maximum allowed height
-const ARENA_RADIUS: f32 = 100.0; // Maximum distance from origin in the XZ plane
+pub const ARENA_RADIUS: f32 = 100.0; // Maximum distance from origin in the XZ plane
 
 fn move_ship(
     mut q_ship: Query<
@@ -528,12 +662,13 @@ fn move_ship(
             &mut LinearVelocity,
             &mut Rotation,
             &Transform, // Added position to track height
+            Has<SpeedBoost>,
         ),
         (Simulated, With<Ship>),
     >,
     time: Res<Time<Fixed>>,
 ) {
-    for (action_state, mut velocity, mut rotation, transform) in q_ship.iter_mut() {
+    for (action_state, mut velocity, mut rotation, transform, speed_boosted) in q_ship.iter_mut() {
         if let Some(movement) = action_state.dual_axis_data(&NetworkedInput::Aim) {
             // Get current orientation vectors
             let forward = (rotation.0 * -Vec3::Z).normalize();
@@ -603,7 +738,19 @@ fn move_ship(
         // Always move forward in the direction the ship is facing
         let forward = (rotation.0 * -Vec3::Z).normalize();
 
-        let mut adjusted_velocity = forward * SHIP_MOVE_SPEED;
+        let move_speed = if action_state.pressed(&NetworkedInput::Boost) {
+            SHIP_MOVE_SPEED * BOOST_SPEED_MULTIPLIER
+        } else {
+            SHIP_MOVE_SPEED
+        };
+
+        let move_speed = if speed_boosted {
+            move_speed * SPEED_BOOST_MULTIPLIER
+        } else {
+            move_speed
+        };
+
+        let mut adjusted_velocity = forward * move_speed;
 
         // Check height constraints
         let current_height = transform.translation.y;
@@ -633,8 +780,8 @@ fn move_ship(
             // If moving outward at the boundary, remove that component
             if outward_component > 0.0 {
                 // Remove the outward component from velocity
-                adjusted_velocity.x -= dir_from_origin.x * outward_component * SHIP_MOVE_SPEED;
-                adjusted_velocity.z -= dir_from_origin.y * outward_component * SHIP_MOVE_SPEED;
+                adjusted_velocity.x -= dir_from_origin.x * outward_component * move_speed;
+                adjusted_velocity.z -= dir_from_origin.y * outward_component * move_speed;
             }
         }
 
@@ -0,0 +1,360 @@
+//! Runs a headless server and any number of headless clients in one process, connected over
+//! in-memory channels the same way a client-host talks to its own server.
+//!
+//! Nothing runs on its own. Every app advances by exactly one tick per `Harness::step`, so tests
+//! are deterministic about timing and can script inputs tick by tick.
+
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use avian3d::prelude::{LinearVelocity, Position, Rotation};
+use bevy::{asset::AssetPlugin, prelude::*, time::TimeUpdateStrategy};
+use leafwing_input_manager::{plugin::InputManagerSystem, prelude::{ActionState, InputMap}};
+use lightyear::{
+    client::{
+        config::{ClientConfig, NetcodeConfig as ClientNetcodeConfig},
+        plugin::ClientPlugins,
+    },
+    connection::client::NetConfig as ClientNetConfig,
+    prelude::{
+        ClientConnectionManager, ClientReceiveMessage, SharedConfig, TickConfig,
+        client::{
+            Authentication, ClientCommandsExt, ClientConnection, ClientTransport,
+            IoConfig as ClientIoConfig, NetClient, Predicted,
+        },
+        server::{IoConfig as ServerIoConfig, NetConfig as ServerNetConfig, ServerTransport},
+    },
+    server::config::{NetcodeConfig as ServerNetcodeConfig, ServerConfig},
+};
+use mygame_assets::{CurrentLevel, LevelState};
+use mygame_common::{CommonPlugin, rollback_diagnostics::RollbackDiagnosticsPlugin};
+use mygame_protocol::{
+    component::{Health, Player, Shield, Ship, SpawnProtection},
+    input::NetworkedInput,
+    message::{ClientRequestRespawn, ServerWelcome, UnorderedReliable},
+};
+use mygame_server::app::{ServerMode, add_headless_plugins, build_server_app};
+
+pub const ASSET_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../mygame-assets/assets");
+pub const TICK_DURATION: Duration = Duration::from_millis(16);
+
+const PROTOCOL_ID: u64 = 0;
+const PRIVATE_KEY: [u8; 32] = [0; 32];
+
+/// What a scripted client is "pressing" this tick
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq)]
+pub struct ScriptedInput {
+    pub aim: Vec2,
+    pub fire: bool,
+    pub boost: bool,
+}
+
+pub struct HarnessClient {
+    pub client_id: u64,
+    pub app: App,
+}
+
+impl HarnessClient {
+    /// Hold these inputs until told otherwise
+    pub fn set_input(&mut self, input: ScriptedInput) {
+        self.app.insert_resource(input);
+    }
+
+    /// The predicted ship this client controls, once the server has spawned it
+    pub fn local_ship(&mut self) -> Option<Entity> {
+        let client_id = self.client_id;
+        self.app
+            .world_mut()
+            .query_filtered::<(Entity, &Player), (With<Predicted>, With<Ship>)>()
+            .iter(self.app.world())
+            .find(|(_, player)| player.0.to_bits() == client_id)
+            .map(|(entity, _)| entity)
+    }
+
+    /// What the respawn menu sends when its button is clicked
+    pub fn request_respawn(&mut self) {
+        let _ = self
+            .app
+            .world_mut()
+            .resource_mut::<ClientConnectionManager>()
+            .send_message::<UnorderedReliable, ClientRequestRespawn>(&ClientRequestRespawn);
+    }
+}
+
+pub struct Harness {
+    pub server: App,
+    pub clients: Vec<HarnessClient>,
+}
+
+impl Harness {
+    /// A server plus `client_count` clients, with client ids starting at 1.
+    /// Every client connects and asks to spawn as soon as the level is loaded.
+    pub fn new(client_count: usize) -> Self {
+        let shared_config = SharedConfig {
+            server_replication_send_interval: Duration::ZERO,
+            client_replication_send_interval: Duration::ZERO,
+            tick: TickConfig {
+                tick_duration: TICK_DURATION,
+            },
+        };
+
+        let mut server_channels = Vec::new();
+        let mut clients = Vec::new();
+
+        for index in 0..client_count {
+            let client_id = index as u64 + 1;
+
+            let (from_server_send, from_server_recv) = crossbeam_channel::unbounded();
+            let (to_server_send, to_server_recv) = crossbeam_channel::unbounded();
+
+            // Each client needs its own address so the server can tell them apart
+            let client_addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 20000 + index as u16);
+            server_channels.push((client_addr, to_server_recv, from_server_send));
+
+            let client_config = ClientConfig {
+                shared: shared_config,
+                net: ClientNetConfig::Netcode {
+                    auth: Authentication::Manual {
+                        server_addr: SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0),
+                        client_id,
+                        private_key: PRIVATE_KEY,
+                        protocol_id: PROTOCOL_ID,
+                    },
+                    config: ClientNetcodeConfig {
+                        token_expire_secs: -1,
+                        client_timeout_secs: 5,
+                        ..default()
+                    },
+                    io: ClientIoConfig::from_transport(ClientTransport::LocalChannel {
+                        recv: from_server_recv,
+                        send: to_server_send,
+                    }),
+                },
+                ..default()
+            };
+
+            clients.push(HarnessClient {
+                client_id,
+                app: build_headless_client_app(client_config),
+            });
+        }
+
+        let server_config = ServerConfig {
+            shared: shared_config,
+            net: vec![ServerNetConfig::Netcode {
+                config: ServerNetcodeConfig::default()
+                    .with_protocol_id(PROTOCOL_ID)
+                    .with_key(PRIVATE_KEY),
+                io: ServerIoConfig::from_transport(ServerTransport::Channels {
+                    channels: server_channels,
+                }),
+            }],
+            ..default()
+        };
+
+        let mut server = build_server_app(server_config, ASSET_PATH.to_string(), ServerMode::Headless);
+        prepare_for_stepping(&mut server);
+
+        Self { server, clients }
+    }
+
+    /// Advance the server, then every client, by one tick
+    pub fn step(&mut self) {
+        self.server.update();
+        for client in &mut self.clients {
+            client.app.update();
+        }
+    }
+
+    pub fn step_ticks(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.step();
+        }
+    }
+
+    /// Step until `predicate` holds, giving up after `max_ticks`. Returns whether it held.
+    pub fn run_ticks_until(&mut self, max_ticks: u32, mut predicate: impl FnMut(&mut Harness) -> bool) -> bool {
+        for _ in 0..max_ticks {
+            if predicate(self) {
+                return true;
+            }
+            self.step();
+        }
+
+        predicate(self)
+    }
+
+    /// Step until `predicate` holds, giving up after `timeout` of real time.
+    /// For waiting on things that aren't tied to ticks, like assets loading from disk.
+    pub fn run_until(&mut self, timeout: Duration, mut predicate: impl FnMut(&mut Harness) -> bool) -> bool {
+        let started = Instant::now();
+
+        while started.elapsed() < timeout {
+            if predicate(self) {
+                return true;
+            }
+            self.step();
+            // Give the asset and IO task pools a chance to make progress
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        predicate(self)
+    }
+
+    /// Wait for every client to be flying its own ship
+    pub fn wait_for_ships(&mut self, timeout: Duration) -> bool {
+        self.run_until(timeout, |harness| {
+            harness
+                .clients
+                .iter_mut()
+                .all(|client| client.local_ship().is_some())
+        })
+    }
+
+    pub fn client(&mut self, client_id: u64) -> &mut HarnessClient {
+        self.clients
+            .iter_mut()
+            .find(|client| client.client_id == client_id)
+            .expect("No client with that id in the harness")
+    }
+
+    /// The server's authoritative ship for a client
+    pub fn server_ship(&mut self, client_id: u64) -> Option<Entity> {
+        self.server
+            .world_mut()
+            .query_filtered::<(Entity, &Player), With<Ship>>()
+            .iter(self.server.world())
+            .find(|(_, player)| player.0.to_bits() == client_id)
+            .map(|(entity, _)| entity)
+    }
+
+    pub fn server_ship_health(&mut self, client_id: u64) -> Option<Health> {
+        let ship = self.server_ship(client_id)?;
+        self.server.world().get::<Health>(ship).cloned()
+    }
+
+    pub fn server_ship_shield(&mut self, client_id: u64) -> Option<Shield> {
+        let ship = self.server_ship(client_id)?;
+        self.server.world().get::<Shield>(ship).cloned()
+    }
+
+    /// Wait until a client's ship has spawned and lost its SpawnProtection
+    pub fn wait_out_spawn_protection(&mut self, client_id: u64, max_ticks: u32) -> bool {
+        self.run_ticks_until(max_ticks, |harness| {
+            harness
+                .server_ship(client_id)
+                .is_some_and(|ship| !harness.server.world().entity(ship).contains::<SpawnProtection>())
+        })
+    }
+
+    /// Teleport a client's ship on the server, level and facing -Z. Clients catch up through rollback.
+    pub fn place_ship(&mut self, client_id: u64, position: Vec3) {
+        let Some(ship) = self.server_ship(client_id) else {
+            return;
+        };
+
+        self.server.world_mut().entity_mut(ship).insert((
+            Position(position),
+            Rotation::default(),
+            LinearVelocity::ZERO,
+        ));
+    }
+}
+
+/// Drive time by hand, one tick per update, and finish plugin setup that `App::run` would do
+fn prepare_for_stepping(app: &mut App) {
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(TICK_DURATION));
+    app.finish();
+    app.cleanup();
+}
+
+/// A client with no window, rendering, UI or real input. Just enough to connect, load the
+/// level, spawn and fly a ship from a ScriptedInput.
+pub fn build_headless_client_app(client_config: ClientConfig) -> App {
+    let mut app = App::new();
+
+    add_headless_plugins(
+        &mut app,
+        AssetPlugin {
+            file_path: ASSET_PATH.to_string(),
+            ..default()
+        },
+    );
+
+    app.add_plugins((
+        ClientPlugins {
+            config: client_config,
+        },
+        CommonPlugin,
+        RollbackDiagnosticsPlugin,
+    ))
+    .init_resource::<ScriptedInput>()
+    .add_systems(Startup, connect)
+    .add_systems(Update, (on_server_welcome, take_control_of_local_ship))
+    .add_systems(OnEnter(LevelState::Loaded), request_spawn)
+    .add_systems(PreUpdate, apply_scripted_input.in_set(InputManagerSystem::ManualControl));
+
+    prepare_for_stepping(&mut app);
+
+    app
+}
+
+fn connect(mut commands: Commands) {
+    commands.connect_client();
+}
+
+fn on_server_welcome(
+    mut commands: Commands,
+    mut server_welcome_events: ResMut<Events<ClientReceiveMessage<ServerWelcome>>>,
+    mut current_level: ResMut<CurrentLevel>,
+) {
+    for ev in server_welcome_events.drain() {
+        current_level.0 = ev.message.current_level;
+        commands.insert_resource(ev.message.match_rules);
+    }
+}
+
+fn request_spawn(mut client: ResMut<ClientConnectionManager>) {
+    let _ = client.send_message::<UnorderedReliable, ClientRequestRespawn>(&ClientRequestRespawn);
+}
+
+/// An InputMap is what tells lightyear to send this entity's inputs to the server.
+/// It's left empty so only ScriptedInput drives the ActionState.
+fn take_control_of_local_ship(
+    mut commands: Commands,
+    q_predicted_ships: Query<Entity, (With<Predicted>, With<Ship>, Without<InputMap<NetworkedInput>>)>,
+    q_players: Query<&Player>,
+    client: Res<ClientConnection>,
+) {
+    for ship in &q_predicted_ships {
+        let Ok(player) = q_players.get(ship) else {
+            continue;
+        };
+
+        if player.0 == client.id() {
+            commands.entity(ship).insert(InputMap::<NetworkedInput>::default());
+        }
+    }
+}
+
+fn apply_scripted_input(
+    scripted_input: Res<ScriptedInput>,
+    mut q_action_states: Query<&mut ActionState<NetworkedInput>, (With<Predicted>, With<InputMap<NetworkedInput>>)>,
+) {
+    for mut action_state in &mut q_action_states {
+        action_state.set_axis_pair(&NetworkedInput::Aim, scripted_input.aim);
+
+        for (action, pressed) in [
+            (NetworkedInput::Fire, scripted_input.fire),
+            (NetworkedInput::Boost, scripted_input.boost),
+        ] {
+            if pressed {
+                action_state.press(&action);
+            } else {
+                action_state.release(&action);
+            }
+        }
+    }
+}
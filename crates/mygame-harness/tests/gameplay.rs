@@ -0,0 +1,604 @@
+use std::time::Duration;
+
+use avian3d::prelude::Position;
+use bevy::prelude::*;
+use lightyear::prelude::{ClientReceiveMessage, PreSpawned, client::Predicted};
+use mygame_assets::Checkpoint;
+use mygame_common::{
+    rollback_diagnostics::RollbackReport,
+    ship::{RESPAWN_DELAY, SPAWN_PROTECTION_DURATION},
+};
+use mygame_harness::{Harness, ScriptedInput, TICK_DURATION};
+use mygame_protocol::{
+    component::{
+        Flag, FlagState, Health, Pickup, PickupAvailable, PickupKind, Player, Projectile,
+        RapidFire, Shield, Ship, SpawnProtection, Team,
+    },
+    message::{GameMode, MatchRules, ServerCheckpointPassed, ServerRaceLeaderboard},
+};
+
+/// Generous, since the level is loaded from disk by every app
+const SPAWN_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// ~2 seconds of play, plenty for a shot to cross 30 units and replicate back
+const SHOT_TICKS: u32 = 120;
+
+/// Enough for freshly spawned ships to lose their spawn protection
+fn spawn_protection_ticks() -> u32 {
+    (SPAWN_PROTECTION_DURATION.as_secs_f32() / TICK_DURATION.as_secs_f32()).ceil() as u32 + 1
+}
+
+const SHOOTER: u64 = 1;
+const TARGET: u64 = 2;
+
+/// Park the target straight ahead of the shooter. Both face -Z and fly at the same speed,
+/// so the gap between them holds while the shooter fires.
+fn line_up_shot(harness: &mut Harness) {
+    line_up_shot_between(harness, SHOOTER, TARGET);
+}
+
+fn line_up_shot_between(harness: &mut Harness, shooter: u64, target: u64) {
+    harness.place_ship(shooter, Vec3::new(0.0, 10.0, 0.0));
+    harness.place_ship(target, Vec3::new(0.0, 10.0, -30.0));
+}
+
+/// Everything about a ship that damage can change
+fn ship_damage_state(harness: &mut Harness, client_id: u64) -> Option<(Health, Shield)> {
+    Some((
+        harness.server_ship_health(client_id)?,
+        harness.server_ship_shield(client_id)?,
+    ))
+}
+
+#[test]
+fn ship_takes_damage_when_shot() {
+    let mut harness = Harness::new(2);
+    assert!(harness.wait_for_ships(SPAWN_TIMEOUT), "clients never got their ships");
+    assert!(
+        harness.wait_out_spawn_protection(TARGET, spawn_protection_ticks()),
+        "target's spawn protection never ran out"
+    );
+
+    let undamaged = ship_damage_state(&mut harness, TARGET).unwrap();
+
+    line_up_shot(&mut harness);
+    harness.client(SHOOTER).set_input(ScriptedInput {
+        fire: true,
+        ..default()
+    });
+
+    let damaged = harness.run_ticks_until(SHOT_TICKS, |harness| {
+        ship_damage_state(harness, TARGET).is_some_and(|state| state != undamaged)
+    });
+
+    assert!(damaged, "target was never damaged");
+}
+
+#[test]
+fn shield_absorbs_damage_before_hull() {
+    let mut harness = Harness::new(2);
+    assert!(harness.wait_for_ships(SPAWN_TIMEOUT), "clients never got their ships");
+    assert!(
+        harness.wait_out_spawn_protection(TARGET, spawn_protection_ticks()),
+        "target's spawn protection never ran out"
+    );
+
+    let Shield { max, .. } = harness.server_ship_shield(TARGET).unwrap();
+
+    line_up_shot(&mut harness);
+    harness.client(SHOOTER).set_input(ScriptedInput {
+        fire: true,
+        ..default()
+    });
+
+    let shield_hit = harness.run_ticks_until(SHOT_TICKS, |harness| {
+        harness
+            .server_ship_shield(TARGET)
+            .is_some_and(|shield| shield.current < max)
+    });
+    assert!(shield_hit, "target's shield was never hit");
+
+    let health = harness.server_ship_health(TARGET).unwrap();
+    assert_eq!(health.current, health.max, "hull took damage while the shield was up");
+}
+
+#[test]
+fn spawn_protection_blocks_damage() {
+    let mut harness = Harness::new(2);
+    assert!(harness.wait_for_ships(SPAWN_TIMEOUT), "clients never got their ships");
+
+    // It may have already run out while the level loaded, so put it back
+    let target_ship = harness.server_ship(TARGET).unwrap();
+    harness
+        .server
+        .world_mut()
+        .entity_mut(target_ship)
+        .insert(SpawnProtection);
+
+    let undamaged = ship_damage_state(&mut harness, TARGET);
+
+    line_up_shot(&mut harness);
+    harness.client(SHOOTER).set_input(ScriptedInput {
+        fire: true,
+        ..default()
+    });
+
+    let damaged = harness.run_ticks_until(SHOT_TICKS, |harness| {
+        ship_damage_state(harness, TARGET) != undamaged
+    });
+
+    assert!(!damaged, "protected target took damage");
+}
+
+#[test]
+fn respawn_after_death() {
+    let mut harness = Harness::new(2);
+    assert!(harness.wait_for_ships(SPAWN_TIMEOUT), "clients never got their ships");
+    assert!(
+        harness.wait_out_spawn_protection(TARGET, spawn_protection_ticks()),
+        "target's spawn protection never ran out"
+    );
+
+    // One hit from dead
+    let target_ship = harness.server_ship(TARGET).unwrap();
+    harness
+        .server
+        .world_mut()
+        .get_mut::<Health>(target_ship)
+        .unwrap()
+        .current = 1;
+    harness
+        .server
+        .world_mut()
+        .get_mut::<Shield>(target_ship)
+        .unwrap()
+        .current = 0;
+
+    line_up_shot(&mut harness);
+    harness.client(SHOOTER).set_input(ScriptedInput {
+        fire: true,
+        ..default()
+    });
+
+    let died = harness.run_ticks_until(SHOT_TICKS, |harness| harness.server_ship(TARGET).is_none());
+    assert!(died, "target never died");
+
+    harness.client(SHOOTER).set_input(ScriptedInput::default());
+
+    // Too early, the server should ignore it
+    harness.client(TARGET).request_respawn();
+    harness.step_ticks(SHOT_TICKS);
+    assert!(
+        harness.server_ship(TARGET).is_none(),
+        "target respawned before the respawn delay was up"
+    );
+
+    let delay_ticks = (RESPAWN_DELAY.as_secs_f32() / TICK_DURATION.as_secs_f32()).ceil() as u32;
+    harness.step_ticks(delay_ticks);
+    harness.client(TARGET).request_respawn();
+
+    let respawned = harness.run_ticks_until(SHOT_TICKS, |harness| {
+        harness
+            .server_ship_health(TARGET)
+            .is_some_and(|health| health.current == health.max)
+            && harness.client(TARGET).local_ship().is_some()
+    });
+
+    assert!(respawned, "target never respawned");
+}
+
+#[test]
+fn no_friendly_fire_between_teammates() {
+    let mut harness = Harness::new(3);
+    harness.server.insert_resource(MatchRules {
+        mode: GameMode::TeamDeathmatch,
+        friendly_fire: false,
+    });
+    assert!(harness.wait_for_ships(SPAWN_TIMEOUT), "clients never got their ships");
+
+    // Three players split two to one, whichever order they connected in
+    let teams: Vec<(u64, Team)> = (1..=3)
+        .map(|client_id| {
+            let ship = harness.server_ship(client_id).unwrap();
+            (client_id, *harness.server.world().get::<Team>(ship).unwrap())
+        })
+        .collect();
+
+    let (shooter, target) = teams
+        .iter()
+        .enumerate()
+        .find_map(|(index, (shooter, team))| {
+            teams[index + 1..]
+                .iter()
+                .find(|(_, other_team)| other_team == team)
+                .map(|(target, _)| (*shooter, *target))
+        })
+        .expect("teams weren't balanced two to one");
+
+    assert!(
+        harness.wait_out_spawn_protection(target, spawn_protection_ticks()),
+        "target's spawn protection never ran out"
+    );
+
+    let undamaged = ship_damage_state(&mut harness, target);
+
+    line_up_shot_between(&mut harness, shooter, target);
+    harness.client(shooter).set_input(ScriptedInput {
+        fire: true,
+        ..default()
+    });
+
+    let damaged = harness.run_ticks_until(SHOT_TICKS, |harness| {
+        ship_damage_state(harness, target) != undamaged
+    });
+
+    assert!(!damaged, "teammate took damage with friendly fire off");
+}
+
+/// A team's flag on the server, and where it is
+fn server_flag(harness: &mut Harness, team: Team) -> Option<(Entity, Flag, FlagState)> {
+    harness
+        .server
+        .world_mut()
+        .query::<(Entity, &Flag, &FlagState, &Team)>()
+        .iter(harness.server.world())
+        .find(|(_, _, _, flag_team)| **flag_team == team)
+        .map(|(entity, flag, flag_state, _)| (entity, flag.clone(), *flag_state))
+}
+
+#[test]
+fn capture_the_flag() {
+    let mut harness = Harness::new(2);
+    harness.server.insert_resource(MatchRules {
+        mode: GameMode::CaptureTheFlag,
+        friendly_fire: false,
+    });
+    assert!(harness.wait_for_ships(SPAWN_TIMEOUT), "clients never got their ships");
+
+    let runner_ship = harness.server_ship(SHOOTER).unwrap();
+    let runner_team = *harness.server.world().get::<Team>(runner_ship).unwrap();
+    let enemy_team = Team::ALL.into_iter().find(|team| *team != runner_team).unwrap();
+
+    let (enemy_flag, enemy_flag_info, _) =
+        server_flag(&mut harness, enemy_team).expect("no enemy flag in the level");
+    let (_, own_flag_info, _) = server_flag(&mut harness, runner_team).expect("no own flag in the level");
+
+    harness.place_ship(SHOOTER, enemy_flag_info.base);
+    let picked_up = harness.run_ticks_until(SHOT_TICKS, |harness| {
+        server_flag(harness, enemy_team)
+            .is_some_and(|(_, _, flag_state)| flag_state == FlagState::Carried(runner_ship))
+    });
+    assert!(picked_up, "runner never picked up the enemy flag");
+
+    harness.place_ship(SHOOTER, own_flag_info.base);
+    let captured = harness.run_ticks_until(SHOT_TICKS, |harness| {
+        server_flag(harness, enemy_team)
+            .is_some_and(|(_, _, flag_state)| flag_state == FlagState::AtBase)
+    });
+    assert!(captured, "runner never captured the enemy flag");
+
+    let position = harness.server.world().get::<Position>(enemy_flag).unwrap();
+    assert_eq!(position.0, enemy_flag_info.base, "captured flag didn't go home");
+}
+
+#[test]
+fn no_combat_in_race_mode() {
+    let mut harness = Harness::new(2);
+    harness.server.insert_resource(MatchRules {
+        mode: GameMode::Race,
+        friendly_fire: false,
+    });
+    assert!(harness.wait_for_ships(SPAWN_TIMEOUT), "clients never got their ships");
+    assert!(
+        harness.wait_out_spawn_protection(TARGET, spawn_protection_ticks()),
+        "target's spawn protection never ran out"
+    );
+
+    let undamaged = ship_damage_state(&mut harness, TARGET);
+
+    line_up_shot(&mut harness);
+    harness.client(SHOOTER).set_input(ScriptedInput {
+        fire: true,
+        ..default()
+    });
+
+    let damaged = harness.run_ticks_until(SHOT_TICKS, |harness| {
+        ship_damage_state(harness, TARGET) != undamaged
+    });
+
+    assert!(!damaged, "target took damage in race mode");
+}
+
+/// The level's checkpoints on the server, in the order they're raced through
+fn server_checkpoints(harness: &mut Harness) -> Vec<GlobalTransform> {
+    let mut checkpoints: Vec<(u32, GlobalTransform)> = harness
+        .server
+        .world_mut()
+        .query::<(&Checkpoint, &GlobalTransform)>()
+        .iter(harness.server.world())
+        .map(|(checkpoint, transform)| (checkpoint.0, *transform))
+        .collect();
+    checkpoints.sort_by_key(|(index, _)| *index);
+
+    checkpoints.into_iter().map(|(_, transform)| transform).collect()
+}
+
+/// Everything the race has told a client, gathered tick by tick since messages only last a
+/// couple of updates
+#[derive(Default)]
+struct RaceMessages {
+    checkpoints_passed: Vec<ServerCheckpointPassed>,
+    leaderboard: Option<ServerRaceLeaderboard>,
+}
+
+impl RaceMessages {
+    fn step(&mut self, harness: &mut Harness, client_id: u64, ticks: u32) {
+        for _ in 0..ticks {
+            harness.step();
+
+            let world = harness.client(client_id).app.world_mut();
+
+            self.checkpoints_passed.extend(
+                world
+                    .resource_mut::<Events<ClientReceiveMessage<ServerCheckpointPassed>>>()
+                    .drain()
+                    .map(|ev| ev.message),
+            );
+
+            if let Some(ev) = world
+                .resource_mut::<Events<ClientReceiveMessage<ServerRaceLeaderboard>>>()
+                .drain()
+                .last()
+            {
+                self.leaderboard = Some(ev.message);
+            }
+        }
+    }
+}
+
+/// Teleport a ship from just in front of a checkpoint to just behind it, the way it's meant to
+/// be flown through
+fn fly_through(
+    harness: &mut Harness,
+    messages: &mut RaceMessages,
+    client_id: u64,
+    checkpoint: &GlobalTransform,
+) {
+    let forward = *checkpoint.forward();
+
+    // One tick apart, so the ship hardly drifts from where it's put
+    harness.place_ship(client_id, checkpoint.translation() - forward * 2.0);
+    messages.step(harness, client_id, 1);
+    harness.place_ship(client_id, checkpoint.translation() + forward * 2.0);
+    messages.step(harness, client_id, 1);
+}
+
+#[test]
+fn racing_a_lap() {
+    let mut harness = Harness::new(1);
+    harness.server.insert_resource(MatchRules {
+        mode: GameMode::Race,
+        friendly_fire: false,
+    });
+    assert!(harness.wait_for_ships(SPAWN_TIMEOUT), "client never got its ship");
+
+    let checkpoints = server_checkpoints(&mut harness);
+    assert!(!checkpoints.is_empty(), "no checkpoints in the level");
+
+    let mut messages = RaceMessages::default();
+
+    // Crossing the start line starts the lap, crossing it again after every other checkpoint
+    // finishes it
+    for checkpoint in checkpoints.iter().chain(checkpoints.first()) {
+        fly_through(&mut harness, &mut messages, SHOOTER, checkpoint);
+    }
+    // Long enough for the messages to arrive, not for the ship to fly anywhere
+    messages.step(&mut harness, SHOOTER, 10);
+
+    let passed: Vec<u32> = messages
+        .checkpoints_passed
+        .iter()
+        .map(|passed| passed.checkpoint)
+        .collect();
+    let expected: Vec<u32> = (0..checkpoints.len() as u32).chain([0]).collect();
+    assert!(
+        passed.starts_with(&expected),
+        "passed checkpoints {:?}, expected {:?}",
+        passed,
+        expected
+    );
+
+    let lap_time = messages
+        .checkpoints_passed
+        .iter()
+        .find_map(|passed| passed.lap_time)
+        .expect("crossing the start line again didn't finish the lap");
+    assert!(lap_time > Duration::ZERO, "lap took no time");
+
+    let leaderboard = messages.leaderboard.expect("never received the leaderboard");
+    let entry = leaderboard
+        .entries
+        .iter()
+        .find(|entry| entry.player.to_bits() == SHOOTER)
+        .expect("racer isn't on the leaderboard");
+    assert_eq!(entry.laps, 1);
+    assert_eq!(entry.best_lap, Some(lap_time));
+}
+
+#[test]
+fn collecting_a_pickup() {
+    let mut harness = Harness::new(1);
+    assert!(harness.wait_for_ships(SPAWN_TIMEOUT), "client never got its ship");
+
+    let (pickup, position) = harness
+        .server
+        .world_mut()
+        .query::<(Entity, &Pickup, &Position)>()
+        .iter(harness.server.world())
+        .find(|(_, kind, _)| kind.0 == PickupKind::RapidFire)
+        .map(|(entity, _, position)| (entity, position.0))
+        .expect("no rapid fire pickup in the level");
+
+    harness.place_ship(SHOOTER, position);
+    let ship = harness.server_ship(SHOOTER).unwrap();
+
+    let collected = harness.run_ticks_until(SHOT_TICKS, |harness| {
+        harness.server.world().get::<RapidFire>(ship).is_some()
+    });
+    assert!(collected, "ship never picked up rapid fire");
+
+    let available = harness.server.world().get::<PickupAvailable>(pickup).unwrap();
+    assert!(!available.0, "pickup was still available after being collected");
+}
+
+#[test]
+fn leaving_the_arena_causes_damage() {
+    let mut harness = Harness::new(1);
+    assert!(harness.wait_for_ships(SPAWN_TIMEOUT), "client never got its ship");
+    assert!(
+        harness.wait_out_spawn_protection(SHOOTER, spawn_protection_ticks()),
+        "ship's spawn protection never ran out"
+    );
+
+    let undamaged = ship_damage_state(&mut harness, SHOOTER);
+
+    // Facing -Z, so it can't fly any further out, but it can't get back in on its own either
+    harness.place_ship(SHOOTER, Vec3::new(0.0, 10.0, -120.0));
+
+    let damaged = harness.run_ticks_until(SHOT_TICKS, |harness| {
+        ship_damage_state(harness, SHOOTER) != undamaged
+    });
+
+    assert!(damaged, "ship outside the arena was never damaged");
+}
+
+#[test]
+fn straight_flight_is_predicted_exactly() {
+    let mut harness = Harness::new(1);
+    assert!(harness.wait_for_ships(SPAWN_TIMEOUT), "client never got its ship");
+
+    // Facing -Z, towards the middle of the arena, with nothing to steer
+    harness.place_ship(SHOOTER, Vec3::new(0.0, 10.0, 40.0));
+
+    // Teleporting is a misprediction in itself, so only check once the client has caught up
+    harness.step_ticks(SHOT_TICKS);
+    harness
+        .client(SHOOTER)
+        .app
+        .insert_resource(RollbackReport::default());
+
+    harness.step_ticks(SHOT_TICKS);
+
+    let report = harness.client(SHOOTER).app.world().resource::<RollbackReport>();
+    assert!(report.ticks_checked > 0, "no confirmed updates were checked");
+    assert!(
+        report.mispredictions.is_empty(),
+        "straight flight was mispredicted: {:?}",
+        report.mispredictions
+    );
+}
+
+/// Further apart than either player's relevance radius. Both face -Z and fly at the same
+/// speed, so the gap holds, and neither leaves the arena for a few seconds.
+fn place_ships_apart(harness: &mut Harness) {
+    harness.place_ship(SHOOTER, Vec3::new(0.0, 10.0, 90.0));
+    harness.place_ship(TARGET, Vec3::new(0.0, 10.0, -60.0));
+}
+
+/// Whether the viewer's client has any copy of the owner's ship
+fn client_has_ship(harness: &mut Harness, viewer: u64, owner: u64) -> bool {
+    let client = &mut harness.client(viewer).app;
+
+    client
+        .world_mut()
+        .query_filtered::<&Player, With<Ship>>()
+        .iter(client.world())
+        .any(|player| player.0.to_bits() == owner)
+}
+
+#[test]
+fn only_nearby_ships_are_replicated() {
+    let mut harness = Harness::new(2);
+    assert!(harness.wait_for_ships(SPAWN_TIMEOUT), "clients never got their ships");
+
+    place_ships_apart(&mut harness);
+    let forgotten = harness.run_ticks_until(SHOT_TICKS, |harness| {
+        !client_has_ship(harness, SHOOTER, TARGET)
+    });
+    assert!(forgotten, "a far away ship was still replicated");
+
+    line_up_shot(&mut harness);
+    let replicated = harness.run_ticks_until(SHOT_TICKS, |harness| {
+        client_has_ship(harness, SHOOTER, TARGET)
+    });
+    assert!(replicated, "a nearby ship was never replicated");
+}
+
+#[test]
+fn distant_ships_are_still_corrected() {
+    let mut harness = Harness::new(2);
+    assert!(harness.wait_for_ships(SPAWN_TIMEOUT), "clients never got their ships");
+
+    // With no other ship near, the shooter's ship is only sent now and then
+    place_ships_apart(&mut harness);
+    harness.step_ticks(SHOT_TICKS);
+
+    // Only the server moves it sideways, so the pilot can only get there by being corrected
+    let moved_to = Vec3::new(30.0, 10.0, 60.0);
+    harness.place_ship(SHOOTER, moved_to);
+
+    let corrected = harness.run_ticks_until(SHOT_TICKS, |harness| {
+        let client = harness.client(SHOOTER);
+        let Some(ship) = client.local_ship() else {
+            return false;
+        };
+
+        client
+            .app
+            .world()
+            .get::<Position>(ship)
+            .is_some_and(|position| (position.x - moved_to.x).abs() < 0.5)
+    });
+    assert!(corrected, "the distant ship's pilot was never corrected");
+}
+
+#[test]
+fn distant_ships_shots_match_their_prespawned_copies() {
+    let mut harness = Harness::new(2);
+    assert!(harness.wait_for_ships(SPAWN_TIMEOUT), "clients never got their ships");
+
+    place_ships_apart(&mut harness);
+    harness.step_ticks(SHOT_TICKS);
+    harness
+        .client(SHOOTER)
+        .app
+        .insert_resource(RollbackReport::default());
+
+    harness.client(SHOOTER).set_input(ScriptedInput {
+        fire: true,
+        ..default()
+    });
+
+    let matched = harness.run_ticks_until(SHOT_TICKS, |harness| {
+        let client = &mut harness.client(SHOOTER).app;
+
+        client
+            .world_mut()
+            .query_filtered::<(), (With<Projectile>, With<Predicted>, Without<PreSpawned>)>()
+            .iter(client.world())
+            .next()
+            .is_some()
+    });
+    assert!(matched, "no shot was ever matched to the server's");
+
+    // Long enough for every shot fired to have despawned, matched or not
+    harness.client(SHOOTER).set_input(ScriptedInput::default());
+    harness.step_ticks(SHOT_TICKS);
+
+    let report = harness.client(SHOOTER).app.world().resource::<RollbackReport>();
+    assert!(
+        report.unmatched_prespawns.is_empty() && report.unmatched_prespawns_dropped == 0,
+        "shots were never matched: {:?}",
+        report.unmatched_prespawns
+    );
+}
@@ -1,4 +1,5 @@
 use lightyear::prelude::{LinkConditionerConfig, TickConfig, server::ServerTransport};
+use mygame_protocol::message::{GameMode, MatchRules};
 use serde::{Deserialize, Serialize};
 use std::net::Ipv4Addr;
 use std::time::Duration;
@@ -102,6 +103,8 @@ pub struct ClientLaunchOptions {
     pub correction_ticks_factor: f32,
     pub min_delay: Duration,
     pub certificate_digest: Option<String>,
+    /// Where browsers connect over WebSocket when WebTransport isn't available or doesn't work
+    pub websocket_server_port: Option<u16>,
     pub asset_path: String,
 }
 
@@ -120,6 +123,7 @@ impl Default for ClientLaunchOptions {
             correction_ticks_factor: 2.0,
             min_delay: Duration::from_millis(25),
             certificate_digest: None,
+            websocket_server_port: None,
             asset_path: String::from("../mygame-assets/assets"),
         }
     }
@@ -135,6 +139,8 @@ pub struct SerializableClientLaunchOptions {
     pub correction_ticks_factor: f32,
     pub min_delay_ms: u64,
     pub certificate_digest: Option<String>,
+    #[serde(default)]
+    pub websocket_server_port: Option<u16>,
     pub asset_path: String,
 }
 
@@ -149,6 +155,7 @@ impl From<ClientLaunchOptions> for SerializableClientLaunchOptions {
             correction_ticks_factor: options.correction_ticks_factor,
             min_delay_ms: options.min_delay.as_millis() as u64,
             certificate_digest: options.certificate_digest,
+            websocket_server_port: options.websocket_server_port,
             asset_path: options.asset_path,
         }
     }
@@ -171,6 +178,7 @@ impl From<SerializableClientLaunchOptions> for ClientLaunchOptions {
             correction_ticks_factor: serializable.correction_ticks_factor,
             min_delay: Duration::from_millis(serializable.min_delay_ms),
             certificate_digest: serializable.certificate_digest,
+            websocket_server_port: serializable.websocket_server_port,
             asset_path: serializable.asset_path,
         }
     }
@@ -181,10 +189,27 @@ pub struct ServerLaunchOptions {
     pub listen_addr: Ipv4Addr,
     pub udp_listen_port: u16,
     pub webtransport_listen_port: u16,
+    /// For browsers without WebTransport
+    pub websocket_listen_port: u16,
     pub conditioner: LinkConditionerConfig,
     pub webtransport_cert_path: String,
     pub webtransport_key_path: String,
     pub asset_path: String,
+    pub match_rules: MatchRules,
+    pub race_records_path: String,
+    /// What the match on the ports above is called in the match list
+    pub match_name: String,
+    /// More matches to run alongside it in the same process, each on its own ports
+    pub instances: Vec<MatchInstanceOptions>,
+}
+
+pub struct MatchInstanceOptions {
+    pub name: String,
+    pub udp_listen_port: u16,
+    pub webtransport_listen_port: u16,
+    pub websocket_listen_port: u16,
+    pub match_rules: MatchRules,
+    pub race_records_path: String,
 }
 
 impl Default for ServerLaunchOptions {
@@ -194,6 +219,7 @@ impl Default for ServerLaunchOptions {
             listen_addr: Ipv4Addr::LOCALHOST,
             udp_listen_port: 12025,
             webtransport_listen_port: 12026,
+            websocket_listen_port: default_websocket_listen_port(),
             conditioner: LinkConditionerConfig {
                 incoming_latency: Duration::from_millis(50),
                 incoming_jitter: Duration::ZERO,
@@ -202,6 +228,10 @@ impl Default for ServerLaunchOptions {
             webtransport_cert_path: String::from("./crates/mygame-launcher/web/certs/cert.pem"),
             webtransport_key_path: String::from("./crates/mygame-launcher/web/certs/key.pem"),
             asset_path: String::from("../mygame-assets/assets"),
+            match_rules: MatchRules::default(),
+            race_records_path: default_race_records_path(),
+            match_name: default_match_name(),
+            instances: Vec::new(),
         }
     }
 }
@@ -212,10 +242,100 @@ pub struct SerializableServerLaunchOptions {
     pub listen_addr: String,
     pub udp_listen_port: u16,
     pub webtransport_listen_port: u16,
+    #[serde(default = "default_websocket_listen_port")]
+    pub websocket_listen_port: u16,
     pub conditioner: SerializableLinkConditionerConfig,
     pub webtransport_cert_path: String,
     pub webtransport_key_path: String,
     pub asset_path: String,
+    #[serde(default)]
+    pub game_mode: GameMode,
+    #[serde(default)]
+    pub friendly_fire: bool,
+    #[serde(default = "default_race_records_path")]
+    pub race_records_path: String,
+    #[serde(default = "default_match_name")]
+    pub match_name: String,
+    #[serde(default)]
+    pub instances: Vec<SerializableMatchInstanceOptions>,
+}
+
+fn default_race_records_path() -> String {
+    String::from("./server_data/race_records.ron")
+}
+
+fn default_match_name() -> String {
+    String::from("Main")
+}
+
+fn default_websocket_listen_port() -> u16 {
+    12028
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SerializableMatchInstanceOptions {
+    pub name: String,
+    pub udp_listen_port: u16,
+    pub webtransport_listen_port: u16,
+    pub websocket_listen_port: u16,
+    #[serde(default)]
+    pub game_mode: GameMode,
+    #[serde(default)]
+    pub friendly_fire: bool,
+    /// Defaults to a file named after the match, so matches don't share records
+    #[serde(default)]
+    pub race_records_path: Option<String>,
+}
+
+/// Where a match keeps its race records unless told otherwise, e.g.
+/// "./server_data/race_records_night_race.ron" for "Night Race"
+fn instance_race_records_path(name: &str) -> String {
+    let file_name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    format!("./server_data/race_records_{}.ron", file_name)
+}
+
+impl From<MatchInstanceOptions> for SerializableMatchInstanceOptions {
+    fn from(options: MatchInstanceOptions) -> Self {
+        Self {
+            name: options.name,
+            udp_listen_port: options.udp_listen_port,
+            webtransport_listen_port: options.webtransport_listen_port,
+            websocket_listen_port: options.websocket_listen_port,
+            game_mode: options.match_rules.mode,
+            friendly_fire: options.match_rules.friendly_fire,
+            race_records_path: Some(options.race_records_path),
+        }
+    }
+}
+
+impl From<SerializableMatchInstanceOptions> for MatchInstanceOptions {
+    fn from(serializable: SerializableMatchInstanceOptions) -> Self {
+        let race_records_path = serializable
+            .race_records_path
+            .unwrap_or_else(|| instance_race_records_path(&serializable.name));
+
+        Self {
+            name: serializable.name,
+            udp_listen_port: serializable.udp_listen_port,
+            webtransport_listen_port: serializable.webtransport_listen_port,
+            websocket_listen_port: serializable.websocket_listen_port,
+            match_rules: MatchRules {
+                mode: serializable.game_mode,
+                friendly_fire: serializable.friendly_fire,
+            },
+            race_records_path,
+        }
+    }
 }
 
 impl From<ServerLaunchOptions> for SerializableServerLaunchOptions {
@@ -225,10 +345,20 @@ impl From<ServerLaunchOptions> for SerializableServerLaunchOptions {
             listen_addr: options.listen_addr.to_string(),
             udp_listen_port: options.udp_listen_port,
             webtransport_listen_port: options.webtransport_listen_port,
+            websocket_listen_port: options.websocket_listen_port,
             conditioner: SerializableLinkConditionerConfig::from(options.conditioner),
             webtransport_cert_path: options.webtransport_cert_path,
             webtransport_key_path: options.webtransport_key_path,
             asset_path: options.asset_path,
+            game_mode: options.match_rules.mode,
+            friendly_fire: options.match_rules.friendly_fire,
+            race_records_path: options.race_records_path,
+            match_name: options.match_name,
+            instances: options
+                .instances
+                .into_iter()
+                .map(SerializableMatchInstanceOptions::from)
+                .collect(),
         }
     }
 }
@@ -243,10 +373,22 @@ impl From<SerializableServerLaunchOptions> for ServerLaunchOptions {
                 .unwrap_or(Ipv4Addr::LOCALHOST),
             udp_listen_port: serializable.udp_listen_port,
             webtransport_listen_port: serializable.webtransport_listen_port,
+            websocket_listen_port: serializable.websocket_listen_port,
             conditioner: LinkConditionerConfig::from(serializable.conditioner),
             webtransport_cert_path: serializable.webtransport_cert_path,
             webtransport_key_path: serializable.webtransport_key_path,
             asset_path: serializable.asset_path,
+            match_rules: MatchRules {
+                mode: serializable.game_mode,
+                friendly_fire: serializable.friendly_fire,
+            },
+            race_records_path: serializable.race_records_path,
+            match_name: serializable.match_name,
+            instances: serializable
+                .instances
+                .into_iter()
+                .map(MatchInstanceOptions::from)
+                .collect(),
         }
     }
 }
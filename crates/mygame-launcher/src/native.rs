@@ -1,6 +1,8 @@
 #![cfg(not(target_family = "wasm"))]
 use crate::{
-    launch_options::{ClientLaunchOptions, ServerLaunchOptions, SharedLaunchOptions},
+    launch_options::{
+        ClientLaunchOptions, MatchInstanceOptions, ServerLaunchOptions, SharedLaunchOptions,
+    },
     launch_options::{
         SerializableClientLaunchOptions, SerializableServerLaunchOptions,
         SerializableSharedLaunchOptions,
@@ -23,14 +25,25 @@ use lightyear::{
     },
     server::config::{NetcodeConfig as ServerNetcodeConfig, ServerConfig},
 };
-use mygame_client::app::build_client_app;
-use mygame_server::app::{ServerMode, build_server_app};
+use mygame_client::{app::build_client_app, replay::build_replay_app};
+use mygame_common::{
+    match_recording::RecordMatch,
+    rollback_diagnostics::{RollbackDiagnosticsPlugin, RollbackReportPath},
+};
+use mygame_server::{
+    app::{ServerMode, build_server_app},
+    discovery::MatchName,
+    instances::{MatchDirectory, MatchDirectoryEntry},
+    race::RaceRecordsPath,
+    shutdown::ShutdownSignal,
+};
 use ron::de::from_str;
 use std::{
     error::Error,
     fs,
     net::{IpAddr, Ipv4Addr, SocketAddr},
     path::{Path, PathBuf},
+    sync::atomic::Ordering,
     time::Duration,
 };
 
@@ -46,6 +59,10 @@ struct Cli {
     #[arg(value_enum)]
     mode: Mode,
 
+    /// The recording to play back in replay mode
+    #[arg(value_name = "FILE", required_if_eq("mode", "replay"))]
+    replay_file: Option<PathBuf>,
+
     #[arg(long, default_value_t = false)]
     headless: bool,
 
@@ -60,12 +77,22 @@ struct Cli {
 
     #[arg(long, value_name = "FILE")]
     server_options: Option<PathBuf>,
+
+    /// Check the client's predictions against the server, and write what was found to this
+    /// file on exit
+    #[arg(long, value_name = "FILE")]
+    rollback_report: Option<PathBuf>,
+
+    /// Record the match the server runs to this file, for watching in replay mode
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 enum Mode {
     Client,
     Server,
+    Replay,
 }
 
 fn load_config<T, S>(path: Option<PathBuf>, default_path: &str) -> Option<T>
@@ -276,6 +303,17 @@ pub fn run() {
                     })
                     .with_conditioner(server_launch_options.conditioner.clone()),
                 },
+                ServerNetConfig::Netcode {
+                    // websocket, for browsers without webtransport
+                    config: server_netcode_config.clone(),
+                    io: ServerIoConfig::from_transport(ServerTransport::WebSocketServer {
+                        server_addr: SocketAddr::new(
+                            IpAddr::V4(server_launch_options.listen_addr),
+                            server_launch_options.websocket_listen_port,
+                        ),
+                    })
+                    .with_conditioner(server_launch_options.conditioner.clone()),
+                },
             ];
 
             let server_config = ServerConfig {
@@ -284,81 +322,240 @@ pub fn run() {
                 ..default()
             };
 
-            build_client_app(
+            let mut client_app = build_client_app(
                 remote_client_config,
                 local_client_config,
                 client_launch_options.asset_path,
                 server_config,
-            )
-            .run();
+            );
+
+            // A debugging aid, too chatty and costly to leave on
+            if let Some(rollback_report) = cli.rollback_report {
+                client_app
+                    .add_plugins(RollbackDiagnosticsPlugin)
+                    .insert_resource(RollbackReportPath(rollback_report));
+            }
+
+            client_app.run();
         }
         Mode::Server => {
-            let server_launch_options = load_server_options(cli.server_options);
+            let mut server_launch_options = load_server_options(cli.server_options);
 
             let headless = cli.headless || server_launch_options.headless;
 
-            let server_netcode_config = ServerNetcodeConfig::default()
-                .with_protocol_id(shared_launch_options.protocol_id)
-                .with_key(shared_launch_options.key);
+            let mode = if headless {
+                ServerMode::Headless
+            } else {
+                ServerMode::Windowed
+            };
 
-            let webtransport_identity = load_certificate_from_files(
-                Path::new(&server_launch_options.webtransport_cert_path),
-                Path::new(&server_launch_options.webtransport_key_path),
-            )
-            .unwrap();
+            let mut matches = vec![MatchInstanceOptions {
+                name: server_launch_options.match_name.clone(),
+                udp_listen_port: server_launch_options.udp_listen_port,
+                webtransport_listen_port: server_launch_options.webtransport_listen_port,
+                websocket_listen_port: server_launch_options.websocket_listen_port,
+                match_rules: server_launch_options.match_rules,
+                race_records_path: server_launch_options.race_records_path.clone(),
+            }];
+            matches.extend(server_launch_options.instances.drain(..));
+
+            // Matches writing the same file would overwrite each other's records
+            for (index, instance) in matches.iter().enumerate() {
+                if let Some(other) = matches[..index]
+                    .iter()
+                    .find(|other| other.race_records_path == instance.race_records_path)
+                {
+                    eprintln!(
+                        "Error: matches \"{}\" and \"{}\" both keep race records in {}. Give each match its own race_records_path in the server options.",
+                        other.name, instance.name, instance.race_records_path
+                    );
+                    std::process::exit(1);
+                }
+            }
 
-            println!(
-                "Launching Server with Certificate Digest: {}",
-                webtransport_identity.certificate_chain().as_slice()[0]
-                    .hash()
-                    .to_string()
-                    .replace(":", "")
+            // Only worth listing matches when there's more than one to pick from
+            let directory = (matches.len() > 1).then(|| {
+                MatchDirectory::new(
+                    matches
+                        .iter()
+                        .map(|instance| {
+                            MatchDirectoryEntry::new(
+                                instance.name.clone(),
+                                instance.match_rules.mode,
+                                instance.udp_listen_port,
+                                instance.webtransport_listen_port,
+                                instance.websocket_listen_port,
+                            )
+                        })
+                        .collect(),
+                )
+            });
+
+            // SIGINT and SIGTERM give everyone a countdown before the server goes away. A second
+            // one doesn't wait.
+            let shutdown_signal = ShutdownSignal::default();
+            let signalled = shutdown_signal.0.clone();
+            ctrlc::set_handler(move || {
+                if signalled.swap(true, Ordering::Relaxed) {
+                    std::process::exit(1);
+                }
+                println!("Shutting down, press Ctrl-C again to stop immediately");
+            })
+            .expect("unable to listen for shutdown signals");
+
+            let mut matches = matches.into_iter().enumerate();
+            let (_, main_match) = matches.next().expect("there is always a main match");
+
+            // Every other match runs headless in its own app, on its own thread
+            for (index, instance) in matches {
+                let server_config = match_server_config(
+                    shared_config,
+                    &shared_launch_options,
+                    &server_launch_options,
+                    &instance,
+                );
+                let asset_path = server_launch_options.asset_path.clone();
+                let directory = directory
+                    .as_ref()
+                    .map(|directory| directory.for_match(index));
+                let shutdown_signal = shutdown_signal.clone();
+
+                println!(
+                    "Launching match \"{}\" on UDP port {}, WebTransport port {} and WebSocket port {}",
+                    instance.name,
+                    instance.udp_listen_port,
+                    instance.webtransport_listen_port,
+                    instance.websocket_listen_port
+                );
+
+                std::thread::Builder::new()
+                    .name(format!("match {}", instance.name))
+                    .spawn(move || {
+                        let mut server_app =
+                            build_server_app(server_config, asset_path, ServerMode::Headless);
+                        configure_match(&mut server_app, instance, directory);
+                        server_app.insert_resource(shutdown_signal);
+                        server_app.run();
+                    })
+                    .expect("unable to start a thread for the match");
+            }
+
+            let server_config = match_server_config(
+                shared_config,
+                &shared_launch_options,
+                &server_launch_options,
+                &main_match,
             );
 
-            let net_configs = vec![
-                ServerNetConfig::Netcode {
-                    // normal udp sockets for desktop
-                    config: server_netcode_config.clone(),
-                    io: ServerIoConfig::from_transport(ServerTransport::UdpSocket(
-                        (
-                            server_launch_options.listen_addr,
-                            server_launch_options.udp_listen_port,
-                        )
-                            .into(),
-                    ))
-                    .with_conditioner(server_launch_options.conditioner.clone()),
-                },
-                ServerNetConfig::Netcode {
-                    // webtransport
-                    config: server_netcode_config.clone(),
-                    io: ServerIoConfig::from_transport(ServerTransport::WebTransportServer {
-                        server_addr: SocketAddr::new(
-                            IpAddr::V4(server_launch_options.listen_addr),
-                            server_launch_options.webtransport_listen_port,
-                        ),
-                        certificate: webtransport_identity,
-                    })
-                    .with_conditioner(server_launch_options.conditioner.clone()),
-                },
-            ];
+            let mut server_app =
+                build_server_app(server_config, server_launch_options.asset_path, mode);
 
-            let server_config = ServerConfig {
-                shared: shared_config,
-                net: net_configs,
-                ..default()
-            };
+            configure_match(&mut server_app, main_match, directory);
+            server_app.insert_resource(shutdown_signal);
 
-            let mode = if headless {
-                ServerMode::Headless
-            } else {
-                ServerMode::Windowed
-            };
+            if let Some(record) = cli.record {
+                server_app.insert_resource(RecordMatch(record));
+            }
+
+            server_app.run();
+        }
+        Mode::Replay => {
+            let client_launch_options = load_client_options(cli.client_options);
+
+            // clap guarantees the file is there in replay mode
+            let replay_file = cli.replay_file.unwrap();
 
-            build_server_app(server_config, server_launch_options.asset_path, mode).run();
+            match build_replay_app(&replay_file, client_launch_options.asset_path) {
+                Ok(mut replay_app) => {
+                    replay_app.run();
+                }
+                Err(e) => println!("Unable to load replay {:?}: {}", replay_file, e),
+            }
         }
     }
 }
 
+/// The server config for one match in the server process, listening on that match's ports
+fn match_server_config(
+    shared_config: SharedConfig,
+    shared_launch_options: &SharedLaunchOptions,
+    server_launch_options: &ServerLaunchOptions,
+    instance: &MatchInstanceOptions,
+) -> ServerConfig {
+    let server_netcode_config = ServerNetcodeConfig::default()
+        .with_protocol_id(shared_launch_options.protocol_id)
+        .with_key(shared_launch_options.key);
+
+    let webtransport_identity = load_certificate_from_files(
+        Path::new(&server_launch_options.webtransport_cert_path),
+        Path::new(&server_launch_options.webtransport_key_path),
+    )
+    .unwrap();
+
+    println!(
+        "Launching Server with Certificate Digest: {}",
+        webtransport_identity.certificate_chain().as_slice()[0]
+            .hash()
+            .to_string()
+            .replace(":", "")
+    );
+
+    let net_configs = vec![
+        ServerNetConfig::Netcode {
+            // normal udp sockets for desktop
+            config: server_netcode_config.clone(),
+            io: ServerIoConfig::from_transport(ServerTransport::UdpSocket(
+                (server_launch_options.listen_addr, instance.udp_listen_port).into(),
+            ))
+            .with_conditioner(server_launch_options.conditioner.clone()),
+        },
+        ServerNetConfig::Netcode {
+            // webtransport
+            config: server_netcode_config.clone(),
+            io: ServerIoConfig::from_transport(ServerTransport::WebTransportServer {
+                server_addr: SocketAddr::new(
+                    IpAddr::V4(server_launch_options.listen_addr),
+                    instance.webtransport_listen_port,
+                ),
+                certificate: webtransport_identity,
+            })
+            .with_conditioner(server_launch_options.conditioner.clone()),
+        },
+        ServerNetConfig::Netcode {
+            // websocket, for browsers without webtransport
+            config: server_netcode_config.clone(),
+            io: ServerIoConfig::from_transport(ServerTransport::WebSocketServer {
+                server_addr: SocketAddr::new(
+                    IpAddr::V4(server_launch_options.listen_addr),
+                    instance.websocket_listen_port,
+                ),
+            })
+            .with_conditioner(server_launch_options.conditioner.clone()),
+        },
+    ];
+
+    ServerConfig {
+        shared: shared_config,
+        net: net_configs,
+        ..default()
+    }
+}
+
+/// The resources that make a server app run one particular match
+fn configure_match(
+    server_app: &mut App,
+    instance: MatchInstanceOptions,
+    directory: Option<MatchDirectory>,
+) {
+    server_app.insert_resource(MatchName(instance.name));
+    server_app.insert_resource(instance.match_rules);
+    server_app.insert_resource(RaceRecordsPath(PathBuf::from(instance.race_records_path)));
+
+    if let Some(directory) = directory {
+        server_app.insert_resource(directory);
+    }
+}
+
 pub fn load_certificate_from_files(
     cert_path: &Path,
     key_path: &Path,
@@ -15,7 +15,7 @@ use lightyear::{
         },
     },
 };
-use mygame_client::app::build_client_app;
+use mygame_client::{app::build_client_app, network::WebSocketFallback};
 use ron::de::from_str;
 use std::{
     net::{IpAddr, Ipv4Addr, SocketAddr},
@@ -36,6 +36,15 @@ fn extract_client_id() -> Option<u64> {
     client_id.parse::<u64>().ok()
 }
 
+/// Older browsers, and some locked down ones, have no WebTransport at all
+fn webtransport_available() -> bool {
+    web_sys::window()
+        .map(|window| {
+            js_sys::Reflect::has(&window, &JsValue::from_str("WebTransport")).unwrap_or(false)
+        })
+        .unwrap_or(false)
+}
+
 async fn fetch_config(path: &str) -> Result<String, JsValue> {
     let opts = RequestInit::new();
     opts.set_method("GET");
@@ -113,6 +122,8 @@ async fn initialize_game() -> Result<(), JsValue> {
     let client_launch_options = load_client_config().await?;
     let shared_launch_options = load_shared_config().await?;
 
+    let use_webtransport = webtransport_available();
+
     let certificate_digest = match &client_launch_options.certificate_digest {
         Some(digest) => {
             console::log_2(
@@ -121,6 +132,8 @@ async fn initialize_game() -> Result<(), JsValue> {
             );
             digest.clone()
         }
+        // Only WebTransport needs the digest
+        None if !use_webtransport => String::new(),
         None => {
             console::log_1(&"No certificate digest found in options.".into());
             return Err(JsValue::from_str("Missing certificate digest"));
@@ -141,23 +154,41 @@ async fn initialize_game() -> Result<(), JsValue> {
         },
     };
 
-    let transport_config = ClientIoConfig::from_transport(ClientTransport::WebTransportClient {
-        client_addr: SocketAddr::new(
-            IpAddr::V4(client_launch_options.listen_addr),
-            client_launch_options.listen_port,
+    let server_addr = match (use_webtransport, client_launch_options.websocket_server_port) {
+        (false, Some(websocket_port)) => SocketAddr::new(
+            IpAddr::V4(client_launch_options.server_addr),
+            websocket_port,
         ),
-        server_addr: SocketAddr::new(
+        (false, None) => {
+            console::log_1(
+                &"WebTransport is unavailable and no WebSocket port is configured.".into(),
+            );
+            return Err(JsValue::from_str("No usable transport"));
+        }
+        (true, _) => SocketAddr::new(
             IpAddr::V4(client_launch_options.server_addr),
             client_launch_options.server_port,
         ),
-        certificate_digest: certificate_digest.to_owned(),
-    });
+    };
+
+    let transport = if use_webtransport {
+        ClientTransport::WebTransportClient {
+            client_addr: SocketAddr::new(
+                IpAddr::V4(client_launch_options.listen_addr),
+                client_launch_options.listen_port,
+            ),
+            server_addr,
+            certificate_digest: certificate_digest.to_owned(),
+        }
+    } else {
+        console::log_1(&"WebTransport is unavailable, connecting over WebSocket.".into());
+        ClientTransport::WebSocketClient { server_addr }
+    };
+
+    let transport_config = ClientIoConfig::from_transport(transport);
 
     let auth = Authentication::Manual {
-        server_addr: SocketAddr::new(
-            IpAddr::V4(client_launch_options.server_addr),
-            client_launch_options.server_port,
-        ),
+        server_addr,
         client_id,
         private_key: shared_launch_options.key,
         protocol_id: shared_launch_options.protocol_id,
@@ -184,7 +215,16 @@ async fn initialize_game() -> Result<(), JsValue> {
     };
 
     console::log_1(&"Starting client app...".into());
-    build_client_app(client_config, client_launch_options.asset_path).run();
+    let mut client_app = build_client_app(client_config, client_launch_options.asset_path);
+
+    // WebTransport can still fail to connect, behind proxies that break it
+    if use_webtransport {
+        if let Some(port) = client_launch_options.websocket_server_port {
+            client_app.insert_resource(WebSocketFallback { port });
+        }
+    }
+
+    client_app.run();
 
     Ok(())
 }
@@ -19,7 +19,9 @@ pub struct Bot(pub u64);
 
 #[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Projectile {
-    pub owner: Entity
+    pub owner: Entity,
+    /// How much damage a hit does, before shields and armor
+    pub damage: u16,
 }
 
 impl MapEntities for Projectile {
@@ -37,6 +39,116 @@ pub struct Health {
     pub max: u16
 }
 
+/// Soaks up damage before Health does, and recharges once the ship hasn't been hit for a while
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Shield {
+    pub current: u16,
+    pub max: u16,
+}
+
+/// Which side a ship fights for, in game modes that have teams. Players keep theirs for the whole
+/// match, so it only needs replicating once.
+#[derive(Component, Serialize, Deserialize, Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Team {
+    Red,
+    Blue,
+}
+
+impl Team {
+    pub const ALL: [Team; 2] = [Team::Red, Team::Blue];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Team::Red => "Red",
+            Team::Blue => "Blue",
+        }
+    }
+}
+
+/// A team's flag in capture the flag, spawned on its FlagBase. The flag entity also has the team's Team.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Flag {
+    /// Where the flag sits when it's home
+    pub base: Vec3,
+}
+
+/// Where a Flag is. Predicted by every client, so picking up a flag feels instant and
+/// gets rolled back if the server disagrees.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum FlagState {
+    AtBase,
+    /// Following this ship around
+    Carried(Entity),
+    /// Left where its carrier was destroyed, until someone touches it or it returns on its own
+    Dropped,
+}
+
+impl MapEntities for FlagState {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        if let FlagState::Carried(carrier) = self {
+            *carrier = entity_mapper.get_mapped(*carrier);
+        }
+    }
+}
+
+/// A freshly spawned ship can't be damaged until the server removes this
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SpawnProtection;
+
+/// What a Pickup does for the ship that touches it
+#[derive(Serialize, Deserialize, Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PickupKind {
+    /// Restores the ship's health
+    Repair,
+    RapidFire,
+    Shield,
+    SpeedBoost,
+}
+
+impl PickupKind {
+    pub const ALL: [PickupKind; 4] = [
+        PickupKind::Repair,
+        PickupKind::RapidFire,
+        PickupKind::Shield,
+        PickupKind::SpeedBoost,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PickupKind::Repair => "Repair",
+            PickupKind::RapidFire => "RapidFire",
+            PickupKind::Shield => "Shield",
+            PickupKind::SpeedBoost => "SpeedBoost",
+        }
+    }
+}
+
+/// A power-up floating in the level, put out by the server at the level's pickup spawns
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Pickup(pub PickupKind);
+
+/// Whether a Pickup can be collected, or is waiting to respawn
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PickupAvailable(pub bool);
+
+/// Weapons cool down faster until the tick this expires on
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RapidFire {
+    pub expires: Tick,
+}
+
+/// The ship flies faster until the tick this expires on
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SpeedBoost {
+    pub expires: Tick,
+}
+
+/// The ship's shield is overcharged past its max until the tick this expires on
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ShieldBoost {
+    pub expires: Tick,
+}
+
 pub fn register_components(app: &mut App) {
     app.register_component::<Player>(ChannelDirection::ServerToClient)
         .add_prediction(ComponentSyncMode::Once)
@@ -59,7 +171,50 @@ pub fn register_components(app: &mut App) {
         .add_prediction(ComponentSyncMode::Full);
 
     app.register_component::<Health>(ChannelDirection::ServerToClient)
-        .add_prediction(ComponentSyncMode::Simple);
+        .add_prediction(ComponentSyncMode::Simple)
+        .add_interpolation(ComponentSyncMode::Simple);
+
+    app.register_component::<Shield>(ChannelDirection::ServerToClient)
+        .add_prediction(ComponentSyncMode::Simple)
+        .add_interpolation(ComponentSyncMode::Simple);
+
+    app.register_component::<Team>(ChannelDirection::ServerToClient)
+        .add_prediction(ComponentSyncMode::Once)
+        .add_interpolation(ComponentSyncMode::Once);
+
+    app.register_component::<Flag>(ChannelDirection::ServerToClient)
+        .add_prediction(ComponentSyncMode::Once)
+        .add_interpolation(ComponentSyncMode::Once);
+
+    app.register_component::<FlagState>(ChannelDirection::ServerToClient)
+        .add_prediction(ComponentSyncMode::Full)
+        .add_map_entities();
+
+    app.register_component::<SpawnProtection>(ChannelDirection::ServerToClient)
+        .add_prediction(ComponentSyncMode::Simple)
+        .add_interpolation(ComponentSyncMode::Simple);
+
+    app.register_component::<Pickup>(ChannelDirection::ServerToClient)
+        .add_prediction(ComponentSyncMode::Once)
+        .add_interpolation(ComponentSyncMode::Once);
+
+    app.register_component::<PickupAvailable>(ChannelDirection::ServerToClient)
+        .add_prediction(ComponentSyncMode::Simple)
+        .add_interpolation(ComponentSyncMode::Simple);
+
+    // Buffs only change when a pickup starts one, and the owner predicts exactly when they
+    // wear off. Everyone else only needs to know they're there.
+    app.register_component::<RapidFire>(ChannelDirection::ServerToClient)
+        .add_prediction(ComponentSyncMode::Full)
+        .add_interpolation(ComponentSyncMode::Simple);
+
+    app.register_component::<SpeedBoost>(ChannelDirection::ServerToClient)
+        .add_prediction(ComponentSyncMode::Full)
+        .add_interpolation(ComponentSyncMode::Simple);
+
+    app.register_component::<ShieldBoost>(ChannelDirection::ServerToClient)
+        .add_prediction(ComponentSyncMode::Full)
+        .add_interpolation(ComponentSyncMode::Simple);
 
     app.register_component::<Position>(ChannelDirection::ServerToClient)
         .add_prediction(ComponentSyncMode::Full)
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::message::{GameMode, Level};
+
+/// Servers listen for discovery probes on this port, on every interface
+pub const DISCOVERY_PORT: u16 = 12030;
+
+/// Broadcast by clients looking for games on the local network.
+///
+/// Discovery happens outside of lightyear, over plain UDP, since the client isn't connected to
+/// anything yet. Probes and replies are sent as RON.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DiscoveryProbe;
+
+/// A server's answer to a DiscoveryProbe. Clients join it on the address the reply came from,
+/// at `udp_port`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DiscoveryReply {
+    pub name: String,
+    pub level: Level,
+    pub mode: GameMode,
+    pub players: u32,
+    pub udp_port: u16,
+    /// Still waiting for players to ready up
+    pub in_lobby: bool,
+}
+
+pub fn encode<T: Serialize>(value: &T) -> Option<Vec<u8>> {
+    ron::ser::to_string(value).ok().map(String::into_bytes)
+}
+
+/// None for anything that isn't a `T`, which a port open to the whole network will get
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Option<T> {
+    let text = std::str::from_utf8(bytes).ok()?;
+    ron::de::from_str(text).ok()
+}
@@ -7,7 +7,9 @@ pub enum NetworkedInput {
     #[actionlike(DualAxis)]
     Aim,
     #[actionlike(Button)]
-    Fire
+    Fire,
+    #[actionlike(Button)]
+    Boost,
 }
 
 pub fn register_input(app: &mut App) {
@@ -1,6 +1,7 @@
 use bevy::prelude::*;
 
 pub mod component;
+pub mod discovery;
 pub mod input;
 pub mod message;
 
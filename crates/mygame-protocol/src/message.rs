@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use bevy::prelude::*;
 use lightyear::prelude::*;
 
@@ -8,20 +10,245 @@ pub enum Level {
     Example,
 }
 
+impl Level {
+    /// Every level a match can be played on
+    pub const PLAYABLE: [Level; 1] = [Level::Example];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Level::Void => "None",
+            Level::Example => "Example",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum GameMode {
+    #[default]
+    FreeForAll,
+    TeamDeathmatch,
+    CaptureTheFlag,
+    /// Laps through the level's checkpoints, without weapons
+    Race,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 4] = [
+        GameMode::FreeForAll,
+        GameMode::TeamDeathmatch,
+        GameMode::CaptureTheFlag,
+        GameMode::Race,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::FreeForAll => "Free for all",
+            GameMode::TeamDeathmatch => "Team deathmatch",
+            GameMode::CaptureTheFlag => "Capture the flag",
+            GameMode::Race => "Race",
+        }
+    }
+}
+
+/// How the server runs the match. Chosen in the server options and sent to clients in ServerWelcome.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub struct MatchRules {
+    pub mode: GameMode,
+    /// Whether projectiles damage ships on the same team as whoever fired them
+    pub friendly_fire: bool,
+}
+
+impl MatchRules {
+    pub fn has_teams(&self) -> bool {
+        match self.mode {
+            GameMode::FreeForAll | GameMode::Race => false,
+            GameMode::TeamDeathmatch | GameMode::CaptureTheFlag => true,
+        }
+    }
+
+    /// Whether ships can fire at all
+    pub fn has_combat(&self) -> bool {
+        self.mode != GameMode::Race
+    }
+}
+
+/// The match a hosted server will start once everyone in the lobby is ready. The host picks it
+/// in the lobby.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct MatchSettings {
+    pub level: Level,
+    pub rules: MatchRules,
+    pub bot_count: u32,
+    /// How long until the match ends, if it ends at all
+    pub time_limit: Option<Duration>,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        Self {
+            level: Level::Example,
+            rules: MatchRules::default(),
+            bot_count: 0,
+            time_limit: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LobbyPlayer {
+    pub id: ClientId,
+    pub ready: bool,
+}
+
+/// Sent instead of ServerWelcome to clients that connect before the match has started, and to
+/// everyone in the lobby whenever it changes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct ServerLobby {
+    /// The player hosting the server, who picks the settings
+    pub host: Option<ClientId>,
+    pub players: Vec<LobbyPlayer>,
+    pub settings: MatchSettings,
+}
+
+/// Whether this client is ready for the match to start
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ClientSetReady(pub bool);
+
+/// New match settings from the lobby. Ignored unless it's from the host.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ClientHostMatchSettings(pub MatchSettings);
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct ServerWelcome {
     pub current_level: Level,
+    pub match_rules: MatchRules,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct ClientRequestRespawn;
 
+/// Leave the game, if playing, and watch without a ship until the next ClientRequestRespawn
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ClientRequestSpectate;
+
+/// Sent right before disconnecting on purpose, so the server removes the ship straight away
+/// instead of holding it for a reconnect
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ClientRequestLeave;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct ClientHostRequestShutdown;
 
+/// Why the server is shutting down
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShutdownReason {
+    HostLeft,
+    /// The match's time limit ran out
+    MatchOver,
+    /// The server was told to stop by whoever runs it
+    ServerStopping,
+}
+
+impl ShutdownReason {
+    pub fn message(&self) -> &'static str {
+        match self {
+            ShutdownReason::HostLeft => "The host left the game",
+            ShutdownReason::MatchOver => "The match is over",
+            ShutdownReason::ServerStopping => "The server is shutting down",
+        }
+    }
+}
+
+/// Sent to everyone when the server is about to stop, `seconds` before it does
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ServerShutdownNotice {
+    pub reason: ShutdownReason,
+    pub seconds: u32,
+}
+
+/// What a hit on a ship damaged
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum HitKind {
+    /// Absorbed entirely by the ship's shield
+    Shield,
+    #[default]
+    Hull,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct ServerShipHit {
     pub position: Vec3,
+    pub kind: HitKind,
+}
+
+/// Whoever fired the projectile that destroyed a ship
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum Killer {
+    Player(ClientId),
+    Bot(u64),
+}
+
+/// Sent to a player when their ship is destroyed. They may not respawn until `respawn_delay` has passed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ServerShipDestroyed {
+    pub position: Vec3,
+    pub killer: Option<Killer>,
+    pub respawn_delay: Duration,
+}
+
+/// Each team's score: kills in team deathmatch, captures in capture the flag. Sent to everyone whenever it changes, and to clients as they connect.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct ServerTeamScores {
+    pub red: u32,
+    pub blue: u32,
+}
+
+/// Sent to a racer each time they pass their next checkpoint
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ServerCheckpointPassed {
+    pub checkpoint: u32,
+    pub checkpoint_count: u32,
+    /// Laps completed so far
+    pub lap: u32,
+    /// Time since the lap started. Zero when crossing the start line to begin the first lap.
+    pub split: Duration,
+    /// Set when this pass finished a lap
+    pub lap_time: Option<Duration>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RaceLeaderboardEntry {
+    pub player: ClientId,
+    pub laps: u32,
+    /// Fastest lap this session
+    pub best_lap: Option<Duration>,
+    /// Fastest lap ever on this server
+    pub personal_best: Option<Duration>,
+}
+
+/// Every racer, fastest lap first. Sent to everyone whenever someone finishes a lap, and to clients as they connect.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct ServerRaceLeaderboard {
+    pub entries: Vec<RaceLeaderboardEntry>,
+}
+
+/// One of the matches running in the server process
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MatchListing {
+    pub name: String,
+    pub mode: GameMode,
+    pub players: u32,
+    /// Ports to connect to this match on, on the same address as the server that sent the list
+    pub udp_port: u16,
+    pub webtransport_port: u16,
+    pub websocket_port: u16,
+}
+
+/// Every match the server process is running, including the one the client is in. Sent to
+/// clients as they connect, and again every so often as player counts change.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct ServerMatchList {
+    pub matches: Vec<MatchListing>,
 }
 
 #[derive(Channel)]
@@ -33,9 +260,20 @@ pub struct Reliable;
 pub fn register_messages(app: &mut App) {
     app.register_message::<ServerWelcome>(ChannelDirection::ServerToClient);
     app.register_message::<ServerShipHit>(ChannelDirection::ServerToClient);
+    app.register_message::<ServerShipDestroyed>(ChannelDirection::ServerToClient);
+    app.register_message::<ServerTeamScores>(ChannelDirection::ServerToClient);
+    app.register_message::<ServerCheckpointPassed>(ChannelDirection::ServerToClient);
+    app.register_message::<ServerRaceLeaderboard>(ChannelDirection::ServerToClient);
+    app.register_message::<ServerMatchList>(ChannelDirection::ServerToClient);
+    app.register_message::<ServerLobby>(ChannelDirection::ServerToClient);
+    app.register_message::<ServerShutdownNotice>(ChannelDirection::ServerToClient);
 
     app.register_message::<ClientRequestRespawn>(ChannelDirection::ClientToServer);
+    app.register_message::<ClientRequestSpectate>(ChannelDirection::ClientToServer);
+    app.register_message::<ClientRequestLeave>(ChannelDirection::ClientToServer);
     app.register_message::<ClientHostRequestShutdown>(ChannelDirection::ClientToServer);
+    app.register_message::<ClientSetReady>(ChannelDirection::ClientToServer);
+    app.register_message::<ClientHostMatchSettings>(ChannelDirection::ClientToServer);
 
     app.add_channel::<UnorderedReliable>(ChannelSettings {
         mode: ChannelMode::UnorderedReliable(ReliableSettings::default()),
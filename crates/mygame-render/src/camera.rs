@@ -1,5 +1,5 @@
 use avian3d::prelude::{LinearVelocity, Position};
-use bevy::{math::VectorSpace, prelude::*, render::view::RenderLayers};
+use bevy::{input::mouse::AccumulatedMouseMotion, math::VectorSpace, prelude::*, render::view::RenderLayers};
 use lightyear::prelude::client::InterpolationSet;
 
 pub (crate) struct CameraPlugin;
@@ -14,6 +14,7 @@ impl Plugin for CameraPlugin {
                 .insert(CameraVelocity::default())
                 .insert(RenderLayers::layer(0));
         })
+        .add_systems(Update, free_fly_camera)
         .add_systems(PostUpdate, follow_camera_target.after(InterpolationSet::VisualInterpolation).before(TransformSystem::TransformPropagate));
     }
 }
@@ -27,6 +28,23 @@ pub struct CameraTarget {
     pub smooth_time: f32,
 }
 
+/// Put on the MainCamera to fly it around by hand instead of following a CameraTarget.
+/// WASD moves, Q/E go down/up, Shift goes faster, and holding the right mouse button looks around.
+#[derive(Component)]
+pub struct FreeFlyCamera {
+    pub speed: f32,
+    pub look_sensitivity: f32,
+}
+
+impl Default for FreeFlyCamera {
+    fn default() -> Self {
+        Self {
+            speed: 20.0,
+            look_sensitivity: 0.003,
+        }
+    }
+}
+
 // Storage for the smooth damp velocity
 #[derive(Component, Default)]
 pub struct CameraVelocity {
@@ -98,7 +116,7 @@ fn smooth_damp_quat(
 fn follow_camera_target(
     time: Res<Time>,
     q_camera_targets: Query<(&Transform, &CameraTarget, Option<&LinearVelocity>)>,
-    mut q_cameras: Query<(&mut Transform, &mut CameraVelocity), (With<MainCamera>, Without<CameraTarget>)>,
+    mut q_cameras: Query<(&mut Transform, &mut CameraVelocity), (With<MainCamera>, Without<CameraTarget>, Without<FreeFlyCamera>)>,
 ) {
     let Some((target_transform, target, velocity_opt)) = q_camera_targets.iter().next() else {
         return;
@@ -140,3 +158,44 @@ fn follow_camera_target(
 
     camera_transform.rotation = desired_rotation;
 }
+
+fn free_fly_camera(
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mouse_motion: Res<AccumulatedMouseMotion>,
+    mut q_cameras: Query<(&mut Transform, &FreeFlyCamera), With<MainCamera>>,
+) {
+    for (mut transform, free_fly) in &mut q_cameras {
+        if mouse_buttons.pressed(MouseButton::Right) && mouse_motion.delta != Vec2::ZERO {
+            let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+            let yaw = yaw - mouse_motion.delta.x * free_fly.look_sensitivity;
+            // Stop just short of straight up/down so the yaw doesn't flip over
+            let pitch = (pitch - mouse_motion.delta.y * free_fly.look_sensitivity)
+                .clamp(-1.54, 1.54);
+            transform.rotation = Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0);
+        }
+
+        let mut direction = Vec3::ZERO;
+        for (key, key_direction) in [
+            (KeyCode::KeyW, transform.forward().as_vec3()),
+            (KeyCode::KeyS, transform.back().as_vec3()),
+            (KeyCode::KeyA, transform.left().as_vec3()),
+            (KeyCode::KeyD, transform.right().as_vec3()),
+            (KeyCode::KeyE, Vec3::Y),
+            (KeyCode::KeyQ, Vec3::NEG_Y),
+        ] {
+            if keys.pressed(key) {
+                direction += key_direction;
+            }
+        }
+
+        let speed = if keys.pressed(KeyCode::ShiftLeft) {
+            free_fly.speed * 4.0
+        } else {
+            free_fly.speed
+        };
+
+        transform.translation += direction.normalize_or_zero() * speed * time.delta_secs();
+    }
+}
@@ -0,0 +1,69 @@
+use bevy::{color::palettes::tailwind::AMBER_400, prelude::*};
+use mygame_assets::{CHECKPOINT_RADIUS, Checkpoint};
+use mygame_protocol::message::{GameMode, MatchRules};
+
+/// Draws a glowing ring at each of the level's checkpoints in race mode
+pub(crate) struct CheckpointsPlugin;
+
+impl Plugin for CheckpointsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CheckpointAssets>()
+            .add_systems(Update, add_checkpoint_rings);
+    }
+}
+
+/// How thick the ring's tube is
+const RING_THICKNESS: f32 = 0.4;
+
+#[derive(Resource)]
+struct CheckpointAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for CheckpointAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world.resource_mut::<Assets<Mesh>>().add(Torus::new(
+            CHECKPOINT_RADIUS - RING_THICKNESS,
+            CHECKPOINT_RADIUS + RING_THICKNESS,
+        ));
+
+        let material = world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(StandardMaterial {
+                base_color: AMBER_400.into(),
+                emissive: LinearRgba::from(AMBER_400) * 4.0,
+                unlit: true,
+                ..default()
+            });
+
+        Self { mesh, material }
+    }
+}
+
+/// Lives on the checkpoint once its ring is drawn
+#[derive(Component)]
+struct CheckpointRing;
+
+fn add_checkpoint_rings(
+    mut commands: Commands,
+    match_rules: Res<MatchRules>,
+    checkpoint_assets: Res<CheckpointAssets>,
+    q_checkpoints: Query<Entity, (With<Checkpoint>, Without<CheckpointRing>)>,
+) {
+    if match_rules.mode != GameMode::Race {
+        return;
+    }
+
+    for checkpoint in &q_checkpoints {
+        commands.spawn((
+            Mesh3d(checkpoint_assets.mesh.clone()),
+            MeshMaterial3d(checkpoint_assets.material.clone()),
+            // The torus lies flat, stand it up so ships fly through it along the checkpoint's forward
+            Transform::from_rotation(Quat::from_rotation_x(std::f32::consts::FRAC_PI_2)),
+            ChildOf(checkpoint),
+        ));
+
+        commands.entity(checkpoint).insert(CheckpointRing);
+    }
+}
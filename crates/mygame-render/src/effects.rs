@@ -4,46 +4,58 @@ use bevy_hanabi::{EffectProperties, ParticleEffect, Value, VectorValue};
 use lightyear::{client::message::ClientMessage, prelude::{is_client, FromServer, Message, TickManager}};
 use mygame_assets::assets::FxAssets;
 use mygame_common::{ship::{DespawnAfter, ProjectileHitNonShip}, Rendered};
-use mygame_protocol::{component::Ship, message::ServerShipHit};
+use mygame_protocol::{
+    component::Ship,
+    message::{HitKind, ServerShipHit},
+};
 
 pub (crate) struct FxPlugin;
 
 impl Plugin for FxPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Update, (render_ship_hit_fx).run_if(is_client));
+        app.add_observer(play_fx);
         app.add_observer(render_ship_destroy_fx);
         app.add_observer(render_non_ship_hit_fx);
     }
 }
 
-fn render_ship_hit_fx(
-    mut commands: Commands,
-    fx_assets: Res<FxAssets>,
-    tick_manager: Res<TickManager>,
-    mut ship_hit_event_reader: EventReader<FromServer<ServerShipHit>>,
-) {
-    for ev in ship_hit_event_reader.read() {
-        commands.spawn((
-            ParticleEffect::new(fx_assets.laser_hit_vfx_large.clone()),
-            Transform::from_translation(ev.message.position),
-            DespawnAfter {
-                created_at_tick: *tick_manager.tick(),
-                lifetime_ticks: 62,
-                is_server_controlled: false,
-            },
-        ));
+/// Play a one-off effect at a position. Usually triggered by the systems below in response to
+/// gameplay, but anything else that wants the same effects (like a replay) can trigger it too.
+#[derive(Event, Clone, Copy, Debug)]
+pub enum PlayFx {
+    ShipHit(Vec3),
+    ShieldHit(Vec3),
+    NonShipHit(Vec3),
+    ShipDestroyed(Vec3),
+}
+
+impl PlayFx {
+    /// Hits the shield soaked up look different to hits on the hull
+    pub fn ship_hit(position: Vec3, kind: HitKind) -> Self {
+        match kind {
+            HitKind::Shield => PlayFx::ShieldHit(position),
+            HitKind::Hull => PlayFx::ShipHit(position),
+        }
     }
 }
 
-fn render_non_ship_hit_fx(
-    trigger: Trigger<ProjectileHitNonShip>,
+fn play_fx(
+    trigger: Trigger<PlayFx>,
     mut commands: Commands,
     fx_assets: Res<FxAssets>,
     tick_manager: Res<TickManager>,
 ) {
+    let (effect, position) = match *trigger {
+        PlayFx::ShipHit(position) => (fx_assets.laser_hit_vfx_large.clone(), position),
+        PlayFx::ShieldHit(position) => (fx_assets.shield_hit_vfx.clone(), position),
+        PlayFx::NonShipHit(position) => (fx_assets.laser_hit_vfx_small.clone(), position),
+        PlayFx::ShipDestroyed(position) => (fx_assets.ship_destroy_vfx.clone(), position),
+    };
+
     commands.spawn((
-        ParticleEffect::new(fx_assets.laser_hit_vfx_small.clone()),
-        Transform::from_translation(trigger.position),
+        ParticleEffect::new(effect),
+        Transform::from_translation(position),
         DespawnAfter {
             created_at_tick: *tick_manager.tick(),
             lifetime_ticks: 62,
@@ -52,22 +64,25 @@ fn render_non_ship_hit_fx(
     ));
 }
 
+fn render_ship_hit_fx(
+    mut commands: Commands,
+    mut ship_hit_event_reader: EventReader<FromServer<ServerShipHit>>,
+) {
+    for ev in ship_hit_event_reader.read() {
+        commands.trigger(PlayFx::ship_hit(ev.message.position, ev.message.kind));
+    }
+}
+
+fn render_non_ship_hit_fx(trigger: Trigger<ProjectileHitNonShip>, mut commands: Commands) {
+    commands.trigger(PlayFx::NonShipHit(trigger.position));
+}
+
 fn render_ship_destroy_fx(
     trigger: Trigger<OnRemove, Ship>,
     mut commands: Commands,
-    fx_assets: Res<FxAssets>,
-    tick_manager: Res<TickManager>,
     q_rendered_ships: Query<&Position, (Rendered, With<Ship>)>,
 ) {
     if let Ok(ship_position) = q_rendered_ships.get(trigger.target()) {
-        commands.spawn((
-            ParticleEffect::new(fx_assets.ship_destroy_vfx.clone()),
-            Transform::from_translation(ship_position.0),
-            DespawnAfter {
-                created_at_tick: *tick_manager.tick(),
-                lifetime_ticks: 62,
-                is_server_controlled: false,
-            },
-        ));
+        commands.trigger(PlayFx::ShipDestroyed(ship_position.0));
     }
 }
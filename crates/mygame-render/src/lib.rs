@@ -7,7 +7,11 @@ use bevy_inspector_egui::quick::WorldInspectorPlugin;
 pub struct RenderPlugin;
 
 pub mod camera;
+mod checkpoints;
 pub mod effects;
+mod pickups;
+mod shield;
+pub mod teams;
 
 // If the headless server can't run it or doesn't need it
 // It goes in this plugin
@@ -15,7 +19,11 @@ impl Plugin for RenderPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins((
             camera::CameraPlugin,
+            checkpoints::CheckpointsPlugin,
             effects::FxPlugin,
+            pickups::PickupsPlugin,
+            shield::ShieldPlugin,
+            teams::TeamsPlugin,
             //PhysicsDebugPlugin::default(),
             EguiPlugin { enable_multipass_for_primary_context: true },
             WorldInspectorPlugin::default(),
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use bevy::{
+    color::palettes::tailwind::{AMBER_400, EMERALD_400, FUCHSIA_400, SKY_400},
+    prelude::*,
+};
+use mygame_common::Rendered;
+use mygame_protocol::component::{Pickup, PickupKind};
+
+/// Gives each pickup a spinning, glowing model colored by what it does
+pub(crate) struct PickupsPlugin;
+
+impl Plugin for PickupsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PickupAssets>()
+            .add_systems(Update, (add_pickup_models, spin_pickup_models));
+    }
+}
+
+const PICKUP_SIZE: f32 = 1.5;
+/// Radians per second
+const PICKUP_SPIN_SPEED: f32 = 1.5;
+
+#[derive(Resource)]
+struct PickupAssets {
+    mesh: Handle<Mesh>,
+    materials: HashMap<PickupKind, Handle<StandardMaterial>>,
+}
+
+fn pickup_color(kind: PickupKind) -> Color {
+    match kind {
+        PickupKind::Repair => EMERALD_400.into(),
+        PickupKind::RapidFire => AMBER_400.into(),
+        PickupKind::Shield => SKY_400.into(),
+        PickupKind::SpeedBoost => FUCHSIA_400.into(),
+    }
+}
+
+impl FromWorld for PickupAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Cuboid::from_length(PICKUP_SIZE));
+
+        let mut standard_materials = world.resource_mut::<Assets<StandardMaterial>>();
+        let materials = PickupKind::ALL
+            .into_iter()
+            .map(|kind| {
+                let color = pickup_color(kind);
+                let material = standard_materials.add(StandardMaterial {
+                    base_color: color,
+                    emissive: LinearRgba::from(color) * 3.0,
+                    ..default()
+                });
+
+                (kind, material)
+            })
+            .collect();
+
+        Self { mesh, materials }
+    }
+}
+
+#[derive(Component)]
+struct PickupModel;
+
+fn add_pickup_models(
+    mut commands: Commands,
+    pickup_assets: Res<PickupAssets>,
+    q_pickups: Query<(Entity, &Pickup), (Rendered, Without<Children>)>,
+) {
+    for (pickup, kind) in &q_pickups {
+        commands.entity(pickup).with_child((
+            Mesh3d(pickup_assets.mesh.clone()),
+            MeshMaterial3d(pickup_assets.materials[&kind.0].clone()),
+            // Stood on a corner, so it reads as a pickup rather than a crate
+            Transform::from_rotation(Quat::from_euler(
+                EulerRot::XYZ,
+                std::f32::consts::FRAC_PI_4,
+                0.0,
+                std::f32::consts::FRAC_PI_4,
+            )),
+            PickupModel,
+        ));
+    }
+}
+
+fn spin_pickup_models(time: Res<Time>, mut q_models: Query<&mut Transform, With<PickupModel>>) {
+    for mut transform in &mut q_models {
+        transform.rotate_y(PICKUP_SPIN_SPEED * time.delta_secs());
+    }
+}
@@ -0,0 +1,116 @@
+use bevy::{color::palettes::tailwind::SKY_400, prelude::*};
+use mygame_common::Rendered;
+use mygame_protocol::component::{ShieldBoost, Ship, SpawnProtection};
+
+/// Draws a bubble around ships with SpawnProtection, which can't be damaged, or an overcharged
+/// shield from a ShieldBoost
+pub(crate) struct ShieldPlugin;
+
+impl Plugin for ShieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShieldAssets>().add_systems(
+            Update,
+            (add_shields, remove_shields, pulse_shields).chain(),
+        );
+    }
+}
+
+const SHIELD_RADIUS: f32 = 2.5;
+const SHIELD_ALPHA: f32 = 0.25;
+/// Pulses per second
+const SHIELD_PULSE_SPEED: f32 = 2.0;
+
+#[derive(Resource)]
+struct ShieldAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for ShieldAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(Sphere::new(SHIELD_RADIUS).mesh().ico(3).unwrap());
+
+        let material = world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(StandardMaterial {
+                base_color: SKY_400.with_alpha(SHIELD_ALPHA).into(),
+                emissive: LinearRgba::from(SKY_400) * 2.0,
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                cull_mode: None,
+                ..default()
+            });
+
+        Self { mesh, material }
+    }
+}
+
+/// Lives on the ship, pointing at its shield bubble
+#[derive(Component)]
+struct ShieldBubble(Entity);
+
+/// The bubble itself, so other systems dressing up ship meshes can leave it alone
+#[derive(Component)]
+pub(crate) struct ShieldBubbleMesh;
+
+fn add_shields(
+    mut commands: Commands,
+    shield_assets: Res<ShieldAssets>,
+    q_protected_ships: Query<
+        Entity,
+        (
+            Rendered,
+            With<Ship>,
+            Or<(With<SpawnProtection>, With<ShieldBoost>)>,
+            Without<ShieldBubble>,
+        ),
+    >,
+) {
+    for ship in &q_protected_ships {
+        let bubble = commands
+            .spawn((
+                Mesh3d(shield_assets.mesh.clone()),
+                MeshMaterial3d(shield_assets.material.clone()),
+                Transform::default(),
+                ShieldBubbleMesh,
+                ChildOf(ship),
+            ))
+            .id();
+
+        commands.entity(ship).insert(ShieldBubble(bubble));
+    }
+}
+
+fn remove_shields(
+    mut commands: Commands,
+    q_unprotected_ships: Query<
+        (Entity, &ShieldBubble),
+        (Without<SpawnProtection>, Without<ShieldBoost>),
+    >,
+) {
+    for (ship, bubble) in &q_unprotected_ships {
+        commands.entity(bubble.0).despawn();
+        commands.entity(ship).remove::<ShieldBubble>();
+    }
+}
+
+/// All bubbles share a material, so they all pulse together
+fn pulse_shields(
+    time: Res<Time>,
+    shield_assets: Res<ShieldAssets>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    q_bubbles: Query<(), With<ShieldBubble>>,
+) {
+    if q_bubbles.is_empty() {
+        return;
+    }
+
+    if let Some(material) = materials.get_mut(&shield_assets.material) {
+        let pulse = (time.elapsed_secs() * SHIELD_PULSE_SPEED * std::f32::consts::TAU).sin() * 0.5 + 0.5;
+        material
+            .base_color
+            .set_alpha(SHIELD_ALPHA * (0.6 + 0.4 * pulse));
+    }
+}
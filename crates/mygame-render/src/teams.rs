@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+
+use bevy::{
+    color::{
+        Mix,
+        palettes::tailwind::{BLUE_500, RED_500},
+    },
+    prelude::*,
+};
+use mygame_common::Rendered;
+use mygame_protocol::component::{Flag, Ship, Team};
+
+use crate::shield::ShieldBubbleMesh;
+
+/// Tints ships and flags toward their team's color
+pub(crate) struct TeamsPlugin;
+
+impl Plugin for TeamsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TintedMaterials>()
+            .add_systems(Update, tint_team_models);
+    }
+}
+
+/// How far a model's own colors are pulled toward its team color
+const TINT_STRENGTH: f32 = 0.6;
+
+pub fn team_color(team: Team) -> Color {
+    match team {
+        Team::Red => RED_500.into(),
+        Team::Blue => BLUE_500.into(),
+    }
+}
+
+/// Each glTF material tinted for each team, shared by everything using it
+#[derive(Resource, Default)]
+struct TintedMaterials(HashMap<(AssetId<StandardMaterial>, Team), Handle<StandardMaterial>>);
+
+#[derive(Component)]
+struct TeamTinted;
+
+/// A model spawns some time after its entity does, so keep checking until its meshes show up
+fn tint_team_models(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut tinted_materials: ResMut<TintedMaterials>,
+    q_models: Query<
+        (Entity, &Team),
+        (Rendered, Or<(With<Ship>, With<Flag>)>, Without<TeamTinted>),
+    >,
+    q_children: Query<&Children>,
+    mut q_meshes: Query<&mut MeshMaterial3d<StandardMaterial>, Without<ShieldBubbleMesh>>,
+) {
+    for (entity, team) in &q_models {
+        let mut tinted_any = false;
+
+        for descendant in q_children.iter_descendants(entity) {
+            let Ok(mut material) = q_meshes.get_mut(descendant) else {
+                continue;
+            };
+
+            let tinted = tinted_materials
+                .0
+                .entry((material.id(), *team))
+                .or_insert_with(|| {
+                    let mut tinted = materials.get(&material.0).cloned().unwrap_or_default();
+                    tinted.base_color = tinted.base_color.mix(&team_color(*team), TINT_STRENGTH);
+                    materials.add(tinted)
+                });
+
+            material.0 = tinted.clone();
+            tinted_any = true;
+        }
+
+        if tinted_any {
+            commands.entity(entity).insert(TeamTinted);
+        }
+    }
+}
@@ -24,10 +24,24 @@ use lightyear::{
     prelude::*,
     server::{config::ServerConfig, plugin::ServerPlugins},
 };
-use mygame_common::{CommonPlugin, LaunchConfigurations};
+use mygame_common::{CommonPlugin, LaunchConfigurations, match_recording::MatchRecordingPlugin};
 use mygame_render::RenderPlugin;
 
-use crate::{bots::BotsPlugin, network::NetworkPlugin, replication::ReplicationPlugin};
+use crate::{
+    bots::BotsPlugin,
+    ctf::CtfPlugin,
+    discovery::DiscoveryPlugin,
+    instances::InstancesPlugin,
+    interest::InterestPlugin,
+    lobby::{LobbyPlugin, MatchPhase},
+    network::NetworkPlugin,
+    pickups::PickupsPlugin,
+    race::RacePlugin,
+    replication::ReplicationPlugin,
+    shutdown::ShutdownPlugin,
+    spawning::SpawningPlugin,
+    teams::TeamsPlugin,
+};
 
 #[derive(Resource, PartialEq, Eq)]
 pub enum ServerMode {
@@ -49,31 +63,7 @@ pub fn build_server_app(server_config: ServerConfig, asset_path: String, mode: S
             app.add_plugins((DefaultPlugins.build().set(asset_plugin), RenderPlugin));
         }
         _ => {
-            app.add_plugins((
-                MinimalPlugins.build().set(ScheduleRunnerPlugin::run_loop(
-                    Duration::from_secs_f64(1.0 / 100.0),
-                )),
-                asset_plugin,
-                WindowPlugin {
-                    primary_window: None,
-                    exit_condition: ExitCondition::DontExit,
-                    ..default()
-                },
-                BevyRenderPlugin {
-                    render_creation: RenderCreation::Automatic(WgpuSettings {
-                        backends: None,
-                        ..default()
-                    }),
-                    ..default()
-                },
-                PanicHandlerPlugin,
-                TransformPlugin,
-                DiagnosticsPlugin,
-                StatesPlugin,
-                ScenePlugin,
-                GltfPlugin::default(),
-                PbrPlugin::default(),
-            ));
+            add_headless_plugins(&mut app, asset_plugin);
 
             match mode {
                 ServerMode::ClientHost(_) => {}
@@ -87,11 +77,17 @@ pub fn build_server_app(server_config: ServerConfig, asset_path: String, mode: S
                         });
                 }
             }
-
-            app.init_asset::<Image>(); // or add ImagePlugin
         }
     };
 
+    // A player hosting gathers everyone in the lobby first
+    let match_phase = match mode {
+        ServerMode::ClientHost(_) => MatchPhase::Lobby,
+        _ => MatchPhase::InProgress,
+    };
+
+    app.add_plugins(DiscoveryPlugin::for_config(&server_config));
+
     app.add_plugins(ServerPlugins {
         config: server_config,
     })
@@ -99,10 +95,53 @@ pub fn build_server_app(server_config: ServerConfig, asset_path: String, mode: S
         CommonPlugin,
         NetworkPlugin,
         ReplicationPlugin,
+        SpawningPlugin,
+        TeamsPlugin,
+        CtfPlugin,
+        RacePlugin,
+        PickupsPlugin,
         BotsPlugin,
+        InterestPlugin,
+        InstancesPlugin,
+        LobbyPlugin,
+        ShutdownPlugin,
+        MatchRecordingPlugin,
         EntropyPlugin::<WyRand>::default(),
     ))
+    .insert_state(match_phase)
     .insert_resource(mode);
 
     app
 }
+
+/// Everything needed to load levels and simulate the game without a window or GPU.
+/// Also used for headless clients, which is why it doesn't add any networking.
+pub fn add_headless_plugins(app: &mut App, asset_plugin: AssetPlugin) {
+    app.add_plugins((
+        MinimalPlugins.build().set(ScheduleRunnerPlugin::run_loop(
+            Duration::from_secs_f64(1.0 / 100.0),
+        )),
+        asset_plugin,
+        WindowPlugin {
+            primary_window: None,
+            exit_condition: ExitCondition::DontExit,
+            ..default()
+        },
+        BevyRenderPlugin {
+            render_creation: RenderCreation::Automatic(WgpuSettings {
+                backends: None,
+                ..default()
+            }),
+            ..default()
+        },
+        PanicHandlerPlugin,
+        TransformPlugin,
+        DiagnosticsPlugin,
+        StatesPlugin,
+        ScenePlugin,
+        GltfPlugin::default(),
+        PbrPlugin::default(),
+    ));
+
+    app.init_asset::<Image>(); // or add ImagePlugin
+}
@@ -2,16 +2,27 @@ use avian3d::{math::TAU, prelude::{Position, Rotation}};
 use bevy::{ecs::entity::MapEntities, platform::collections::HashMap, prelude::*};
 use bevy_rand::{global::GlobalEntropy, prelude::{Entropy, WyRand}, traits::ForkableRng};
 use leafwing_input_manager::prelude::ActionState;
-use lightyear::prelude::{server::{ControlledBy, Lifetime, SyncTarget}, DisableReplicateHierarchy, NetworkTarget, ServerReplicate, TickManager};
-use mygame_common::REPLICATION_GROUP_PREDICTED;
-use mygame_protocol::{component::{Bot, Health, Ship}, input::NetworkedInput};
+use lightyear::prelude::{server::{ControlledBy, Lifetime, SyncTarget}, DisableReplicateHierarchy, NetworkRelevanceMode, NetworkTarget, ServerReplicate, TickManager};
+use mygame_common::{
+    REPLICATION_GROUP_PREDICTED,
+    damage::{Armor, SHIP_MAX_HEALTH, SHIP_MAX_SHIELD},
+};
+use mygame_protocol::{component::{Bot, Health, Shield, Ship, Team}, input::NetworkedInput, message::{MatchRules, MatchSettings}};
 use rand_core::RngCore;
 
+use crate::{lobby::MatchPhase, teams::TeamAssignments};
+
 pub struct BotsPlugin;
 
 impl Plugin for BotsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(FixedUpdate, (spawn_bots, control_bots));
+        app.add_systems(
+            FixedUpdate,
+            (
+                spawn_bots.run_if(in_state(MatchPhase::InProgress)),
+                control_bots,
+            ),
+        );
     }
 }
 
@@ -24,35 +35,44 @@ struct BotAI {
 }
 
 const BOT_SPAWN_TICK_INTERVAL: u16 = 7;
-const MAX_BOTS: usize = 0;
 const SPAWN_RADIUS: f32 = 100.0;
 const CEILING_HEIGHT: f32 = 50.0;
 const TARGET_REACH_DISTANCE: f32 = 2.0;
 const MAX_CHASE_TICKS: u32 = 300;
+/// Bots go down a little quicker than players
+const BOT_ARMOR: f32 = 1.5;
 
 fn spawn_bots(
     mut commands: Commands,
     tick_manager: Res<TickManager>,
-    q_bots: Query<Entity, With<Bot>>,
+    q_bots: Query<Option<&Team>, With<Bot>>,
     mut global_rng: GlobalEntropy<WyRand>,
+    match_settings: Res<MatchSettings>,
+    match_rules: Res<MatchRules>,
+    team_assignments: Res<TeamAssignments>,
 ) {
     if *tick_manager.tick() % BOT_SPAWN_TICK_INTERVAL != 0 {
         return;
     }
     let bot_count = q_bots.iter().count();
-    if bot_count >= MAX_BOTS {
+    if bot_count >= match_settings.bot_count as usize {
         return;
     }
     
     let spawn_position = random_position_in_area(&mut global_rng, SPAWN_RADIUS, CEILING_HEIGHT);
     let initial_target = random_position_in_area(&mut global_rng, SPAWN_RADIUS, CEILING_HEIGHT);
     
-    commands.spawn((
+    let mut bot = commands.spawn((
         Ship,
         Health {
-            current: 6,
-            max: 6
+            current: SHIP_MAX_HEALTH,
+            max: SHIP_MAX_HEALTH,
+        },
+        Shield {
+            current: SHIP_MAX_SHIELD,
+            max: SHIP_MAX_SHIELD,
         },
+        Armor(BOT_ARMOR),
         Bot(global_rng.next_u64()),
         BotAI {
             target_location: initial_target,
@@ -76,11 +96,17 @@ fn spawn_bots(
             //     prediction: NetworkTarget::None,
             //     interpolation: NetworkTarget::All,
             // },
+            relevance_mode: NetworkRelevanceMode::InterestManagement,
             ..default()
         },
         DisableReplicateHierarchy,
         ActionState::<NetworkedInput>::default(),
     ));
+
+    // Bots even out the teams, counting the bots already on them
+    if match_rules.has_teams() {
+        bot.insert(team_assignments.smallest_team_with(q_bots.iter().flatten().copied()));
+    }
 }
 
 fn control_bots(
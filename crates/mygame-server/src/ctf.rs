@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use avian3d::prelude::{Position, Rotation};
+use bevy::prelude::*;
+use lightyear::prelude::{NetworkTarget, ServerReplicate, server::SyncTarget};
+use mygame_assets::FlagBase;
+use mygame_common::REPLICATION_GROUP_PREDICTED;
+use mygame_protocol::{
+    component::{Flag, FlagState},
+    message::{GameMode, MatchRules},
+};
+
+/// The server's side of capture the flag: putting the flags out, and sending dropped flags home.
+/// Pickups and captures are in mygame_common::flag so clients can predict them.
+pub struct CtfPlugin;
+
+impl Plugin for CtfPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            spawn_flags.after(TransformSystem::TransformPropagate),
+        )
+        .add_systems(FixedUpdate, return_dropped_flags);
+    }
+}
+
+/// How long a dropped flag lies around before it goes home on its own
+const FLAG_RETURN_DELAY: Duration = Duration::from_secs(20);
+
+#[derive(Component)]
+struct FlagReturnTimer(Timer);
+
+/// The level's flag bases appear once its scene spawns, after transforms are propagated so
+/// we know where they are
+fn spawn_flags(
+    mut commands: Commands,
+    match_rules: Res<MatchRules>,
+    q_flag_bases: Query<(&FlagBase, &GlobalTransform), Added<FlagBase>>,
+) {
+    if match_rules.mode != GameMode::CaptureTheFlag {
+        return;
+    }
+
+    for (flag_base, transform) in &q_flag_bases {
+        let base = transform.translation();
+        info!("placing the {} flag at {}", flag_base.0.name(), base);
+
+        commands.spawn((
+            Flag { base },
+            FlagState::AtBase,
+            flag_base.0,
+            Position(base),
+            Rotation::default(),
+            ServerReplicate {
+                group: REPLICATION_GROUP_PREDICTED,
+                sync: SyncTarget {
+                    prediction: NetworkTarget::All,
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+    }
+}
+
+fn return_dropped_flags(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_flags: Query<(
+        Entity,
+        &Flag,
+        &mut FlagState,
+        &mut Position,
+        Option<&mut FlagReturnTimer>,
+    )>,
+) {
+    for (entity, flag, mut flag_state, mut position, return_timer) in &mut q_flags {
+        match (*flag_state, return_timer) {
+            (FlagState::Dropped, Some(mut return_timer)) => {
+                if return_timer.0.tick(time.delta()).just_finished() {
+                    *flag_state = FlagState::AtBase;
+                    position.0 = flag.base;
+                    commands.entity(entity).remove::<FlagReturnTimer>();
+                }
+            }
+            (FlagState::Dropped, None) => {
+                commands
+                    .entity(entity)
+                    .insert(FlagReturnTimer(Timer::new(FLAG_RETURN_DELAY, TimerMode::Once)));
+            }
+            // Picked up or sent home by a teammate before it timed out
+            (_, Some(_)) => {
+                commands.entity(entity).remove::<FlagReturnTimer>();
+            }
+            (_, None) => {}
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    io::ErrorKind,
+    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+};
+
+use bevy::prelude::*;
+use lightyear::{
+    prelude::{
+        ServerConnectEvent, ServerDisconnectEvent,
+        server::{NetConfig, ServerTransport},
+    },
+    server::config::ServerConfig,
+};
+use mygame_assets::CurrentLevel;
+use mygame_protocol::{
+    discovery::{DISCOVERY_PORT, DiscoveryProbe, DiscoveryReply, decode, encode},
+    message::{MatchRules, MatchSettings},
+};
+
+use crate::{app::ServerMode, instances::MatchDirectory, lobby::MatchPhase};
+
+/// Answers clients on the local network looking for games, so they can be listed and joined
+/// without typing an address.
+///
+/// Only one socket can listen on DISCOVERY_PORT, so when several servers run on one machine,
+/// only the first one to start is found. A server process running several matches opens it
+/// once, in the first match, which answers each probe with a reply for every match in the
+/// MatchDirectory. Servers listening on loopback only answer probes from the same machine.
+pub struct DiscoveryPlugin {
+    udp_addr: Option<SocketAddr>,
+}
+
+impl DiscoveryPlugin {
+    /// Games are joined over UDP, so servers without a UDP transport aren't advertised
+    pub fn for_config(config: &ServerConfig) -> Self {
+        let udp_addr = config.net.iter().find_map(|net_config| {
+            let NetConfig::Netcode { io, .. } = net_config;
+
+            match &io.transport {
+                ServerTransport::UdpSocket(addr) => Some(*addr),
+                _ => None,
+            }
+        });
+
+        Self { udp_addr }
+    }
+}
+
+impl Plugin for DiscoveryPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(udp_addr) = self.udp_addr else {
+            return;
+        };
+
+        app.insert_resource(DiscoveryAddr(udp_addr))
+            .init_resource::<ConnectedPlayers>()
+            .init_resource::<OwnDiscoveryReply>()
+            .add_observer(count_connected_player)
+            .add_observer(count_disconnected_player)
+            .add_systems(Startup, open_discovery_socket)
+            .add_systems(
+                Update,
+                (
+                    update_discovery_reply,
+                    answer_probes.run_if(resource_exists::<DiscoverySocket>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// What a server is called in the LAN games list. Hosted games are named after their host.
+#[derive(Resource, Clone, Debug)]
+pub struct MatchName(pub String);
+
+/// Where the server listens for clients to join
+#[derive(Resource)]
+struct DiscoveryAddr(SocketAddr);
+
+#[derive(Resource)]
+struct DiscoverySocket(UdpSocket);
+
+/// What this match currently tells clients looking for games
+#[derive(Resource, Default)]
+struct OwnDiscoveryReply(Option<DiscoveryReply>);
+
+#[derive(Resource, Default)]
+struct ConnectedPlayers(u32);
+
+fn open_discovery_socket(
+    mut commands: Commands,
+    discovery_addr: Res<DiscoveryAddr>,
+    directory: Option<Res<MatchDirectory>>,
+) {
+    // The first match answers for the others
+    if directory.is_some_and(|directory| !directory.answers_discovery()) {
+        return;
+    }
+
+    // Broadcasts only reach sockets bound to every interface
+    let ip: IpAddr = if discovery_addr.0.ip().is_loopback() {
+        discovery_addr.0.ip()
+    } else {
+        Ipv4Addr::UNSPECIFIED.into()
+    };
+
+    let socket = match UdpSocket::bind((ip, DISCOVERY_PORT)) {
+        Ok(socket) => socket,
+        Err(e) => {
+            info!(
+                "not answering LAN discovery, port {} is unavailable: {}",
+                DISCOVERY_PORT, e
+            );
+            return;
+        }
+    };
+
+    if let Err(e) = socket.set_nonblocking(true) {
+        error!("unable to make the discovery socket non-blocking: {}", e);
+        return;
+    }
+
+    commands.insert_resource(DiscoverySocket(socket));
+}
+
+fn count_connected_player(
+    _trigger: Trigger<ServerConnectEvent>,
+    mut connected_players: ResMut<ConnectedPlayers>,
+) {
+    connected_players.0 += 1;
+}
+
+fn count_disconnected_player(
+    _trigger: Trigger<ServerDisconnectEvent>,
+    mut connected_players: ResMut<ConnectedPlayers>,
+) {
+    connected_players.0 = connected_players.0.saturating_sub(1);
+}
+
+fn update_discovery_reply(
+    mut own_reply: ResMut<OwnDiscoveryReply>,
+    discovery_addr: Res<DiscoveryAddr>,
+    connected_players: Res<ConnectedPlayers>,
+    match_name: Option<Res<MatchName>>,
+    server_mode: Res<ServerMode>,
+    match_phase: Res<State<MatchPhase>>,
+    current_level: Res<CurrentLevel>,
+    match_rules: Res<MatchRules>,
+    settings: Res<MatchSettings>,
+    directory: Option<Res<MatchDirectory>>,
+) {
+    // In the lobby, the host hasn't settled on the level and mode yet
+    let in_lobby = *match_phase.get() == MatchPhase::Lobby;
+    let (level, mode) = if in_lobby {
+        (settings.level, settings.rules.mode)
+    } else {
+        (current_level.0, match_rules.mode)
+    };
+
+    let name = match (&match_name, &*server_mode) {
+        (Some(match_name), _) => match_name.0.clone(),
+        (None, ServerMode::ClientHost(client_id)) => format!("Player {}'s game", client_id),
+        (None, _) => String::from("Server"),
+    };
+
+    let reply = DiscoveryReply {
+        name,
+        level,
+        mode,
+        players: connected_players.0,
+        udp_port: discovery_addr.0.port(),
+        in_lobby,
+    };
+
+    if let Some(directory) = directory {
+        directory.set_discovery_reply(reply.clone());
+    }
+
+    own_reply.0 = Some(reply);
+}
+
+fn answer_probes(
+    socket: Res<DiscoverySocket>,
+    own_reply: Res<OwnDiscoveryReply>,
+    directory: Option<Res<MatchDirectory>>,
+) {
+    let mut buffer = [0; 512];
+
+    loop {
+        let (len, from) = match socket.0.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => return,
+            Err(e) => {
+                warn!("unable to read from the discovery socket: {}", e);
+                return;
+            }
+        };
+
+        if decode::<DiscoveryProbe>(&buffer[..len]).is_none() {
+            continue;
+        }
+
+        // Each match is listed separately, under the UDP port it's joined on
+        let replies = match &directory {
+            Some(directory) => directory.discovery_replies(),
+            None => own_reply.0.iter().cloned().collect(),
+        };
+
+        for reply in replies {
+            let Some(bytes) = encode(&reply) else {
+                error!("unable to encode the discovery reply");
+                return;
+            };
+
+            if let Err(e) = socket.0.send_to(&bytes, from) {
+                warn!("unable to answer a discovery probe from {}: {}", from, e);
+            }
+        }
+    }
+}
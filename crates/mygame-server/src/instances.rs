@@ -0,0 +1,207 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use lightyear::prelude::{
+    NetworkTarget, ServerConnectEvent, ServerConnectionManager, ServerDisconnectEvent,
+};
+use mygame_protocol::{
+    discovery::DiscoveryReply,
+    message::{GameMode, MatchListing, ServerMatchList, UnorderedReliable},
+};
+
+/// Tells clients about the other matches running in the same server process.
+///
+/// Each match is its own server app, with its own world, level, physics and ports, running on
+/// its own thread. Rooms would only split up what gets replicated to whom, while the level,
+/// physics, match rules and match phase are all one per world, so matches sharing a world would
+/// need every one of those keyed by match.
+///
+/// The MatchDirectory is how clients find the matches. Every match sends its players the full
+/// list, with up to date player counts, and the first match answers LAN discovery for all of
+/// them. Servers running a single match have no directory, and this does nothing.
+pub struct InstancesPlugin;
+
+impl Plugin for InstancesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(count_connected_player)
+            .add_observer(count_disconnected_player)
+            .add_systems(
+                Update,
+                broadcast_match_list.run_if(resource_exists::<MatchDirectory>),
+            );
+    }
+}
+
+/// How often everyone is sent the match list, to keep player counts fresh
+const MATCH_LIST_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A match running in this server process
+pub struct MatchDirectoryEntry {
+    pub name: String,
+    pub mode: GameMode,
+    pub udp_port: u16,
+    pub webtransport_port: u16,
+    pub websocket_port: u16,
+    players: AtomicU32,
+    /// What the match last said about itself to LAN discovery, see DiscoveryPlugin
+    discovery_reply: Mutex<Option<DiscoveryReply>>,
+}
+
+impl MatchDirectoryEntry {
+    pub fn new(
+        name: String,
+        mode: GameMode,
+        udp_port: u16,
+        webtransport_port: u16,
+        websocket_port: u16,
+    ) -> Self {
+        Self {
+            name,
+            mode,
+            udp_port,
+            webtransport_port,
+            websocket_port,
+            players: AtomicU32::new(0),
+            discovery_reply: Mutex::new(None),
+        }
+    }
+}
+
+/// Every match in the server process, shared between their apps, and which one this app runs
+#[derive(Resource, Clone)]
+pub struct MatchDirectory {
+    entries: Arc<[MatchDirectoryEntry]>,
+    this: usize,
+}
+
+impl MatchDirectory {
+    /// A directory of `entries`, for the app running the first of them
+    pub fn new(entries: Vec<MatchDirectoryEntry>) -> Self {
+        Self {
+            entries: entries.into(),
+            this: 0,
+        }
+    }
+
+    /// The same directory, for the app running the match at `index`
+    pub fn for_match(&self, index: usize) -> Self {
+        Self {
+            entries: self.entries.clone(),
+            this: index,
+        }
+    }
+
+    fn players(&self) -> &AtomicU32 {
+        &self.entries[self.this].players
+    }
+
+    /// Only one socket can listen for discovery probes, so only the first match opens it
+    pub(crate) fn answers_discovery(&self) -> bool {
+        self.this == 0
+    }
+
+    pub(crate) fn set_discovery_reply(&self, reply: DiscoveryReply) {
+        let mut discovery_reply = self.entries[self.this]
+            .discovery_reply
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *discovery_reply = Some(reply);
+    }
+
+    /// Every match's latest discovery reply, skipping any that haven't started yet
+    pub(crate) fn discovery_replies(&self) -> Vec<DiscoveryReply> {
+        self.entries
+            .iter()
+            .filter_map(|entry| {
+                entry
+                    .discovery_reply
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .clone()
+            })
+            .collect()
+    }
+
+    fn to_message(&self) -> ServerMatchList {
+        ServerMatchList {
+            matches: self
+                .entries
+                .iter()
+                .map(|entry| MatchListing {
+                    name: entry.name.clone(),
+                    mode: entry.mode,
+                    players: entry.players.load(Ordering::Relaxed),
+                    udp_port: entry.udp_port,
+                    webtransport_port: entry.webtransport_port,
+                    websocket_port: entry.websocket_port,
+                })
+                .collect(),
+        }
+    }
+}
+
+fn count_connected_player(
+    trigger: Trigger<ServerConnectEvent>,
+    directory: Option<Res<MatchDirectory>>,
+    mut server: ResMut<ServerConnectionManager>,
+) {
+    let Some(directory) = directory else {
+        return;
+    };
+
+    directory.players().fetch_add(1, Ordering::Relaxed);
+
+    let client_id = trigger.event().client_id;
+
+    if let Err(e) = server.send_message_to_target::<UnorderedReliable, ServerMatchList>(
+        &directory.to_message(),
+        NetworkTarget::Single(client_id),
+    ) {
+        error!(
+            "unable to send the match list to client {}: {}",
+            client_id, e
+        );
+    }
+}
+
+fn count_disconnected_player(
+    _trigger: Trigger<ServerDisconnectEvent>,
+    directory: Option<Res<MatchDirectory>>,
+) {
+    let Some(directory) = directory else {
+        return;
+    };
+
+    // Never wraps, even if a disconnect somehow arrives without its connect
+    let _ = directory
+        .players()
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |players| {
+            players.checked_sub(1)
+        });
+}
+
+fn broadcast_match_list(
+    time: Res<Time>,
+    mut timer: Local<Option<Timer>>,
+    directory: Res<MatchDirectory>,
+    mut server: ResMut<ServerConnectionManager>,
+) {
+    let timer = timer.get_or_insert_with(|| Timer::new(MATCH_LIST_INTERVAL, TimerMode::Repeating));
+
+    if !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    if let Err(e) = server.send_message_to_target::<UnorderedReliable, ServerMatchList>(
+        &directory.to_message(),
+        NetworkTarget::All,
+    ) {
+        error!("unable to send the match list: {}", e);
+    }
+}
@@ -0,0 +1,374 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use avian3d::prelude::Position;
+use bevy::prelude::*;
+use lightyear::prelude::{
+    ClientId, ReplicationGroup, ServerConnectEvent, ServerDisconnectEvent,
+    server::{ReplicateToClient, RoomId, RoomManager},
+};
+use mygame_common::REPLICATION_GROUP_PREDICTED;
+use mygame_protocol::component::{Flag, FlagState, Player, Projectile, Ship};
+
+use crate::replication::Spectators;
+
+/// Only replicates ships and projectiles to the clients close enough to care about them.
+///
+/// The arena is split into a grid of square cells, each a lightyear room. Ships and projectiles
+/// are in the room for the cell they're in, and each client is in the rooms for every cell
+/// within its relevance radius of its ship. Clients without a ship (spectating, or waiting to
+/// respawn) get a radius around the middle of the arena that covers all of it, since their
+/// camera can go anywhere.
+///
+/// Only entities replicated with NetworkRelevanceMode::InterestManagement are affected.
+/// Flags and pickups go to everyone: there are few of them, and the HUD points at flags
+/// wherever they are. So do ships carrying a flag, through a room every client is in, or a
+/// flag far away would point at a ship the client doesn't have.
+///
+/// Ships with no other player's ship nearby are moved to a replication group that's sent less
+/// often. A group's send rate applies to every client it goes to, spectators and the ship's own
+/// pilot included, whose prediction only needs correcting now and then while nothing is around
+/// to disturb it. Ships rejoin REPLICATION_GROUP_PREDICTED as soon as another ship comes close,
+/// so anything that can interact stays consistent. Flag carriers always stay in it, along with
+/// their flag.
+pub struct InterestPlugin;
+
+impl Plugin for InterestPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ClientInterest>()
+            .add_observer(track_connected_client)
+            .add_observer(forget_disconnected_client)
+            .add_systems(
+                Update,
+                (
+                    update_entity_cells,
+                    update_client_interest,
+                    update_flag_carriers,
+                    update_ship_groups,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Width of a grid cell, in the XZ plane
+const CELL_SIZE: f32 = 25.0;
+/// How far from their ship a player receives other ships and projectiles
+const PLAYER_RELEVANCE_RADIUS: f32 = 60.0;
+/// How far from the middle of the arena a client without a ship receives them. Enough for the
+/// whole arena.
+const SPECTATOR_RELEVANCE_RADIUS: f32 = 150.0;
+/// Every client is in this room. Cell rooms never get this id, it would take a cell billions
+/// of units out.
+const GLOBAL_ROOM: RoomId = RoomId(u64::MAX);
+
+/// Ships this close to another player's ship are sent at the full rate
+const NEAR_RADIUS: f32 = 30.0;
+/// Ships with no other player's ship this close are sent less often. Further out than
+/// NEAR_RADIUS, so ships on the edge don't switch groups back and forth.
+const DISTANT_RADIUS: f32 = 40.0;
+const REPLICATION_GROUP_DISTANT_ID: u64 = 43;
+/// How often ships in the distant group are sent
+const DISTANT_SEND_INTERVAL: Duration = Duration::from_millis(200);
+
+/// The grid cell an interest managed entity was last put in
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+struct GridCell(IVec2);
+
+/// A ship in the distant replication group
+#[derive(Component)]
+struct Distant;
+
+/// A ship carrying a flag, in the GLOBAL_ROOM
+#[derive(Component)]
+struct FlagCarrier;
+
+/// The rooms each connected client is in
+#[derive(Resource, Default)]
+struct ClientInterest(HashMap<ClientId, HashSet<RoomId>>);
+
+fn cell_at(position: Vec3) -> IVec2 {
+    (position.xz() / CELL_SIZE).floor().as_ivec2()
+}
+
+fn cell_room(cell: IVec2) -> RoomId {
+    // Each coordinate gets half of the id, offset so negative cells don't collide
+    let x = (cell.x as i64 + i32::MAX as i64 + 1) as u64;
+    let z = (cell.y as i64 + i32::MAX as i64 + 1) as u64;
+    RoomId((x << 32) | z)
+}
+
+/// Every cell that overlaps a circle around the center
+fn cells_within(center: Vec3, radius: f32) -> HashSet<RoomId> {
+    let center = center.xz();
+    let min = ((center - radius) / CELL_SIZE).floor().as_ivec2();
+    let max = ((center + radius) / CELL_SIZE).floor().as_ivec2();
+
+    let mut rooms = HashSet::new();
+    for x in min.x..=max.x {
+        for z in min.y..=max.y {
+            let cell_min = Vec2::new(x as f32, z as f32) * CELL_SIZE;
+            let closest = center.clamp(cell_min, cell_min + CELL_SIZE);
+            if closest.distance(center) <= radius {
+                rooms.insert(cell_room(IVec2::new(x, z)));
+            }
+        }
+    }
+
+    rooms
+}
+
+fn track_connected_client(
+    trigger: Trigger<ServerConnectEvent>,
+    mut client_interest: ResMut<ClientInterest>,
+    mut room_manager: ResMut<RoomManager>,
+) {
+    let client_id = trigger.event().client_id;
+
+    client_interest.0.entry(client_id).or_default();
+    room_manager.add_client(client_id, GLOBAL_ROOM);
+}
+
+fn forget_disconnected_client(
+    trigger: Trigger<ServerDisconnectEvent>,
+    mut client_interest: ResMut<ClientInterest>,
+    mut room_manager: ResMut<RoomManager>,
+) {
+    let client_id = trigger.event().client_id;
+
+    room_manager.remove_client(client_id, GLOBAL_ROOM);
+
+    if let Some(rooms) = client_interest.0.remove(&client_id) {
+        for room in rooms {
+            room_manager.remove_client(client_id, room);
+        }
+    }
+}
+
+/// Moves ships and projectiles between rooms as they cross cells. Runs every frame, so
+/// projectiles are in a room before they're first replicated.
+fn update_entity_cells(
+    mut commands: Commands,
+    mut room_manager: ResMut<RoomManager>,
+    q_entities: Query<
+        (Entity, &Position, Option<&GridCell>),
+        (Or<(With<Ship>, With<Projectile>)>, With<ReplicateToClient>),
+    >,
+) {
+    for (entity, position, grid_cell) in &q_entities {
+        let cell = cell_at(position.0);
+
+        match grid_cell {
+            Some(grid_cell) if grid_cell.0 == cell => continue,
+            Some(grid_cell) => room_manager.remove_entity(entity, cell_room(grid_cell.0)),
+            None => {}
+        }
+
+        room_manager.add_entity(entity, cell_room(cell));
+        commands.entity(entity).insert(GridCell(cell));
+    }
+}
+
+fn update_client_interest(
+    mut client_interest: ResMut<ClientInterest>,
+    mut room_manager: ResMut<RoomManager>,
+    spectators: Res<Spectators>,
+    q_player_ships: Query<(&Player, &Position), With<Ship>>,
+) {
+    let ship_positions: HashMap<ClientId, Vec3> = q_player_ships
+        .iter()
+        .map(|(player, position)| (player.0, position.0))
+        .collect();
+
+    for (client_id, rooms) in client_interest.0.iter_mut() {
+        let wanted = match ship_positions.get(client_id) {
+            Some(position) if !spectators.0.contains(client_id) => {
+                cells_within(*position, PLAYER_RELEVANCE_RADIUS)
+            }
+            _ => cells_within(Vec3::ZERO, SPECTATOR_RELEVANCE_RADIUS),
+        };
+
+        for room in rooms.difference(&wanted) {
+            room_manager.remove_client(*client_id, *room);
+        }
+
+        for room in wanted.difference(rooms) {
+            room_manager.add_client(*client_id, *room);
+        }
+
+        *rooms = wanted;
+    }
+}
+
+/// Puts ships in the GLOBAL_ROOM while they carry a flag, and takes them out once they drop it
+/// or score
+fn update_flag_carriers(
+    mut commands: Commands,
+    mut room_manager: ResMut<RoomManager>,
+    q_flags: Query<&FlagState, With<Flag>>,
+    q_carriers: Query<Entity, With<FlagCarrier>>,
+    q_ships: Query<(), (With<Ship>, With<ReplicateToClient>)>,
+) {
+    let carriers: HashSet<Entity> = q_flags
+        .iter()
+        .filter_map(|flag_state| match flag_state {
+            FlagState::Carried(carrier) => Some(*carrier),
+            _ => None,
+        })
+        .filter(|carrier| q_ships.contains(*carrier))
+        .collect();
+
+    for ship in &q_carriers {
+        if !carriers.contains(&ship) {
+            room_manager.remove_entity(ship, GLOBAL_ROOM);
+            commands.entity(ship).remove::<FlagCarrier>();
+        }
+    }
+
+    for carrier in carriers {
+        if !q_carriers.contains(carrier) {
+            room_manager.add_entity(carrier, GLOBAL_ROOM);
+            commands.entity(carrier).insert(FlagCarrier);
+        }
+    }
+}
+
+fn distant_group() -> ReplicationGroup {
+    ReplicationGroup::new_id(REPLICATION_GROUP_DISTANT_ID).set_send_frequency(DISTANT_SEND_INTERVAL)
+}
+
+/// Moves ships into the distant group once no other player's ship is near, and back out as
+/// soon as one is
+fn update_ship_groups(
+    mut commands: Commands,
+    q_ships: Query<
+        (
+            Entity,
+            &Position,
+            Option<&Player>,
+            Has<Distant>,
+            Has<FlagCarrier>,
+        ),
+        (With<Ship>, With<ReplicateToClient>),
+    >,
+) {
+    let player_ships: Vec<(ClientId, Vec3)> = q_ships
+        .iter()
+        .filter_map(|(_, position, player, _, _)| Some((player?.0, position.0)))
+        .collect();
+
+    for (ship, position, player, distant, flag_carrier) in &q_ships {
+        let owner = player.map(|player| player.0);
+
+        // Bots have no pilot, so every player counts
+        let nearest = player_ships
+            .iter()
+            .filter(|(client_id, _)| Some(*client_id) != owner)
+            .map(|(_, other)| other.distance(position.0))
+            .fold(f32::INFINITY, f32::min);
+
+        if distant && (flag_carrier || nearest <= NEAR_RADIUS) {
+            commands
+                .entity(ship)
+                .insert(REPLICATION_GROUP_PREDICTED)
+                .remove::<Distant>();
+        } else if !distant && !flag_carrier && nearest > DISTANT_RADIUS {
+            commands.entity(ship).insert((distant_group(), Distant));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+
+    use mygame_common::ship::{ARENA_RADIUS, BOUNDARY_TOLERANCE};
+
+    use super::*;
+
+    #[test]
+    fn cells_below_zero_round_down() {
+        assert_eq!(cell_at(Vec3::new(0.1, 0.0, 0.1)), IVec2::new(0, 0));
+        assert_eq!(cell_at(Vec3::new(-0.1, 0.0, 0.1)), IVec2::new(-1, 0));
+        assert_eq!(cell_at(Vec3::new(0.1, 0.0, -0.1)), IVec2::new(0, -1));
+        assert_eq!(
+            cell_at(Vec3::new(-CELL_SIZE - 0.1, 0.0, -0.1)),
+            IVec2::new(-2, -1)
+        );
+    }
+
+    #[test]
+    fn cell_edges_belong_to_the_cell_above() {
+        assert_eq!(cell_at(Vec3::new(CELL_SIZE, 0.0, 0.0)), IVec2::new(1, 0));
+        assert_eq!(cell_at(Vec3::new(-CELL_SIZE, 0.0, 0.0)), IVec2::new(-1, 0));
+        assert_eq!(
+            cell_at(Vec3::new(CELL_SIZE - 0.01, 0.0, 0.0)),
+            IVec2::new(0, 0)
+        );
+    }
+
+    #[test]
+    fn height_does_not_change_the_cell() {
+        assert_eq!(
+            cell_at(Vec3::new(3.0, -50.0, 3.0)),
+            cell_at(Vec3::new(3.0, 50.0, 3.0))
+        );
+    }
+
+    #[test]
+    fn every_cell_has_its_own_room() {
+        let mut rooms = HashSet::new();
+
+        for x in -10..=10 {
+            for z in -10..=10 {
+                let room = cell_room(IVec2::new(x, z));
+                assert_ne!(room, GLOBAL_ROOM, "cell ({x}, {z}) is in the global room");
+                assert!(rooms.insert(room), "cell ({x}, {z}) shares a room");
+            }
+        }
+    }
+
+    #[test]
+    fn circles_include_cells_they_reach_the_edge_of() {
+        // Reaches exactly to x = 0 and z = 25, the lower edges of the cells beside and above
+        // it, and to x = -25 and z = 0, which are still in its own cell
+        let center = Vec3::new(-CELL_SIZE / 2.0, 0.0, CELL_SIZE / 2.0);
+        let rooms = cells_within(center, CELL_SIZE / 2.0);
+
+        let expected: HashSet<RoomId> = [(-1, 0), (0, 0), (-1, 1)]
+            .into_iter()
+            .map(|(x, z)| cell_room(IVec2::new(x, z)))
+            .collect();
+        assert_eq!(rooms, expected);
+    }
+
+    #[test]
+    fn circles_include_the_cell_they_are_in() {
+        let center = Vec3::new(-60.0, 10.0, -60.0);
+
+        assert!(cells_within(center, 0.0).contains(&cell_room(cell_at(center))));
+        assert!(
+            cells_within(center, PLAYER_RELEVANCE_RADIUS).contains(&cell_room(cell_at(center)))
+        );
+    }
+
+    #[test]
+    fn spectators_see_the_whole_arena() {
+        let spectator_rooms = cells_within(Vec3::ZERO, SPECTATOR_RELEVANCE_RADIUS);
+        let furthest = ARENA_RADIUS + BOUNDARY_TOLERANCE;
+
+        for step in 0..360 {
+            let angle = step as f32 / 360.0 * TAU;
+
+            for distance in [0.0, furthest / 2.0, furthest] {
+                let position = Vec3::new(angle.cos(), 0.0, angle.sin()) * distance;
+                assert!(
+                    spectator_rooms.contains(&cell_room(cell_at(position))),
+                    "a spectator can't see {position}"
+                );
+            }
+        }
+    }
+}
@@ -1,4 +1,14 @@
 pub mod app;
+pub mod discovery;
+pub mod instances;
 mod network;
+pub mod race;
+pub mod shutdown;
 mod replication;
 mod bots;
+mod ctf;
+mod interest;
+mod lobby;
+mod pickups;
+mod spawning;
+mod teams;
@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use lightyear::prelude::{
+    ClientId, FromClients, NetworkTarget, ServerConnectEvent, ServerConnectionManager,
+    ServerDisconnectEvent,
+};
+use mygame_assets::CurrentLevel;
+use mygame_protocol::message::{
+    ClientHostMatchSettings, ClientSetReady, Level, LobbyPlayer, MatchSettings, Reliable,
+    ServerLobby, ServerWelcome, ShutdownReason, UnorderedReliable,
+};
+
+use crate::{app::ServerMode, shutdown::BeginShutdown, teams::TeamAssignments};
+
+/// A hosted server starts in a lobby. Players connect and ready up while the host picks the
+/// level, mode, bots and time limit, and the match starts once everyone is ready. Dedicated
+/// servers skip the lobby and start straight away with the settings from their options.
+pub struct LobbyPlugin;
+
+impl Plugin for LobbyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LobbyPlayers>()
+            .init_resource::<MatchSettings>()
+            .add_observer(join_lobby)
+            .add_observer(leave_lobby)
+            .add_systems(
+                Update,
+                (receive_ready, receive_host_settings, start_when_ready)
+                    .chain()
+                    .run_if(in_state(MatchPhase::Lobby)),
+            )
+            .add_systems(OnEnter(MatchPhase::InProgress), start_match_timer)
+            .add_systems(
+                Update,
+                end_match_on_time_limit.run_if(in_state(MatchPhase::InProgress)),
+            );
+    }
+}
+
+/// Most bots the host may ask for
+const MAX_BOT_COUNT: u32 = 16;
+
+#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
+pub enum MatchPhase {
+    /// Waiting for players to ready up
+    Lobby,
+    #[default]
+    InProgress,
+}
+
+/// Everyone connected, and whether they're ready
+#[derive(Resource, Default)]
+struct LobbyPlayers(HashMap<ClientId, bool>);
+
+/// Counts down the match's time limit
+#[derive(Resource)]
+struct MatchTimer(Timer);
+
+fn host(server_mode: &ServerMode) -> Option<ClientId> {
+    match server_mode {
+        ServerMode::ClientHost(client_id) => Some(*client_id),
+        _ => None,
+    }
+}
+
+fn send_lobby(
+    server: &mut ServerConnectionManager,
+    lobby_players: &LobbyPlayers,
+    settings: &MatchSettings,
+    server_mode: &ServerMode,
+) {
+    let mut players: Vec<LobbyPlayer> = lobby_players
+        .0
+        .iter()
+        .map(|(id, ready)| LobbyPlayer {
+            id: *id,
+            ready: *ready,
+        })
+        .collect();
+    // Listed the same way every time
+    players.sort_by_key(|player| player.id.to_bits());
+
+    let lobby = ServerLobby {
+        host: host(server_mode),
+        players,
+        settings: *settings,
+    };
+
+    if let Err(e) =
+        server.send_message_to_target::<Reliable, ServerLobby>(&lobby, NetworkTarget::All)
+    {
+        error!("unable to send the lobby: {}", e);
+    }
+}
+
+fn join_lobby(
+    trigger: Trigger<ServerConnectEvent>,
+    mut lobby_players: ResMut<LobbyPlayers>,
+    settings: Res<MatchSettings>,
+    server_mode: Res<ServerMode>,
+    match_phase: Res<State<MatchPhase>>,
+    mut server: ResMut<ServerConnectionManager>,
+) {
+    lobby_players.0.insert(trigger.event().client_id, false);
+
+    if *match_phase.get() == MatchPhase::Lobby {
+        send_lobby(&mut server, &lobby_players, &settings, &server_mode);
+    }
+}
+
+fn leave_lobby(
+    trigger: Trigger<ServerDisconnectEvent>,
+    mut lobby_players: ResMut<LobbyPlayers>,
+    settings: Res<MatchSettings>,
+    server_mode: Res<ServerMode>,
+    match_phase: Res<State<MatchPhase>>,
+    mut server: ResMut<ServerConnectionManager>,
+) {
+    lobby_players.0.remove(&trigger.event().client_id);
+
+    if *match_phase.get() == MatchPhase::Lobby {
+        send_lobby(&mut server, &lobby_players, &settings, &server_mode);
+    }
+}
+
+fn receive_ready(
+    mut ev_client_set_ready: ResMut<Events<FromClients<ClientSetReady>>>,
+    mut lobby_players: ResMut<LobbyPlayers>,
+    settings: Res<MatchSettings>,
+    server_mode: Res<ServerMode>,
+    mut server: ResMut<ServerConnectionManager>,
+) {
+    let mut changed = false;
+
+    for ev in ev_client_set_ready.drain() {
+        if let Some(ready) = lobby_players.0.get_mut(&ev.from) {
+            *ready = ev.message.0;
+            changed = true;
+        }
+    }
+
+    if changed {
+        send_lobby(&mut server, &lobby_players, &settings, &server_mode);
+    }
+}
+
+fn receive_host_settings(
+    mut ev_host_match_settings: ResMut<Events<FromClients<ClientHostMatchSettings>>>,
+    lobby_players: Res<LobbyPlayers>,
+    mut settings: ResMut<MatchSettings>,
+    server_mode: Res<ServerMode>,
+    mut server: ResMut<ServerConnectionManager>,
+) {
+    let mut changed = false;
+
+    for ev in ev_host_match_settings.drain() {
+        if Some(ev.from) != host(&server_mode) {
+            info!(
+                "Client {} tried to change the match settings without hosting. Ignoring.",
+                ev.from
+            );
+            continue;
+        }
+
+        let mut new_settings = ev.message.0;
+        new_settings.bot_count = new_settings.bot_count.min(MAX_BOT_COUNT);
+        if !Level::PLAYABLE.contains(&new_settings.level) {
+            new_settings.level = settings.level;
+        }
+
+        *settings = new_settings;
+        changed = true;
+    }
+
+    if changed {
+        send_lobby(&mut server, &lobby_players, &settings, &server_mode);
+    }
+}
+
+/// Once everyone is ready, the host's settings become the match's and everyone is sent off to
+/// load the level
+fn start_when_ready(
+    mut commands: Commands,
+    lobby_players: Res<LobbyPlayers>,
+    settings: Res<MatchSettings>,
+    mut team_assignments: ResMut<TeamAssignments>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut next_match_phase: ResMut<NextState<MatchPhase>>,
+    mut server: ResMut<ServerConnectionManager>,
+) {
+    if lobby_players.0.is_empty() || !lobby_players.0.values().all(|ready| *ready) {
+        return;
+    }
+
+    info!("everyone is ready, starting the match");
+
+    // Nobody was put on a team when they connected, the mode hadn't been picked yet
+    if settings.rules.has_teams() {
+        for client_id in lobby_players.0.keys() {
+            let team = team_assignments.smallest_team();
+            team_assignments.0.insert(*client_id, team);
+        }
+    }
+
+    commands.insert_resource(settings.rules);
+    current_level.0 = settings.level;
+    next_match_phase.set(MatchPhase::InProgress);
+
+    if let Err(e) = server.send_message_to_target::<UnorderedReliable, ServerWelcome>(
+        &ServerWelcome {
+            current_level: settings.level,
+            match_rules: settings.rules,
+        },
+        NetworkTarget::All,
+    ) {
+        error!("unable to start the match: {}", e);
+    }
+}
+
+fn start_match_timer(mut commands: Commands, settings: Res<MatchSettings>) {
+    if let Some(time_limit) = settings.time_limit {
+        commands.insert_resource(MatchTimer(Timer::new(time_limit, TimerMode::Once)));
+    }
+}
+
+/// When time's up the match is over, and so is the server
+fn end_match_on_time_limit(
+    mut commands: Commands,
+    time: Res<Time>,
+    match_timer: Option<ResMut<MatchTimer>>,
+) {
+    let Some(mut match_timer) = match_timer else {
+        return;
+    };
+
+    if match_timer.0.tick(time.delta()).just_finished() {
+        info!("time limit reached, ending the match");
+        commands.trigger(BeginShutdown(ShutdownReason::MatchOver));
+    }
+}
@@ -4,9 +4,9 @@ use lightyear::prelude::{
     server::{NetworkingState, ServerCommandsExt, ServerConnection},
 };
 use mygame_assets::{CurrentLevel, LevelState};
-use mygame_protocol::message::{ClientHostRequestShutdown, Level};
+use mygame_protocol::message::{ClientHostRequestShutdown, Level, ShutdownReason};
 
-use crate::app::ServerMode;
+use crate::{app::ServerMode, lobby::MatchPhase, shutdown::BeginShutdown};
 
 pub struct NetworkPlugin;
 
@@ -21,12 +21,20 @@ impl Plugin for NetworkPlugin {
     }
 }
 
-fn start_server(mut commands: Commands, mut current_level: ResMut<CurrentLevel>) {
+fn start_server(
+    mut commands: Commands,
+    mut current_level: ResMut<CurrentLevel>,
+    match_phase: Res<State<MatchPhase>>,
+) {
     commands.start_server();
 
-    current_level.0 = Level::Example;
+    // A hosted server loads whichever level the host picks, once the lobby is ready
+    if *match_phase.get() == MatchPhase::InProgress {
+        current_level.0 = Level::Example;
+    }
 }
 
+/// The host leaving takes everyone else's game with it, so they get a warning first
 fn on_host_request_shutdown(
     mut commands: Commands,
     mut ev_host_request_shutdown: ResMut<Events<FromClients<ClientHostRequestShutdown>>>,
@@ -39,7 +47,7 @@ fn on_host_request_shutdown(
 
     for ev in ev_host_request_shutdown.drain() {
         if ev.from.to_bits() == owner.to_bits() {
-            commands.stop_server();
+            commands.trigger(BeginShutdown(ShutdownReason::HostLeft));
         }
     }
 }
@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use avian3d::prelude::{Position, Rotation};
+use bevy::prelude::*;
+use lightyear::prelude::{NetworkTarget, ServerReplicate, server::SyncTarget};
+use mygame_assets::PickupSpawn;
+use mygame_protocol::component::{Pickup, PickupAvailable};
+
+/// The server's side of pickups: putting them out at the level's pickup spawns, and bringing
+/// them back a while after they're collected. Collecting them is in mygame_common::pickup so
+/// clients can predict it.
+pub struct PickupsPlugin;
+
+impl Plugin for PickupsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            spawn_pickups.after(TransformSystem::TransformPropagate),
+        )
+        .add_systems(FixedUpdate, respawn_pickups);
+    }
+}
+
+/// How long a collected pickup takes to come back
+const PICKUP_RESPAWN_DELAY: Duration = Duration::from_secs(15);
+
+#[derive(Component)]
+struct PickupRespawnTimer(Timer);
+
+/// The level's pickup spawns appear once its scene spawns, after transforms are propagated so
+/// we know where they are
+fn spawn_pickups(
+    mut commands: Commands,
+    q_pickup_spawns: Query<(&PickupSpawn, &GlobalTransform), Added<PickupSpawn>>,
+) {
+    for (pickup_spawn, transform) in &q_pickup_spawns {
+        commands.spawn((
+            Pickup(pickup_spawn.0),
+            PickupAvailable(true),
+            Position(transform.translation()),
+            Rotation::default(),
+            ServerReplicate {
+                sync: SyncTarget {
+                    interpolation: NetworkTarget::All,
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+    }
+}
+
+fn respawn_pickups(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_pickups: Query<(Entity, &mut PickupAvailable, Option<&mut PickupRespawnTimer>)>,
+) {
+    for (entity, mut available, respawn_timer) in &mut q_pickups {
+        match (available.0, respawn_timer) {
+            (false, Some(mut respawn_timer)) => {
+                if respawn_timer.0.tick(time.delta()).just_finished() {
+                    available.0 = true;
+                    commands.entity(entity).remove::<PickupRespawnTimer>();
+                }
+            }
+            (false, None) => {
+                commands.entity(entity).insert(PickupRespawnTimer(Timer::new(
+                    PICKUP_RESPAWN_DELAY,
+                    TimerMode::Once,
+                )));
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use std::{collections::HashMap, fs, path::PathBuf, time::Duration};
+
+use avian3d::prelude::{PhysicsSet, Position};
+use bevy::prelude::*;
+use lightyear::prelude::{ClientId, NetworkTarget, ServerConnectEvent, ServerConnectionManager};
+use mygame_assets::{CHECKPOINT_RADIUS, Checkpoint};
+use mygame_protocol::{
+    component::{Player, Ship},
+    message::{
+        GameMode, MatchRules, RaceLeaderboardEntry, ServerCheckpointPassed, ServerRaceLeaderboard,
+        UnorderedReliable,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+/// Race mode: times each player's laps through the level's checkpoints, keeps a leaderboard,
+/// and remembers everyone's fastest lap across server restarts.
+///
+/// Passes are checked by the server alone, from each ship's Position one tick to the next, so
+/// a client can't claim a checkpoint it didn't fly through.
+pub struct RacePlugin;
+
+impl Plugin for RacePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RaceLeaderboard>()
+            .add_systems(Startup, load_race_records)
+            .add_observer(send_leaderboard_on_connect)
+            .add_systems(
+                FixedPostUpdate,
+                track_race_progress
+                    .after(PhysicsSet::StepSimulation)
+                    .run_if(racing),
+            );
+    }
+}
+
+/// Where personal bests are kept. Without it, they only last as long as the server.
+#[derive(Resource, Clone)]
+pub struct RaceRecordsPath(pub PathBuf);
+
+/// Everyone's fastest lap ever, by client id
+#[derive(Serialize, Deserialize, Default)]
+struct RaceRecords {
+    personal_bests: HashMap<u64, Duration>,
+}
+
+#[derive(Resource, Default)]
+struct RaceLeaderboard {
+    records: RaceRecords,
+    /// Laps completed and fastest lap this session
+    session: HashMap<ClientId, (u32, Option<Duration>)>,
+}
+
+impl RaceLeaderboard {
+    fn to_message(&self) -> ServerRaceLeaderboard {
+        let mut entries: Vec<RaceLeaderboardEntry> = self
+            .session
+            .iter()
+            .map(|(player, (laps, best_lap))| RaceLeaderboardEntry {
+                player: *player,
+                laps: *laps,
+                best_lap: *best_lap,
+                personal_best: self.records.personal_bests.get(&player.to_bits()).copied(),
+            })
+            .collect();
+
+        // Fastest first, then anyone who hasn't finished a lap yet
+        entries.sort_by_key(|entry| {
+            (entry.best_lap.is_none(), entry.best_lap, entry.player.to_bits())
+        });
+
+        ServerRaceLeaderboard { entries }
+    }
+}
+
+/// Where a ship is in its current lap
+#[derive(Component)]
+struct RaceProgress {
+    next_checkpoint: u32,
+    /// When the ship crossed the start line, None until it first does
+    lap_started: Option<Duration>,
+    previous_position: Vec3,
+}
+
+fn racing(match_rules: Res<MatchRules>) -> bool {
+    match_rules.mode == GameMode::Race
+}
+
+fn load_race_records(
+    mut leaderboard: ResMut<RaceLeaderboard>,
+    records_path: Option<Res<RaceRecordsPath>>,
+) {
+    let Some(records_path) = records_path else {
+        return;
+    };
+
+    // Nothing saved yet is fine
+    let Ok(contents) = fs::read_to_string(&records_path.0) else {
+        return;
+    };
+
+    match ron::de::from_str(&contents) {
+        Ok(records) => leaderboard.records = records,
+        Err(e) => warn!(
+            "Discarding race records in {:?} because they failed to parse: {}",
+            records_path.0, e
+        ),
+    }
+}
+
+fn save_race_records(records: &RaceRecords, records_path: &RaceRecordsPath) {
+    let contents = match ron::ser::to_string_pretty(records, ron::ser::PrettyConfig::default()) {
+        Ok(contents) => contents,
+        Err(e) => {
+            error!("Unable to serialize race records: {}", e);
+            return;
+        }
+    };
+
+    if let Some(dir) = records_path.0.parent() {
+        if let Err(e) = fs::create_dir_all(dir) {
+            error!("Unable to create {:?}: {}", dir, e);
+            return;
+        }
+    }
+
+    if let Err(e) = fs::write(&records_path.0, contents) {
+        error!("Unable to save race records to {:?}: {}", records_path.0, e);
+    }
+}
+
+fn send_leaderboard_on_connect(
+    trigger: Trigger<ServerConnectEvent>,
+    match_rules: Res<MatchRules>,
+    leaderboard: Res<RaceLeaderboard>,
+    mut server: ResMut<ServerConnectionManager>,
+) {
+    if match_rules.mode != GameMode::Race {
+        return;
+    }
+
+    let client_id = trigger.event().client_id;
+
+    if let Err(e) = server.send_message_to_target::<UnorderedReliable, ServerRaceLeaderboard>(
+        &leaderboard.to_message(),
+        NetworkTarget::Single(client_id),
+    ) {
+        error!("unable to send the race leaderboard to client {}: {}", client_id, e);
+    }
+}
+
+/// Whether moving from `from` to `to` went through the checkpoint, front to back
+fn passed_through(from: Vec3, to: Vec3, checkpoint: &GlobalTransform) -> bool {
+    let center = checkpoint.translation();
+    let forward = checkpoint.forward();
+
+    let from_distance = (from - center).dot(*forward);
+    let to_distance = (to - center).dot(*forward);
+
+    if from_distance >= 0.0 || to_distance < 0.0 {
+        return false;
+    }
+
+    let t = from_distance / (from_distance - to_distance);
+    let crossing = from.lerp(to, t);
+
+    crossing.distance(center) <= CHECKPOINT_RADIUS
+}
+
+fn track_race_progress(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut leaderboard: ResMut<RaceLeaderboard>,
+    records_path: Option<Res<RaceRecordsPath>>,
+    mut server: ResMut<ServerConnectionManager>,
+    q_checkpoints: Query<(&Checkpoint, &GlobalTransform)>,
+    mut q_ships: Query<(Entity, &Player, &Position, Option<&mut RaceProgress>), With<Ship>>,
+) {
+    let mut checkpoints: Vec<(u32, &GlobalTransform)> = q_checkpoints
+        .iter()
+        .map(|(checkpoint, transform)| (checkpoint.0, transform))
+        .collect();
+    checkpoints.sort_by_key(|(index, _)| *index);
+
+    if checkpoints.is_empty() {
+        return;
+    }
+
+    let checkpoint_count = checkpoints.len() as u32;
+    let now = time.elapsed();
+    let mut leaderboard_changed = false;
+
+    for (ship, player, position, progress) in &mut q_ships {
+        let Some(mut progress) = progress else {
+            commands.entity(ship).insert(RaceProgress {
+                next_checkpoint: 0,
+                lap_started: None,
+                previous_position: position.0,
+            });
+            leaderboard.session.entry(player.0).or_default();
+            leaderboard_changed = true;
+            continue;
+        };
+
+        let from = progress.previous_position;
+        progress.previous_position = position.0;
+
+        let (_, next_checkpoint) = checkpoints[progress.next_checkpoint as usize];
+        if !passed_through(from, position.0, next_checkpoint) {
+            continue;
+        }
+
+        let split = progress
+            .lap_started
+            .map(|lap_started| now - lap_started)
+            .unwrap_or_default();
+        let finished_lap = progress.next_checkpoint == 0 && progress.lap_started.is_some();
+        let (laps, best_lap) = leaderboard.session.entry(player.0).or_default();
+
+        if finished_lap {
+            *laps += 1;
+            *best_lap = Some(best_lap.map_or(split, |best| best.min(split)));
+            leaderboard_changed = true;
+
+            let personal_best = leaderboard
+                .records
+                .personal_bests
+                .entry(player.0.to_bits())
+                .or_insert(split);
+
+            if split <= *personal_best {
+                *personal_best = split;
+
+                if let Some(records_path) = &records_path {
+                    save_race_records(&leaderboard.records, records_path);
+                }
+            }
+        }
+
+        let lap = leaderboard.session.get(&player.0).map_or(0, |(laps, _)| *laps);
+
+        // Crossing the start line always starts a new lap
+        if progress.next_checkpoint == 0 {
+            progress.lap_started = Some(now);
+        }
+
+        if let Err(e) = server.send_message_to_target::<UnorderedReliable, ServerCheckpointPassed>(
+            &ServerCheckpointPassed {
+                checkpoint: progress.next_checkpoint,
+                checkpoint_count,
+                lap,
+                split,
+                lap_time: finished_lap.then_some(split),
+            },
+            NetworkTarget::Single(player.0),
+        ) {
+            error!("unable to tell client {} they passed a checkpoint: {}", player.0, e);
+        }
+
+        progress.next_checkpoint = (progress.next_checkpoint + 1) % checkpoint_count;
+    }
+
+    if leaderboard_changed {
+        if let Err(e) = server.send_message_to_target::<UnorderedReliable, ServerRaceLeaderboard>(
+            &leaderboard.to_message(),
+            NetworkTarget::All,
+        ) {
+            error!("unable to send the race leaderboard: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A checkpoint at the origin, facing -Z, so ships pass through it flying from +Z to -Z
+    fn checkpoint() -> GlobalTransform {
+        GlobalTransform::from(Transform::default())
+    }
+
+    #[test]
+    fn passing_front_to_back_counts() {
+        let from = Vec3::new(0.0, 0.0, 1.0);
+        let to = Vec3::new(0.0, 0.0, -1.0);
+
+        assert!(passed_through(from, to, &checkpoint()));
+    }
+
+    #[test]
+    fn passing_the_wrong_way_does_not_count() {
+        let from = Vec3::new(0.0, 0.0, -1.0);
+        let to = Vec3::new(0.0, 0.0, 1.0);
+
+        assert!(!passed_through(from, to, &checkpoint()));
+    }
+
+    #[test]
+    fn passing_outside_the_ring_does_not_count() {
+        let offset = Vec3::X * (CHECKPOINT_RADIUS + 1.0);
+        let from = offset + Vec3::Z;
+        let to = offset - Vec3::Z;
+
+        assert!(!passed_through(from, to, &checkpoint()));
+    }
+
+    #[test]
+    fn crossing_between_ticks_counts() {
+        // Neither position is inside the ring, but the ship went through it in between
+        let from = Vec3::new(CHECKPOINT_RADIUS * 2.0, 0.0, 5.0);
+        let to = Vec3::new(-CHECKPOINT_RADIUS * 2.0, 0.0, -5.0);
+
+        assert!(passed_through(from, to, &checkpoint()));
+    }
+
+    #[test]
+    fn stopping_short_does_not_count() {
+        let from = Vec3::new(0.0, 0.0, 5.0);
+        let to = Vec3::new(0.0, 0.0, 0.5);
+
+        assert!(!passed_through(from, to, &checkpoint()));
+    }
+}
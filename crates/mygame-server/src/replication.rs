@@ -1,13 +1,28 @@
-use avian3d::prelude::{Position, Rotation};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use avian3d::prelude::Position;
 use bevy::prelude::*;
 use leafwing_input_manager::prelude::ActionState;
 use lightyear::prelude::{
-    server::{ControlledBy, Lifetime, ServerCommandsExt, SyncTarget}, DisableReplicateHierarchy, FromClients, MessageSend, NetworkTarget, Replicating, ServerConnectEvent, ServerConnectionManager, ServerDisconnectEvent, ServerReplicate
+    server::{ControlledBy, Lifetime, ServerCommandsExt, SyncTarget}, ClientId, DisableReplicateHierarchy, FromClients, MessageSend, NetworkRelevanceMode, NetworkTarget, Replicating, ServerConnectEvent, ServerConnectionManager, ServerDisconnectEvent, ServerReplicate
+};
+use mygame_assets::{CurrentLevel, SpawnPoint};
+use mygame_common::{
+    REPLICATION_GROUP_PREDICTED,
+    damage::{DeathEvent, SHIP_MAX_HEALTH, SHIP_MAX_SHIELD},
+    ship::RESPAWN_DELAY,
 };
-use mygame_assets::CurrentLevel;
-use mygame_common::REPLICATION_GROUP_PREDICTED;
 use mygame_protocol::{
-    component::{Health, Player, Ship}, input::NetworkedInput, message::{ClientRequestRespawn, Level, ServerWelcome, UnorderedReliable}
+    component::{Health, Player, Shield, Ship, Team}, input::NetworkedInput, message::{ClientRequestLeave, ClientRequestRespawn, ClientRequestSpectate, Level, MatchRules, ServerWelcome, UnorderedReliable}
+};
+
+use crate::{
+    lobby::MatchPhase,
+    spawning::{choose_spawn_point, spawn_protection},
+    teams::TeamAssignments,
 };
 
 pub struct ReplicationPlugin;
@@ -15,46 +30,161 @@ impl Plugin for ReplicationPlugin {
     fn build(&self, app: &mut App) {
         app.add_observer(on_client_connect_success);
         app.add_observer(on_client_disconnect);
+        app.add_observer(on_ship_destroyed);
+
+        app.init_resource::<Spectators>();
+        app.init_resource::<RespawnCooldowns>();
+
+        app.add_systems(
+            Update,
+            (
+                on_client_request_leave,
+                on_client_request_spectate,
+                tick_respawn_cooldowns,
+                on_client_request_respawn,
+                despawn_abandoned_ships,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Clients that are connected but watching rather than playing. They own no Ship, and receive
+/// every ship as interpolated like any other client that doesn't control it.
+#[derive(Resource, Default)]
+pub struct Spectators(pub HashSet<ClientId>);
+
+/// Players whose ship was recently destroyed, and how long until they may respawn
+#[derive(Resource, Default)]
+struct RespawnCooldowns(HashMap<ClientId, Timer>);
+
+/// How long a disconnected client's ship is kept around in case they reconnect
+const RECONNECT_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+/// Marks a ship whose owner lost their connection. It's despawned once the timer runs out,
+/// unless the same client reconnects first and picks it back up. Clients that leave on purpose
+/// send ClientRequestLeave instead, and their ship goes straight away.
+#[derive(Component)]
+struct AwaitingReconnect(Timer);
+
+fn on_client_request_leave(
+    mut ev_client_request_leave: ResMut<Events<FromClients<ClientRequestLeave>>>,
+    mut commands: Commands,
+    mut spectators: ResMut<Spectators>,
+    q_players: Query<(Entity, &Player)>,
+) {
+    for ev in ev_client_request_leave.drain() {
+        info!("client ${} left the game", ev.from);
+        spectators.0.remove(&ev.from);
+
+        // The disconnect may already have been handled and the ship be awaiting a reconnect
+        for (ship, player) in &q_players {
+            if player.0 == ev.from {
+                commands.entity(ship).despawn_recursive();
+            }
+        }
+    }
+}
+
+fn on_client_request_spectate(
+    mut ev_client_request_spectate: ResMut<Events<FromClients<ClientRequestSpectate>>>,
+    mut commands: Commands,
+    mut spectators: ResMut<Spectators>,
+    q_players: Query<(Entity, &Player)>,
+) {
+    for ev in ev_client_request_spectate.drain() {
+        // Including a ship left over from before a reconnect
+        for (ship, player) in &q_players {
+            if player.0 == ev.from {
+                commands.entity(ship).despawn_recursive();
+            }
+        }
 
-        app.add_systems(Update, on_client_request_respawn);
+        if spectators.0.insert(ev.from) {
+            info!("client ${} is spectating", ev.from);
+        }
     }
 }
 
 fn on_client_request_respawn(
     mut ev_client_load_complete: ResMut<Events<FromClients<ClientRequestRespawn>>>,
     mut commands: Commands,
+    mut spectators: ResMut<Spectators>,
+    respawn_cooldowns: Res<RespawnCooldowns>,
     q_players: Query<&Player>,
+    team_assignments: Res<TeamAssignments>,
+    q_spawn_points: Query<&GlobalTransform, With<SpawnPoint>>,
+    q_ships: Query<(&Position, Option<&Team>), With<Ship>>,
 ) {
+    let spawn_points: Vec<GlobalTransform> = q_spawn_points.iter().copied().collect();
+    // Ships spawned below are added as they go, so players spawning on the same frame don't pick the same point
+    let mut ships: Vec<(Vec3, Option<Team>)> = q_ships
+        .iter()
+        .map(|(position, team)| (position.0, team.copied()))
+        .collect();
+
     for ev in ev_client_load_complete.drain() {
+        // The client doesn't offer to respawn until the delay is up, so this is someone jumping the gun
+        if respawn_cooldowns.0.contains_key(&ev.from) {
+            info!("Client {} requested a respawn before their respawn delay was up. Ignoring.", ev.from);
+            continue;
+        }
+
+        spectators.0.remove(&ev.from);
+
         let player_exists = q_players.iter().any(|player_id| player_id.0 == ev.from);
-        let player_start_position = Position(Vec3::new(0.0, 6.0, 0.0));
 
         if !player_exists {
-            commands.spawn((
-                player_start_position,
-                Rotation::default(),
+            let team = team_assignments.0.get(&ev.from).copied();
+
+            // Without teams, everyone is an enemy
+            let enemies: Vec<Vec3> = ships
+                .iter()
+                .filter(|(_, ship_team)| team.is_none() || *ship_team != team)
+                .map(|(position, _)| *position)
+                .collect();
+
+            let (position, rotation) = choose_spawn_point(&spawn_points, &enemies);
+            ships.push((position.0, team));
+
+            let mut ship = commands.spawn((
+                position,
+                rotation,
                 Player(ev.from),
                 Ship,
                 Health {
-                    current: 6,
-                    max: 6
+                    current: SHIP_MAX_HEALTH,
+                    max: SHIP_MAX_HEALTH,
+                },
+                Shield {
+                    current: SHIP_MAX_SHIELD,
+                    max: SHIP_MAX_SHIELD,
                 },
                 ServerReplicate {
                     group: REPLICATION_GROUP_PREDICTED,
                     controlled_by: ControlledBy {
                         target: NetworkTarget::Single(ev.from),
-                        lifetime: Lifetime::SessionBased,
+                        // Outlive the connection, see AwaitingReconnect
+                        lifetime: Lifetime::Persistent,
                     },
                     sync: SyncTarget {
                         prediction: NetworkTarget::Single(ev.from),
                         interpolation: NetworkTarget::AllExceptSingle(ev.from),
                     },
+                    // See InterestPlugin
+                    relevance_mode: NetworkRelevanceMode::InterestManagement,
                     ..default()
                 },
                 DisableReplicateHierarchy,
+                spawn_protection(),
             ));
+
+            if let Some(team) = team {
+                ship.insert(team);
+            }
         } else {
-            warn!(
+            // Expected when a client reconnects and resumes its old ship
+            info!(
                 "Client {} requested a respawn, but character already existed in world. Ignoring.",
                 ev.from
             );
@@ -67,12 +197,29 @@ fn on_client_connect_success(
     mut commands: Commands,
     mut server: ResMut<ServerConnectionManager>,
     current_level: Res<CurrentLevel>,
+    match_rules: Res<MatchRules>,
+    match_phase: Res<State<MatchPhase>>,
+    q_abandoned_ships: Query<(Entity, &Player), With<AwaitingReconnect>>,
 ) {
     let client_id = trigger.event().client_id;
 
+    for (ship, player) in &q_abandoned_ships {
+        if player.0 == client_id {
+            info!("client ${} reconnected, resuming their ship", client_id);
+            commands.entity(ship).remove::<AwaitingReconnect>();
+        }
+    }
+
+    // Players in the lobby are welcomed once the match starts
+    if *match_phase.get() == MatchPhase::Lobby {
+        info!("client ${} joined the lobby", client_id);
+        return;
+    }
+
     if let Err(e) = server.send_message_to_target::<UnorderedReliable, ServerWelcome>(
         &ServerWelcome {
             current_level: current_level.0,
+            match_rules: *match_rules,
         },
         NetworkTarget::Single(client_id),
     ) {
@@ -88,6 +235,58 @@ fn on_client_connect_success(
     info!("connected client ${}", trigger.event().client_id);
 }
 
-fn on_client_disconnect(trigger: Trigger<ServerDisconnectEvent>) {
-    info!("disconnected client ${}", trigger.event().client_id);
+fn on_client_disconnect(
+    trigger: Trigger<ServerDisconnectEvent>,
+    mut commands: Commands,
+    mut spectators: ResMut<Spectators>,
+    mut q_players: Query<(Entity, &Player, &mut ActionState<NetworkedInput>)>,
+) {
+    let client_id = trigger.event().client_id;
+    info!("disconnected client ${}", client_id);
+
+    spectators.0.remove(&client_id);
+
+    for (ship, player, mut action_state) in &mut q_players {
+        if player.0 == client_id {
+            // Fly straight and hold fire rather than repeating the last input forever
+            action_state.reset_all();
+            commands
+                .entity(ship)
+                .insert(AwaitingReconnect(Timer::new(RECONNECT_GRACE_PERIOD, TimerMode::Once)));
+        }
+    }
+}
+
+fn despawn_abandoned_ships(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_abandoned_ships: Query<(Entity, &Player, &mut AwaitingReconnect)>,
+) {
+    for (ship, player, mut awaiting_reconnect) in &mut q_abandoned_ships {
+        if awaiting_reconnect.0.tick(time.delta()).just_finished() {
+            info!("client ${} did not reconnect, despawning their ship", player.0);
+            commands.entity(ship).despawn_recursive();
+        }
+    }
+}
+
+/// The victim was already told, with ServerShipDestroyed, when the damage system destroyed their ship
+fn on_ship_destroyed(trigger: Trigger<DeathEvent>, mut respawn_cooldowns: ResMut<RespawnCooldowns>) {
+    let Some(victim) = trigger.victim else {
+        return;
+    };
+
+    respawn_cooldowns
+        .0
+        .insert(victim, Timer::new(RESPAWN_DELAY, TimerMode::Once));
+}
+
+fn tick_respawn_cooldowns(time: Res<Time>, mut respawn_cooldowns: ResMut<RespawnCooldowns>) {
+    if respawn_cooldowns.0.is_empty() {
+        return;
+    }
+
+    respawn_cooldowns
+        .0
+        .retain(|_, timer| !timer.tick(time.delta()).finished());
 }
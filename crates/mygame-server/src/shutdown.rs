@@ -0,0 +1,111 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use lightyear::prelude::{
+    NetworkTarget, ServerConnectionManager,
+    server::{NetworkingState, ServerCommandsExt},
+};
+use mygame_protocol::message::{Reliable, ServerShutdownNotice, ShutdownReason};
+
+/// Stops the server with some warning. Everyone is told why and how long they have left, and
+/// the server stops once the countdown is up, then exits.
+///
+/// Trigger BeginShutdown to start it. Whoever launches the server can also set the
+/// ShutdownSignal, for example on SIGTERM.
+pub struct ShutdownPlugin;
+
+impl Plugin for ShutdownPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShutdownSignal>()
+            .add_observer(begin_shutdown)
+            .add_systems(
+                Update,
+                (
+                    watch_shutdown_signal.run_if(not(resource_exists::<ShuttingDown>)),
+                    count_down_shutdown,
+                )
+                    .chain(),
+            )
+            .add_systems(OnExit(NetworkingState::Stopping), exit_after_shutdown);
+    }
+}
+
+/// How long clients are warned before the server stops
+const SHUTDOWN_COUNTDOWN: Duration = Duration::from_secs(5);
+
+/// Set from outside the app to shut it down. Every match in a server process shares one.
+#[derive(Resource, Clone, Default)]
+pub struct ShutdownSignal(pub Arc<AtomicBool>);
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BeginShutdown(pub ShutdownReason);
+
+/// Counts down to the server stopping
+#[derive(Resource)]
+struct ShuttingDown(Timer);
+
+fn begin_shutdown(
+    trigger: Trigger<BeginShutdown>,
+    mut commands: Commands,
+    shutting_down: Option<Res<ShuttingDown>>,
+    mut server: ResMut<ServerConnectionManager>,
+) {
+    // Already on the way out
+    if shutting_down.is_some() {
+        return;
+    }
+
+    let reason = trigger.event().0;
+    info!(
+        "shutting down in {} seconds: {}",
+        SHUTDOWN_COUNTDOWN.as_secs(),
+        reason.message()
+    );
+
+    if let Err(e) = server.send_message_to_target::<Reliable, ServerShutdownNotice>(
+        &ServerShutdownNotice {
+            reason,
+            seconds: SHUTDOWN_COUNTDOWN.as_secs() as u32,
+        },
+        NetworkTarget::All,
+    ) {
+        error!("unable to send the shutdown notice: {}", e);
+    }
+
+    commands.insert_resource(ShuttingDown(Timer::new(
+        SHUTDOWN_COUNTDOWN,
+        TimerMode::Once,
+    )));
+}
+
+fn watch_shutdown_signal(mut commands: Commands, shutdown_signal: Res<ShutdownSignal>) {
+    if shutdown_signal.0.load(Ordering::Relaxed) {
+        commands.trigger(BeginShutdown(ShutdownReason::ServerStopping));
+    }
+}
+
+fn count_down_shutdown(
+    mut commands: Commands,
+    time: Res<Time>,
+    shutting_down: Option<ResMut<ShuttingDown>>,
+) {
+    let Some(mut shutting_down) = shutting_down else {
+        return;
+    };
+
+    if shutting_down.0.tick(time.delta()).just_finished() {
+        commands.stop_server();
+    }
+}
+
+fn exit_after_shutdown(mut commands: Commands, shutting_down: Option<Res<ShuttingDown>>) {
+    if shutting_down.is_some() {
+        commands.send_event(AppExit::Success);
+    }
+}
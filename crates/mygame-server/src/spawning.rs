@@ -0,0 +1,67 @@
+use avian3d::prelude::{Position, Rotation};
+use bevy::prelude::*;
+use mygame_common::ship::SPAWN_PROTECTION_DURATION;
+use mygame_protocol::component::SpawnProtection;
+
+pub struct SpawningPlugin;
+
+impl Plugin for SpawningPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, expire_spawn_protection);
+    }
+}
+
+/// Where ships spawn when the level has no spawn points
+const FALLBACK_SPAWN_POSITION: Vec3 = Vec3::new(0.0, 6.0, 0.0);
+
+/// Counts down the SpawnProtection on a ship. Server only, clients just see SpawnProtection go away.
+#[derive(Component)]
+struct SpawnProtectionTimer(Timer);
+
+/// Everything a newly spawned ship needs to start out protected
+pub(crate) fn spawn_protection() -> impl Bundle {
+    (
+        SpawnProtection,
+        SpawnProtectionTimer(Timer::new(SPAWN_PROTECTION_DURATION, TimerMode::Once)),
+    )
+}
+
+/// Picks the spawn point furthest from its nearest enemy, so nobody spawns on top of,
+/// or right in front of, someone else
+pub(crate) fn choose_spawn_point(
+    spawn_points: &[GlobalTransform],
+    enemies: &[Vec3],
+) -> (Position, Rotation) {
+    let furthest = spawn_points.iter().max_by(|a, b| {
+        let nearest_enemy = |spawn_point: &GlobalTransform| {
+            enemies
+                .iter()
+                .map(|enemy| enemy.distance_squared(spawn_point.translation()))
+                .fold(f32::INFINITY, f32::min)
+        };
+
+        nearest_enemy(a).total_cmp(&nearest_enemy(b))
+    });
+
+    match furthest {
+        Some(spawn_point) => {
+            let (_, rotation, translation) = spawn_point.to_scale_rotation_translation();
+            (Position(translation), Rotation(rotation))
+        }
+        None => (Position(FALLBACK_SPAWN_POSITION), Rotation::default()),
+    }
+}
+
+fn expire_spawn_protection(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_protected: Query<(Entity, &mut SpawnProtectionTimer)>,
+) {
+    for (ship, mut timer) in &mut q_protected {
+        if timer.0.tick(time.delta()).just_finished() {
+            commands
+                .entity(ship)
+                .remove::<(SpawnProtection, SpawnProtectionTimer)>();
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use lightyear::prelude::{
+    ClientId, NetworkTarget, ServerConnectEvent, ServerConnectionManager, ServerDisconnectEvent,
+};
+use mygame_common::{damage::DeathEvent, flag::FlagCaptured};
+use mygame_protocol::{
+    component::{Player, Ship, Team},
+    message::{GameMode, MatchRules, ServerTeamScores, UnorderedReliable},
+};
+
+/// Puts players on teams as they connect, when the match rules call for them, and keeps score
+pub struct TeamsPlugin;
+
+impl Plugin for TeamsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TeamAssignments>()
+            .init_resource::<TeamScores>()
+            .add_observer(assign_team_on_connect)
+            .add_observer(unassign_team_on_disconnect)
+            .add_observer(score_kill)
+            .add_observer(score_capture);
+    }
+}
+
+/// The team each connected client will spawn on
+#[derive(Resource, Default)]
+pub(crate) struct TeamAssignments(pub HashMap<ClientId, Team>);
+
+impl TeamAssignments {
+    /// Whichever team has fewer players, Red on a tie
+    pub(crate) fn smallest_team(&self) -> Team {
+        self.smallest_team_with(std::iter::empty())
+    }
+
+    /// Whichever team has fewer players, also counting `others` such as bots, Red on a tie
+    pub(crate) fn smallest_team_with(&self, others: impl IntoIterator<Item = Team>) -> Team {
+        let others: Vec<Team> = others.into_iter().collect();
+
+        Team::ALL
+            .into_iter()
+            .min_by_key(|team| {
+                self.0
+                    .values()
+                    .chain(&others)
+                    .filter(|assigned| *assigned == team)
+                    .count()
+            })
+            .unwrap_or(Team::Red)
+    }
+}
+
+#[derive(Resource, Default)]
+struct TeamScores(ServerTeamScores);
+
+impl TeamScores {
+    /// Give a team a point and tell everyone
+    fn score(&mut self, team: Team, server: &mut ServerConnectionManager) {
+        match team {
+            Team::Red => self.0.red += 1,
+            Team::Blue => self.0.blue += 1,
+        }
+
+        if let Err(e) = server
+            .send_message_to_target::<UnorderedReliable, ServerTeamScores>(&self.0, NetworkTarget::All)
+        {
+            error!("unable to send team scores: {}", e);
+        }
+    }
+}
+
+fn assign_team_on_connect(
+    trigger: Trigger<ServerConnectEvent>,
+    match_rules: Res<MatchRules>,
+    mut team_assignments: ResMut<TeamAssignments>,
+    team_scores: Res<TeamScores>,
+    mut server: ResMut<ServerConnectionManager>,
+    q_ships: Query<(Option<&Player>, &Team), With<Ship>>,
+) {
+    if !match_rules.has_teams() {
+        return;
+    }
+
+    let client_id = trigger.event().client_id;
+
+    // Ships still on a team without an assigned player: bots, and ships left behind by players
+    // who may yet reconnect to them
+    let unassigned_ships = q_ships
+        .iter()
+        .filter(|(player, _)| {
+            player.is_none_or(|player| !team_assignments.0.contains_key(&player.0))
+        })
+        .map(|(_, team)| *team);
+
+    // Someone reconnecting to a ship they left behind stays on that ship's team
+    let team = q_ships
+        .iter()
+        .find(|(player, _)| player.is_some_and(|player| player.0 == client_id))
+        .map(|(_, team)| *team)
+        .unwrap_or_else(|| team_assignments.smallest_team_with(unassigned_ships));
+
+    info!("client ${} is on team {}", client_id, team.name());
+    team_assignments.0.insert(client_id, team);
+
+    if let Err(e) = server.send_message_to_target::<UnorderedReliable, ServerTeamScores>(
+        &team_scores.0,
+        NetworkTarget::Single(client_id),
+    ) {
+        error!("unable to send team scores to client {}: {}", client_id, e);
+    }
+}
+
+fn unassign_team_on_disconnect(
+    trigger: Trigger<ServerDisconnectEvent>,
+    mut team_assignments: ResMut<TeamAssignments>,
+) {
+    team_assignments.0.remove(&trigger.event().client_id);
+}
+
+fn score_kill(
+    trigger: Trigger<DeathEvent>,
+    match_rules: Res<MatchRules>,
+    mut team_scores: ResMut<TeamScores>,
+    mut server: ResMut<ServerConnectionManager>,
+) {
+    if match_rules.mode != GameMode::TeamDeathmatch {
+        return;
+    }
+
+    // Friendly fire kills, and kills by anything without a team, don't count
+    let Some(killer_team) = trigger.killer_team else {
+        return;
+    };
+
+    if trigger.victim_team == Some(killer_team) {
+        return;
+    }
+
+    team_scores.score(killer_team, &mut server);
+}
+
+fn score_capture(
+    trigger: Trigger<FlagCaptured>,
+    mut team_scores: ResMut<TeamScores>,
+    mut server: ResMut<ServerConnectionManager>,
+) {
+    info!("team {} captured the flag", trigger.team.name());
+    team_scores.score(trigger.team, &mut server);
+}